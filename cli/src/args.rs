@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+
+use calc_core::format::Format;
+use calc_core::rational::{Rounding, MAX_PLACES};
+
+pub const HELP: &str = "\
+Usage: cli [OPTIONS] [SCRIPT]
+
+Evaluates SCRIPT, or the lines piped to stdin, one line at a time. Without
+either an interactive prompt is started.
+
+Options:
+  -e, --eval EXPR       Evaluate EXPR and exit, can be given more than once
+      --float           Show results as floats, like the `f#` flag
+      --precision N     Show results with N decimal places, like `N#`
+      --debug           Print the tokens of every line, like `d#`
+      --format FORMAT   `text` (the default) or `json`, one object per line
+      --vars FILE       Load and save variables in FILE
+      --no-color        Don't color errors
+  -h, --help            Print this help
+  -V, --version         Print the version
+
+Environment:
+  CALC_HISTSIZE         Lines of history the prompt keeps, 1000 by default
+                        and none with 0
+  NO_COLOR              Don't color errors, like --no-color
+  XDG_CONFIG_HOME       Where the rust_calc directory with the history and
+                        the variables is kept, ~/.config by default
+
+Exit status is 0 on success, 1 when an expression fails and 2 when the
+arguments or the input can't be used.";
+
+/// What the command line asks for.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    Version,
+    Run(Args),
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// Expressions given with `-e`, in order.
+    pub eval: Vec<String>,
+    pub script: Option<PathBuf>,
+    /// Output format, `None` shows results exactly.
+    pub format: Option<Format>,
+    pub debug: bool,
+    pub json: bool,
+    /// Variables file used instead of the one in the config directory.
+    pub vars: Option<PathBuf>,
+    pub no_color: bool,
+}
+
+/// Reads the arguments after the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut ret = Args::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        //`--precision=3` is the same as `--precision 3`
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{name} needs a value"))
+        };
+        match name {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-e" | "--eval" => ret.eval.push(value()?),
+            "--float" => ret.format = Some(Format::Float),
+            "--precision" => {
+                let places = value()?;
+                let places = places
+                    .parse()
+                    .ok()
+                    .filter(|x| *x <= MAX_PLACES)
+                    .ok_or_else(|| {
+                        format!("--precision needs 0 to {MAX_PLACES} places, not {places:?}")
+                    })?;
+                ret.format = Some(Format::Fixed(places, Rounding::default()));
+            }
+            "--debug" => ret.debug = true,
+            "--format" => match value()?.as_str() {
+                "text" => ret.json = false,
+                "json" => ret.json = true,
+                x => return Err(format!("unknown format {x:?}, use text or json")),
+            },
+            "--vars" => ret.vars = Some(PathBuf::from(value()?)),
+            "--no-color" => ret.no_color = true,
+            x if x.starts_with('-') && x != "-" => return Err(format!("unknown option {x}")),
+            _ if ret.script.is_some() => return Err(format!("unexpected argument {arg}")),
+            _ => ret.script = Some(PathBuf::from(arg.clone())),
+        }
+    }
+    Ok(Command::Run(ret))
+}
+
+mod test {
+    #[cfg(test)]
+    use super::*;
+
+    #[cfg(test)]
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        super::parse(args.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Ok(Command::Run(Args::default())));
+        assert_eq!(parse(&["-V"]), Ok(Command::Version));
+        assert_eq!(parse(&["--float", "--help"]), Ok(Command::Help));
+        let Ok(Command::Run(args)) = parse(&["-e", "1+1", "--eval=2", "--precision", "3"]) else {
+            panic!("not a run");
+        };
+        assert_eq!(args.eval, vec!["1+1", "2"]);
+        assert_eq!(args.format, Some(Format::Fixed(3, Rounding::HalfEven)));
+        let Ok(Command::Run(args)) = parse(&["--format", "json", "--vars", "v.json", "a.calc"])
+        else {
+            panic!("not a run");
+        };
+        assert!(args.json);
+        assert_eq!(args.vars, Some(PathBuf::from("v.json")));
+        assert_eq!(args.script, Some(PathBuf::from("a.calc")));
+        assert_eq!(parse(&["-e"]), Err(String::from("-e needs a value")));
+        assert_eq!(
+            parse(&["--precision", "99999999"]),
+            Err(String::from(
+                "--precision needs 0 to 1000 places, not \"99999999\""
+            ))
+        );
+        assert!(parse(&["--precision=-1"]).is_err());
+        assert!(parse(&["--precision", "1000"]).is_ok());
+        assert_eq!(
+            parse(&["--nope"]),
+            Err(String::from("unknown option --nope"))
+        );
+        assert_eq!(
+            parse(&["--format", "xml"]),
+            Err(String::from("unknown format \"xml\", use text or json"))
+        );
+        assert_eq!(
+            parse(&["a", "b"]),
+            Err(String::from("unexpected argument b"))
+        );
+    }
+}
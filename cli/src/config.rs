@@ -0,0 +1,15 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Directory the CLI keeps its files in, `~/.config/rust_calc` on Linux.
+pub fn dir() -> Option<PathBuf> {
+    let var = |name| {
+        env::var_os(name)
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = var("XDG_CONFIG_HOME")
+        .or_else(|| var("APPDATA"))
+        .or_else(|| var("HOME").map(|x| x.join(".config")))?;
+    Some(base.join("rust_calc"))
+}
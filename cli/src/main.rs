@@ -1,24 +1,276 @@
+use std::env;
 use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process;
 
 use calc_core::{
-    parser::{KnownLiterals, Parse},
-    tokenizer::{dbg_tokenize, pre_tokenize, tokenize},
+    error::Span,
+    format::Format,
+    integer::WordSize,
+    parser::{KnownLiterals, Literal, Statement},
+    quantity::Value,
+    tokenizer::{dbg_tokenize, pre_tokenize, tokenize, TokenizerOptions},
 };
 
-use crate::term::Term;
+use crate::args::{Args, Command};
+use crate::session::Session;
+use crate::term::{Term, DEFAULT_HISTORY_SIZE};
 
 #[macro_use]
 mod term;
+mod args;
+mod config;
+mod script;
+mod session;
+
+// Marker line pointing at the part of `line` the error refers to.
+fn caret(line: &str, e: &calc_core::Error) -> String {
+    let span = e.span();
+    let start = line.get(..span.start).unwrap_or(line).chars().count();
+    let len = line
+        .get(span.start..span.end)
+        .map(|x| x.chars().count())
+        .unwrap_or_default();
+    format!("{}{}", " ".repeat(start), "^".repeat(len.max(1)))
+}
+
+// `=` and the result in `format`, or `≈` for approximations. With a word
+// size hex, octal and binary show the bits, `0xff` rather than `-0x1`.
+fn show(o: &Value, format: Format, word: Option<WordSize>) -> Result<String, calc_core::Error> {
+    let bits = word.and_then(|x| WordSize::new(x.bits(), false));
+    if let (Format::Radix(_), Some(bits)) = (format, bits) {
+        return Ok(format!(
+            "={}",
+            bits.wrap(o.clone()).to_format_string(format)?
+        ));
+    }
+    if o.is_exact() {
+        return Ok(format!("={}", o.to_format_string(format)?));
+    }
+    //An approximation printed as a fraction would look exact
+    let format = match format {
+        Format::Exact | Format::Mixed | Format::Repeating => Format::Float,
+        format => format,
+    };
+    Ok(format!("≈{}", o.to_format_string(format)?))
+}
+
+// Applies a line with only flags. Only the settings given change, `#`
+// alone resets them all.
+fn update_flags(opts: &TokenizerOptions, format: &mut Format, word: &mut Option<WordSize>) {
+    if opts.format.is_none() && opts.word.is_none() {
+        (*format, *word) = (Format::default(), None);
+    }
+    *format = opts.format.unwrap_or(*format);
+    *word = opts.word.or(*word);
+}
+
+// The lines to run from `-e`, a script named on the command line or piped
+// to stdin, with the name errors are reported under.
+fn read_script(args: &Args) -> Result<Option<(String, String)>, String> {
+    if !args.eval.is_empty() {
+        return Ok(Some((String::from("-e"), args.eval.join("\n"))));
+    }
+    match &args.script {
+        Some(path) if path.as_os_str() != "-" => {
+            let name = path.display().to_string();
+            let source = fs::read_to_string(path).map_err(|e| format!("{name}: {e}"))?;
+            return Ok(Some((name, source)));
+        }
+        Some(_) => {}
+        None if io::stdin().is_terminal() => return Ok(None),
+        None => {}
+    }
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| format!("<stdin>: {e}"))?;
+    Ok(Some((String::from("<stdin>"), source)))
+}
+
+// `text` in red when `color` is set.
+fn red(text: &str, color: bool) -> String {
+    if color {
+        format!(
+            "{}{text}{}",
+            termion::color::Fg(termion::color::Red),
+            termion::color::Fg(termion::color::Reset)
+        )
+    } else {
+        text.to_owned()
+    }
+}
+
+// Runs a script with the variables of `--vars`, if given, and returns the
+// exit status.
+fn run_script(name: &str, source: &str, args: &Args) -> i32 {
+    let color = !args.no_color && env::var_os("NO_COLOR").is_none() && io::stderr().is_terminal();
+    //Without `--vars` scripts don't see the saved variables, so they give
+    //the same results on every machine
+    let mut session = args.vars.clone().map(Session::at);
+    let mut known_literals = match session.as_mut().map(Session::load).transpose() {
+        Ok(x) => x.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("{}", red(&format!("Error: {e}"), color));
+            return 2;
+        }
+    };
+    let options = script::Options {
+        format: args.format.unwrap_or_default(),
+        debug: args.debug,
+        json: args.json,
+    };
+    let status = match script::run(name, source, &mut known_literals, options) {
+        Ok(()) => 0,
+        //The error was printed with the other results
+        Err(_) if args.json => 1,
+        Err(e) => {
+            eprintln!("{}", red(&e.to_string(), color));
+            1
+        }
+    };
+    if let Some(Err(e)) = session.map(|x| x.save(&known_literals)) {
+        eprintln!(
+            "{}",
+            red(&format!("Error: couldn't save variables: {e}"), color)
+        );
+        return 2;
+    }
+    status
+}
+
+// Evaluates one line and prints the result or where it went wrong.
+fn evaluate(
+    term: &mut Term,
+    line: &str,
+    known_literals: &mut KnownLiterals,
+    opts: &TokenizerOptions,
+    (format, word): (Format, Option<WordSize>),
+    color: bool,
+) -> Result<(), Box<dyn Error>> {
+    let statement = Statement::new(tokenize(line), known_literals);
+    if let (true, Ok(statement)) = (opts.echo, &statement) {
+        term_writeln!(term, "interpreted as: {statement}")?;
+    }
+    let out = statement
+        .and_then(|x| x.run::<Value>(known_literals, (), opts.word.or(word)))
+        .and_then(|o| {
+            o.map(|o| show(&o, opts.format.unwrap_or(format), opts.word.or(word)))
+                .transpose()
+                .map_err(|e| e.with_span(Span::new(0, line.len())))
+        });
+    match out {
+        Ok(None) => {}
+        Ok(Some(o)) => {
+            term_writeln!(term, "{o}")?;
+        }
+        Err(e) => {
+            term_writeln!(term, "{}", red(&format!("Error: {e}"), color))?;
+            term_writeln!(term, " {line}")?;
+            term_writeln!(term, " {}", red(&caret(line, &e), color))?;
+        }
+    }
+    Ok(())
+}
+
+// Runs a `:` command on the variables, returns the lines to print.
+fn command(line: &str, known_literals: &mut KnownLiterals) -> Result<Vec<String>, String> {
+    let mut words = line.split_whitespace();
+    match words.next().unwrap_or_default() {
+        ":vars" => {
+            let mut names: Vec<&String> = known_literals.keys().collect();
+            names.sort();
+            let lines: Vec<String> = names
+                .into_iter()
+                .map(|name| match &known_literals[name] {
+                    Literal::Value(x) => match show(x, Format::default(), None) {
+                        //`=5` becomes `x = 5`
+                        Ok(out) => {
+                            let sign = out.chars().next().unwrap_or('=');
+                            format!("{name} {sign} {}", &out[sign.len_utf8()..])
+                        }
+                        Err(e) => format!("{name}: {e}"),
+                    },
+                    Literal::Function(f) => {
+                        format!("{name}({}) = {}", f.params.join(", "), f.body)
+                    }
+                })
+                .collect();
+            if lines.is_empty() {
+                return Ok(vec![String::from("No variables")]);
+            }
+            Ok(lines)
+        }
+        ":delete" | ":del" => {
+            let names: Vec<&str> = words.collect();
+            if names.is_empty() {
+                return Err(String::from(":delete needs the names to delete"));
+            }
+            if let Some(name) = names.iter().find(|x| !known_literals.contains_key(**x)) {
+                return Err(format!("Unknown variable \"{name}\""));
+            }
+            for name in names {
+                known_literals.remove(name);
+            }
+            Ok(Vec::new())
+        }
+        ":reset" => {
+            known_literals.clear();
+            Ok(Vec::new())
+        }
+        name => Err(format!(
+            "Unknown command \"{name}\", try :vars, :delete or :reset"
+        )),
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut term = Term::new();
-    // let mut new_line = true;
-    let mut known_literals = KnownLiterals::new();
-    if let Some(pipe) = term.read_pipe() {
-        let pipe = pipe.trim();
-        term_writeln!(term, "\r{}", tokenize(pipe).parse(&mut known_literals)?)?;
-        return Ok(());
+    let args = match args::parse(env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", args::HELP);
+            return Ok(());
+        }
+        Ok(Command::Version) => {
+            println!("cli {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("Error: {e}\nTry --help for more information");
+            process::exit(2);
+        }
+    };
+    match read_script(&args) {
+        Ok(Some((name, source))) => process::exit(run_script(&name, &source, &args)),
+        Ok(None) => repl(&args),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(2);
+        }
     }
+}
+
+fn repl(args: &Args) -> Result<(), Box<dyn Error>> {
+    let color = !args.no_color && env::var_os("NO_COLOR").is_none();
+    //`CALC_HISTSIZE=0` keeps no history
+    let history_size = env::var("CALC_HISTSIZE")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_SIZE);
+    let mut term = Term::new(history_size);
+    // let mut new_line = true;
+    let mut session = args.vars.clone().map_or_else(Session::new, Session::at);
+    let mut known_literals = match session.load() {
+        Ok(x) => x,
+        Err(e) => {
+            term_writeln!(term, "{}", red(&format!("Error: {e}"), color))?;
+            KnownLiterals::new()
+        }
+    };
+    //Set by a line with only flags, `x#`, and used when a line has none
+    let mut format = args.format.unwrap_or_default();
+    let mut word = None;
     term_write!(term, ">")?;
 
     loop {
@@ -32,22 +284,34 @@ fn main() -> Result<(), Box<dyn Error>> {
         };
         if let Some(line) = line {
             term_writeln!(term, "")?;
+            let has_flags = line.contains('#');
             let (line, opts) = pre_tokenize(&line);
-            let out = if opts.debug {
-                dbg_tokenize(line).parse(&mut known_literals)
-            } else {
-                tokenize(line).parse(&mut known_literals)
-            };
-            match out {
-                Ok(o) => {
-                    if opts.as_float {
-                        term_writeln!(term, "={}", o.to_float())?;
-                    } else {
-                        term_writeln!(term, "={o}")?;
+            if opts.debug || args.debug {
+                term_writeln!(term, "{}", dbg_tokenize(line))?;
+            }
+            if has_flags && line.trim().is_empty() {
+                update_flags(&opts, &mut format, &mut word);
+                term_write!(term, "\r>")?;
+                continue;
+            }
+            let before = known_literals.clone();
+            if line.trim_start().starts_with(':') {
+                match command(line, &mut known_literals) {
+                    Ok(lines) => {
+                        for x in lines {
+                            term_writeln!(term, "{x}")?;
+                        }
                     }
+                    Err(e) => term_writeln!(term, "{}", red(&format!("Error: {e}"), color))?,
                 }
-                Err(e) => {
-                    term_writeln!(term, "Error: {e}")?;
+            } else {
+                let settings = (format, word);
+                evaluate(&mut term, line, &mut known_literals, &opts, settings, color)?;
+            }
+            if known_literals != before {
+                if let Err(e) = session.save(&known_literals) {
+                    let e = format!("Error: couldn't save variables: {e}");
+                    term_writeln!(term, "{}", red(&e, color))?;
                 }
             }
             term_write!(term, "\r>")?;
@@ -56,3 +320,56 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+mod test {
+    #[cfg(test)]
+    use super::command;
+    #[cfg(test)]
+    use calc_core::{
+        parser::{KnownLiterals, Parse},
+        tokenizer::tokenize,
+    };
+
+    #[cfg(test)]
+    fn literals(lines: &[&str]) -> KnownLiterals {
+        let mut known_literals = KnownLiterals::new();
+        for s in lines {
+            tokenize(s).parse(&mut known_literals).unwrap();
+        }
+        known_literals
+    }
+
+    #[test]
+    fn test_vars() {
+        let mut known_literals = literals(&[]);
+        assert_eq!(
+            command(":vars", &mut known_literals),
+            Ok(vec![String::from("No variables")])
+        );
+        let mut known_literals =
+            literals(&["y = 5 km", "x = 1/3", "f(a, b) = a^2 + b", "z = sqrt 2"]);
+        assert_eq!(
+            command(":vars", &mut known_literals).unwrap(),
+            ["f(a, b) = a^2 + b", "x = 1/3", "y = 5000 m", "z = sqrt(2)"]
+        );
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut known_literals = literals(&["x = 1", "y = 2", "f(a) = a"]);
+        let before = known_literals.clone();
+        //One unknown name deletes none of them
+        assert_eq!(
+            command(":delete x nope", &mut known_literals),
+            Err(String::from("Unknown variable \"nope\""))
+        );
+        assert_eq!(known_literals, before);
+        assert!(command(":delete", &mut known_literals).is_err());
+        assert_eq!(known_literals, before);
+        assert_eq!(command(":del x f", &mut known_literals), Ok(Vec::new()));
+        assert_eq!(known_literals, literals(&["y = 2"]));
+        assert_eq!(command(":reset", &mut known_literals), Ok(Vec::new()));
+        assert!(known_literals.is_empty());
+        assert!(command(":nope", &mut known_literals).is_err());
+    }
+}
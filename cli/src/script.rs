@@ -0,0 +1,178 @@
+use std::fmt::Display;
+
+use calc_core::{
+    format::Format,
+    parser::{KnownLiterals, Statement},
+    quantity::Value,
+    report::Report,
+    tokenizer::{dbg_tokenize, pre_tokenize, tokenize},
+};
+
+use serde::Serialize;
+
+use crate::{show, update_flags};
+
+/// How a script is run, set from the command line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Options {
+    /// Format of lines without flags until a line with only flags changes it.
+    pub format: Format,
+    /// Prints the tokens of every line, like `d#`.
+    pub debug: bool,
+    /// Prints a `Report` for every line as JSON, with the `file` and `line`
+    /// it came from and the `column` of errors.
+    pub json: bool,
+}
+
+/// Where a script failed, shown as `file:line:column: message`.
+#[derive(Debug)]
+pub struct ScriptError {
+    name: String,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.name, self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+// A `Report` with the place in the script it came from.
+#[derive(Serialize)]
+struct Located<'a> {
+    #[serde(flatten)]
+    report: &'a Report,
+    file: &'a str,
+    line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+}
+
+// The line without its comment. A `#` at the start of the line or after a
+// space starts a comment, right after flags it ends them: `x#255`.
+fn strip_comment(line: &str) -> &str {
+    let mut prev: Option<char> = None;
+    for (i, c) in line.char_indices() {
+        if c == '#' && prev.is_none_or(char::is_whitespace) {
+            return &line[..i];
+        }
+        prev = Some(c);
+    }
+    line
+}
+
+/// Runs `source` a line at a time, the lines share their variables. Results
+/// are printed as they come, the first error stops the script.
+pub fn run(
+    name: &str,
+    source: &str,
+    known_literals: &mut KnownLiterals,
+    options: Options,
+) -> Result<(), ScriptError> {
+    let mut format = options.format;
+    let mut word = None;
+    for (number, full) in source.lines().enumerate() {
+        let line = strip_comment(full);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let has_flags = line.contains('#');
+        let (code, opts) = pre_tokenize(line);
+        if has_flags && code.trim().is_empty() {
+            update_flags(&opts, &mut format, &mut word);
+            continue;
+        }
+        if opts.debug || options.debug {
+            println!("{}", dbg_tokenize(code).replace('\r', ""));
+        }
+        let input = code.trim();
+        //Spans are relative to the input after the flags
+        let offset = line.len() - code.trim_start().len();
+        let fail = |start: usize, message: String| ScriptError {
+            name: name.to_owned(),
+            line: number + 1,
+            column: full.get(..offset + start).unwrap_or(full).chars().count() + 1,
+            message,
+        };
+        let word = opts.word.or(word);
+        let format = opts.format.unwrap_or(format);
+        if options.json {
+            let report = Report::new(input, known_literals, format, word);
+            let error = report
+                .error
+                .as_ref()
+                .map(|e| fail(e.span.start, e.message.clone()));
+            let out = Located {
+                report: &report,
+                file: name,
+                line: number + 1,
+                column: error.as_ref().map(|e| e.column),
+            };
+            let json = serde_json::to_string(&out).map_err(|e| fail(0, e.to_string()))?;
+            println!("{json}");
+            match error {
+                Some(e) => return Err(e),
+                None => continue,
+            }
+        }
+        let fail = |e: calc_core::Error| fail(e.span().start, e.to_string());
+        let statement = Statement::new(tokenize(input), known_literals).map_err(fail)?;
+        if opts.echo {
+            println!("interpreted as: {statement}");
+        }
+        let out = statement
+            .run::<Value>(known_literals, (), word)
+            .map_err(fail)?;
+        if let Some(o) = out {
+            let out = show(&o, format, word).map_err(fail)?;
+            //Exact results are printed without the `=`
+            println!("{}", out.strip_prefix('=').unwrap_or(&out));
+        }
+    }
+    Ok(())
+}
+
+mod test {
+    #[cfg(test)]
+    use super::{run, strip_comment, Options};
+    #[cfg(test)]
+    use calc_core::parser::KnownLiterals;
+
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(strip_comment("# comment"), "");
+        assert_eq!(strip_comment("x#255"), "x#255");
+        assert_eq!(strip_comment("1+1 # note"), "1+1 ");
+        assert_eq!(strip_comment("x# 255 # hex"), "x# 255 ");
+        assert_eq!(strip_comment("\t#tab"), "\t");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut known_literals = KnownLiterals::new();
+        //Columns count characters of the whole line, flags included
+        let e = run(
+            "a.calc",
+            "π = 3\n  x# π + 1/0",
+            &mut known_literals,
+            Options::default(),
+        )
+        .unwrap_err();
+        assert_eq!(e.to_string(), "a.calc:2:10: Division by zero");
+        //Lines after the error aren't run, the ones before keep their values
+        let source = "a = 2\nb = a / 0\nc = 3";
+        let e = run("b.calc", source, &mut known_literals, Options::default()).unwrap_err();
+        assert_eq!((e.line, e.column), (2, 5));
+        assert!(known_literals.contains_key("a"));
+        assert!(!known_literals.contains_key("b"));
+        assert!(!known_literals.contains_key("c"));
+    }
+}
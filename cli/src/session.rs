@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use calc_core::parser::{KnownLiterals, Literal};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const FILE_NAME: &str = "vars.json";
+
+// What is written to the file, sorted so the same variables always give
+// the same file.
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    literals: BTreeMap<String, Literal>,
+}
+
+/// Variables and user functions kept between runs.
+pub struct Session {
+    path: Option<PathBuf>,
+}
+
+impl Session {
+    /// The session in the config directory, nothing is kept if there is
+    /// none.
+    pub fn new() -> Self {
+        Self {
+            path: config::dir().map(|x| x.join(FILE_NAME)),
+        }
+    }
+
+    /// A session kept in `path` instead.
+    pub fn at(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    /// Reads the saved variables, a missing file is an empty session.
+    ///
+    /// A file that can't be read is left alone, the session stops saving so
+    /// it isn't overwritten.
+    pub fn load(&mut self) -> Result<KnownLiterals, Box<dyn Error>> {
+        let Some(path) = &self.path else {
+            return Ok(KnownLiterals::new());
+        };
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(KnownLiterals::new()),
+            Err(e) => return Err(self.give_up(e.into())),
+        };
+        match serde_json::from_str::<SessionFile>(&data) {
+            Ok(file) => Ok(file.literals.into_iter().collect()),
+            Err(e) => Err(self.give_up(e.into())),
+        }
+    }
+
+    fn give_up(&mut self, e: Box<dyn Error>) -> Box<dyn Error> {
+        let path = self.path.take().unwrap_or_default();
+        format!("couldn't load variables from {}: {e}", path.display()).into()
+    }
+
+    /// Writes the variables, through a temporary file so a crash can't
+    /// leave half of them behind.
+    pub fn save(&self, known_literals: &KnownLiterals) -> Result<(), Box<dyn Error>> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = SessionFile {
+            literals: known_literals
+                .iter()
+                .map(|(name, x)| (name.clone(), x.clone()))
+                .collect(),
+        };
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&file)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Session;
+    #[cfg(test)]
+    use calc_core::{
+        number::Number,
+        parser::{KnownLiterals, Literal, Parse},
+        tokenizer::tokenize,
+    };
+    #[cfg(test)]
+    use std::{env, fs, path::PathBuf, process};
+
+    // A fresh directory for one test, removed first in case a failed run
+    // left it behind.
+    #[cfg(test)]
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("calc-session-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join("config").join("vars.json");
+        let mut known_literals = KnownLiterals::new();
+        for s in ["x = 1/3", "y = 5 km", "f(a, b) = a^2 + b", "z = 2^(2^21)"] {
+            tokenize(s).parse(&mut known_literals).unwrap();
+        }
+        Session {
+            path: Some(path.clone()),
+        }
+        .save(&known_literals)
+        .unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+        let loaded = Session { path: Some(path) }.load().unwrap();
+        assert_eq!(loaded, known_literals);
+        //Approximations that overflowed are kept too
+        match &loaded["z"] {
+            Literal::Value(z) => {
+                assert_eq!(z.to_float(), f64::INFINITY);
+                assert!(!z.is_exact());
+            }
+            x => panic!("{x:?}"),
+        }
+        //Nothing saved yet is an empty session
+        let missing = dir.join("missing.json");
+        assert!(Session {
+            path: Some(missing)
+        }
+        .load()
+        .unwrap()
+        .is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let dir = temp_dir("corrupt");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vars.json");
+        fs::write(&path, "{\"literals\": {\"x\"").unwrap();
+        let mut session = Session {
+            path: Some(path.clone()),
+        };
+        let e = session.load().unwrap_err().to_string();
+        assert!(e.starts_with("couldn't load variables from"), "{e}");
+        //The file is kept for the user to look at
+        let mut known_literals = KnownLiterals::new();
+        tokenize("x = 2").parse(&mut known_literals).unwrap();
+        session.save(&known_literals).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"literals\": {\"x\"");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
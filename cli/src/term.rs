@@ -2,12 +2,19 @@ use io::IsTerminal;
 use io::Read;
 use io::Write;
 use std::fmt::Arguments;
+use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
 use termion::cursor::DetectCursorPos;
 use termion::raw::RawTerminal;
 
 use termion::raw::IntoRawMode;
 
+use crate::config;
+
+/// Lines kept in the history when no other size is set.
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
 type Buffer = [u8; 8];
 type StringResult = Result<Option<String>, Box<dyn std::error::Error>>;
 
@@ -68,10 +75,22 @@ impl StdoutOpt {
     }
 }
 
+// A reverse incremental search started with Ctrl-R.
+struct Search {
+    query: String,
+    //Index of the shown entry of `Term::history`
+    found: Option<usize>,
+    //The line before the search, brought back by Ctrl-G
+    saved_line: String,
+}
+
 pub struct Term {
     line: String,
     history: Vec<String>,
+    //Where the history is kept between sessions
+    history_path: Option<PathBuf>,
     max_hist_len: usize,
+    search: Option<Search>,
     hist_idx: usize,
     use_hist: bool,
     cur_pos: u16,
@@ -87,11 +106,15 @@ impl Drop for Term {
 }
 
 impl Term {
-    pub fn new() -> Self {
+    /// A prompt keeping the newest `history_size` lines of history.
+    pub fn new(history_size: usize) -> Self {
+        let history_path = config::dir().map(|x| x.join("history"));
         Self {
             line: String::new(),
-            history: Vec::new(),
-            max_hist_len: 10,
+            history: load_history(history_path.as_deref(), history_size),
+            history_path,
+            max_hist_len: history_size,
+            search: None,
             hist_idx: 0,
             use_hist: false,
             cur_pos: 0,
@@ -100,26 +123,20 @@ impl Term {
         }
     }
 
-    pub fn read_pipe(&mut self) -> Option<String> {
-        let stdin = io::stdin();
-        if stdin.is_terminal() {
-            None
-        } else {
-            let mut stdin = stdin.lock();
-
-            let mut line = String::new();
+    fn trim_history(&mut self) {
+        let extra = self.history.len().saturating_sub(self.max_hist_len);
+        self.history.drain(..extra);
+    }
 
-            // Could also `match` on the `Result` if you wanted to handle `Err`
-            while let Ok(n_bytes) = stdin.read_to_string(&mut line) {
-                if n_bytes == 0 {
-                    break;
-                }
-            }
-            if line.is_empty() {
-                None
-            } else {
-                Some(line)
-            }
+    fn save_history(&self) {
+        if let Some(path) = &self.history_path {
+            let mut data = self.history.join("\n");
+            data.push('\n');
+            //Like reading it, failing to save the history isn't an error
+            let _ = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(path, data));
         }
     }
 
@@ -135,21 +152,90 @@ impl Term {
         }
     }
 
+    // Newest entry at or before `start` containing the query.
+    fn find(&self, query: &str, start: usize) -> Option<usize> {
+        self.history
+            .get(..=start)
+            .and_then(|x| x.iter().rposition(|x| x.contains(query)))
+    }
+
+    // Shows the entry matching the search from `start` on, or keeps the
+    // last match if there is none.
+    fn search_from(&mut self, start: Option<usize>) {
+        let Some(search) = &self.search else { return };
+        let found = start.and_then(|start| self.find(&search.query, start));
+        if let (Some(found), Some(search)) = (found, &mut self.search) {
+            search.found = Some(found);
+            self.line = self.history[found].clone();
+            self.cur_pos = self.line.len() as u16;
+        }
+    }
+
+    // Typed characters go to the search query while searching.
+    fn handle_search_char(&mut self, ch: char) {
+        if let Some(search) = &mut self.search {
+            search.query.push(ch);
+            let start = search.found.or(self.history.len().checked_sub(1));
+            self.search_from(start);
+        }
+    }
+
+    // Searching keys, false for keys that end the search.
+    fn handle_search_key(&mut self, ch: char) -> bool {
+        let Some(search) = &mut self.search else {
+            return false;
+        };
+        match ch {
+            //Ctrl-R, the next older match
+            '\u{12}' => {
+                if let Some(found) = search.found {
+                    self.search_from(found.checked_sub(1));
+                }
+            }
+            //Backspace, searches again from the newest entry
+            '\u{7f}' => {
+                search.query.pop();
+                search.found = None;
+                let start = self.history.len().checked_sub(1);
+                self.search_from(start);
+            }
+            //Ctrl-G, gives up and brings the line back
+            '\u{7}' => {
+                self.line = std::mem::take(&mut search.saved_line);
+                self.cur_pos = self.line.len() as u16;
+                self.search = None;
+            }
+            //Anything else keeps the match in the line for editing
+            _ => {
+                self.search = None;
+                return false;
+            }
+        }
+        true
+    }
+
     fn handle_char(&mut self, ch: char) {
         // print!("\\u{{{:x}}}\r\n", ch as u32);
+        if self.handle_search_key(ch) {
+            return;
+        }
         match ch {
+            //Ctrl-R, reverse incremental search
+            '\u{12}' => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    found: None,
+                    saved_line: self.line.clone(),
+                });
+            }
             //Backspace
-            '\u{7f}' => {
-                if !self.line.is_empty() && self.cur_pos > 0 {
-                    self.line.remove(self.cur_pos as usize - 1);
-                    self.cur_pos -= 1;
-                }
+            '\u{7f}' if !self.line.is_empty() && self.cur_pos > 0 => {
+                self.line.remove(self.cur_pos as usize - 1);
+                self.cur_pos -= 1;
             }
             //Delete
-            '\u{127}' => {
-                if !self.line.is_empty() && self.cur_pos < self.line.len() as u16 {
-                    self.line.remove(self.cur_pos as usize);
-                }
+            '\u{127}' if !self.line.is_empty() && self.cur_pos < self.line.len() as u16 => {
+                self.line.remove(self.cur_pos as usize);
             }
             //Arrow Up
             '\u{b7}' => {
@@ -174,26 +260,26 @@ impl Term {
                 self.update_history()
             }
             //Arrow Left
-            '\u{ba}' => {
-                if self.cur_pos > 0 {
-                    self.cur_pos -= 1;
-                }
+            '\u{ba}' if self.cur_pos > 0 => {
+                self.cur_pos -= 1;
             }
             //Arrow Right
-            '\u{b9}' => {
-                if self.cur_pos < self.line.len() as u16 {
-                    self.cur_pos += 1;
-                }
+            '\u{b9}' if self.cur_pos < self.line.len() as u16 => {
+                self.cur_pos += 1;
             }
             _ => {}
         }
     }
 
     fn add_line_to_history(&mut self) {
-        if self.history.len() >= self.max_hist_len {
-            self.history.remove(0);
+        let line = std::mem::take(&mut self.line);
+        if !line.trim().is_empty() {
+            //Only the newest copy of a line is kept
+            self.history.retain(|x| *x != line);
+            self.history.push(line);
+            self.trim_history();
+            self.save_history();
         }
-        self.history.push(self.line.clone());
         self.hist_idx = 0;
         self.use_hist = false;
         self.line.clear();
@@ -205,10 +291,9 @@ impl Term {
             .iter()
             .map(|x| *x as u32)
             .reduce(|x, y| x + y)
-            .ok_or(io::Error::new(
-                io::ErrorKind::Other,
-                String::from("Failed to reduce bytes to u32."),
-            ))?;
+            .ok_or(io::Error::other(String::from(
+                "Failed to reduce bytes to u32.",
+            )))?;
         let ch = char::from_u32(nr);
         // print!("{buf:?} {nr:?} {ch:?}\r\n");
         if buf == [3, 0, 0, 0, 0, 0, 0, 0] {
@@ -219,12 +304,18 @@ impl Term {
         } else if let Some(ch) = ch {
             if ch.is_ascii_alphanumeric() || ch.is_ascii_whitespace() || ch.is_ascii_punctuation() {
                 if ch == '\r' {
+                    //Enter runs the line the search found
+                    self.search = None;
                     let ret = self.line.clone();
                     self.add_line_to_history();
                     // print!("\r\n");
                     return Ok(Some(ret));
                 }
                 // print!("{ch}");
+                if self.search.is_some() {
+                    self.handle_search_char(ch);
+                    return Ok(None);
+                }
                 self.line.insert(self.cur_pos as usize, ch);
                 self.cur_pos += 1;
             } else {
@@ -245,7 +336,7 @@ impl Term {
     pub fn writeln(&mut self, data: Arguments<'_>) -> io::Result<()> {
         self.stdout.write_fmt(data)?;
         if io::stdout().is_terminal() {
-            self.stdout.write(b"\r")?;
+            self.stdout.write_all(b"\r")?;
         }
         let _ = self.stdout.write(b"\n")?;
         Ok(())
@@ -253,10 +344,14 @@ impl Term {
 
     pub fn next(&mut self) -> StringResult {
         let mut buf = Buffer::default();
-        self.stdin.read(&mut buf)?;
+        let _ = self.stdin.read(&mut buf)?;
         let ret = self.parse_char(buf)?;
+        let prompt = match &self.search {
+            Some(search) => format!("(search '{}') ", search.query),
+            None => String::from(">"),
+        };
         let change_pos = if let Ok(mut pos) = self.stdout.cursor_pos() {
-            pos.0 = self.cur_pos + 2;
+            pos.0 = prompt.chars().count() as u16 + self.cur_pos + 1;
             termion::cursor::Goto(pos.0, pos.1).to_string()
         } else {
             String::new()
@@ -264,7 +359,7 @@ impl Term {
         if ret.is_none() {
             term_write!(
                 self,
-                "{}\r>{}{}",
+                "{}\r{prompt}{}{}",
                 termion::clear::CurrentLine,
                 self.line.clone(),
                 change_pos,
@@ -273,3 +368,140 @@ impl Term {
         Ok(ret)
     }
 }
+
+// The newest `size` lines saved in `path`. The history is a convenience, a
+// file that can't be read is ignored.
+fn load_history(path: Option<&Path>, size: usize) -> Vec<String> {
+    let mut lines = path
+        .and_then(|x| fs::read_to_string(x).ok())
+        .map(|x| dedup(x.lines().map(str::to_owned).collect()))
+        .unwrap_or_default();
+    lines.drain(..lines.len().saturating_sub(size));
+    lines
+}
+
+// Keeps the newest copy of every line.
+fn dedup(lines: Vec<String>) -> Vec<String> {
+    let mut ret: Vec<String> = Vec::with_capacity(lines.len());
+    for line in lines.into_iter().rev() {
+        if !line.trim().is_empty() && !ret.contains(&line) {
+            ret.push(line);
+        }
+    }
+    ret.reverse();
+    ret
+}
+
+mod test {
+    #[cfg(test)]
+    use super::*;
+    #[cfg(test)]
+    use std::{env, process};
+
+    // A prompt over `history` that reads and writes nothing.
+    #[cfg(test)]
+    fn term(history: &[&str]) -> Term {
+        Term {
+            line: String::new(),
+            history: history.iter().map(|x| x.to_string()).collect(),
+            history_path: None,
+            max_hist_len: DEFAULT_HISTORY_SIZE,
+            search: None,
+            hist_idx: 0,
+            use_hist: false,
+            cur_pos: 0,
+            stdout: StdoutOpt::Stdout(io::stdout()),
+            stdin: fs::File::open("/dev/null").unwrap(),
+        }
+    }
+
+    #[cfg(test)]
+    fn type_str(term: &mut Term, s: &str) {
+        for ch in s.chars() {
+            if term.search.is_some() {
+                term.handle_search_char(ch);
+            } else {
+                term.line.push(ch);
+                term.cur_pos += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let lines = ["1+1", "x = 2", "", "1+1", "  ", "x = 2", "3"];
+        assert_eq!(
+            dedup(lines.iter().map(|x| x.to_string()).collect()),
+            ["1+1", "x = 2", "3"]
+        );
+        let mut term = term(&["a", "b", "c"]);
+        type_str(&mut term, "a");
+        term.add_line_to_history();
+        assert_eq!(term.history, ["b", "c", "a"]);
+        type_str(&mut term, " ");
+        term.add_line_to_history();
+        assert_eq!(term.history, ["b", "c", "a"]);
+        term.max_hist_len = 2;
+        type_str(&mut term, "d");
+        term.add_line_to_history();
+        assert_eq!(term.history, ["a", "d"]);
+    }
+
+    #[test]
+    fn test_load_history() {
+        let dir = env::temp_dir().join(format!("calc-history-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history");
+        let lines: Vec<String> = (0..3000).map(|x| x.to_string()).collect();
+        let data = lines.join("\n") + "\n";
+        fs::write(&path, &data).unwrap();
+        //A size above the default keeps everything and leaves the file alone
+        assert_eq!(load_history(Some(&path), 5000), lines);
+        assert_eq!(
+            load_history(Some(&path), 5),
+            ["2995", "2996", "2997", "2998", "2999"]
+        );
+        assert!(load_history(Some(&path), 0).is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), data);
+        assert!(load_history(Some(&dir.join("missing")), 5).is_empty());
+        assert!(load_history(None, 5).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_search() {
+        let mut term = term(&["x = 1", "y = 2", "x + y", "sqrt x"]);
+        assert_eq!(term.find("x", 3), Some(3));
+        assert_eq!(term.find("x", 2), Some(2));
+        assert_eq!(term.find("y =", 0), None);
+        assert_eq!(term.find("x", 10), None);
+
+        type_str(&mut term, "draft");
+        term.handle_char('\u{12}');
+        type_str(&mut term, "x");
+        assert_eq!(term.line, "sqrt x");
+        //Ctrl-R goes to older matches and stays at the oldest
+        assert!(term.handle_search_key('\u{12}'));
+        assert_eq!(term.line, "x + y");
+        assert!(term.handle_search_key('\u{12}'));
+        assert!(term.handle_search_key('\u{12}'));
+        assert_eq!(term.line, "x = 1");
+        //A longer query searches on from the match
+        type_str(&mut term, " +");
+        assert_eq!(term.line, "x = 1");
+        //Backspace searches from the newest entry again
+        assert!(term.handle_search_key('\u{7f}'));
+        assert_eq!(term.search.as_ref().unwrap().query, "x ");
+        assert_eq!(term.line, "x + y");
+        //Ctrl-G brings the line back
+        assert!(term.handle_search_key('\u{7}'));
+        assert_eq!(term.line, "draft");
+        assert!(term.search.is_none());
+        //Other keys keep the match
+        term.handle_char('\u{12}');
+        type_str(&mut term, "2");
+        assert!(!term.handle_search_key('\u{ba}'));
+        assert_eq!(term.line, "y = 2");
+        assert!(term.search.is_none());
+    }
+}
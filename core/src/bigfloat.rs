@@ -0,0 +1,674 @@
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::error::Error;
+use crate::number::{float_pow, Number};
+use crate::rational::Rational;
+use crate::symbolic::Symbolic;
+
+// Extra bits carried through series evaluation.
+const GUARD_BITS: u64 = 32;
+
+// Smallest supported mantissa.
+const MIN_PRECISION: u32 = 16;
+
+// Halvings applied to the argument of `exp` before summing the series.
+const EXP_HALVINGS: u32 = 16;
+
+/// Binary floating point number `m * 2^e` with a mantissa of at most `prec`
+/// bits, rounded half to even after every operation.
+#[derive(Clone)]
+pub struct BigFloat {
+    m: BigInt,
+    e: i64,
+    prec: u32,
+}
+
+// `m / 2^shift` rounded half to even.
+fn round_shift(m: &BigInt, shift: u64) -> BigInt {
+    if shift == 0 {
+        return m.clone();
+    }
+    let a = m.abs();
+    let q = &a >> shift;
+    let r = &a - (&q << shift);
+    let half = BigInt::one() << (shift - 1);
+    let q = if r > half || (r == half && q.is_odd()) {
+        q + 1
+    } else {
+        q
+    };
+    if m.is_negative() {
+        -q
+    } else {
+        q
+    }
+}
+
+// `a / b` rounded half to even, `b` must be positive.
+fn div_round(a: &BigInt, b: &BigInt) -> BigInt {
+    let (q, r) = a.abs().div_rem(b);
+    let twice = r * 2;
+    let q = if twice > *b || (twice == *b && q.is_odd()) {
+        q + 1
+    } else {
+        q
+    };
+    if a.is_negative() {
+        -q
+    } else {
+        q
+    }
+}
+
+// `x / 2^shift` for any sign of `shift`, rounded when bits are dropped.
+fn shift(x: &BigInt, shift: i64) -> BigInt {
+    if shift >= 0 {
+        x << shift as u64
+    } else {
+        round_shift(x, shift.unsigned_abs())
+    }
+}
+
+// Fixed point helpers, a value `x` stands for `x / 2^w`.
+mod fixed {
+    use num_bigint::BigInt;
+    use num_traits::{One, Zero};
+
+    pub fn one(w: u64) -> BigInt {
+        BigInt::one() << w
+    }
+
+    // Truncated toward zero so series terms of either sign reach zero.
+    pub fn mul(x: &BigInt, y: &BigInt, w: u64) -> BigInt {
+        (x * y) / one(w)
+    }
+
+    pub fn div(x: &BigInt, y: &BigInt, w: u64) -> BigInt {
+        (x << w) / y
+    }
+
+    pub fn sqrt(x: &BigInt, w: u64) -> BigInt {
+        (x << w).sqrt()
+    }
+
+    // atanh(1/k) = sum 1/((2n+1) k^(2n+1))
+    pub fn atanh_inv(k: u64, w: u64) -> BigInt {
+        let k2 = BigInt::from(k * k);
+        let mut power = one(w) / k;
+        let mut sum = BigInt::zero();
+        let mut n = 1u64;
+        while !power.is_zero() {
+            sum += &power / n;
+            power /= &k2;
+            n += 2;
+        }
+        sum
+    }
+
+    // atan(1/k) = sum (-1)^n / ((2n+1) k^(2n+1))
+    pub fn atan_inv(k: u64, w: u64) -> BigInt {
+        let k2 = BigInt::from(k * k);
+        let mut power = one(w) / k;
+        let mut sum = BigInt::zero();
+        let mut n = 1u64;
+        let mut negative = false;
+        while !power.is_zero() {
+            if negative {
+                sum -= &power / n;
+            } else {
+                sum += &power / n;
+            }
+            power /= &k2;
+            n += 2;
+            negative = !negative;
+        }
+        sum
+    }
+
+    pub fn ln2(w: u64) -> BigInt {
+        atanh_inv(3, w) * 2
+    }
+
+    //Machin's formula
+    pub fn pi(w: u64) -> BigInt {
+        atan_inv(5, w) * 16 - atan_inv(239, w) * 4
+    }
+
+    // Taylor series of atanh for |x| < 1.
+    pub fn atanh(x: &BigInt, w: u64) -> BigInt {
+        let x2 = mul(x, x, w);
+        let mut power = x.clone();
+        let mut sum = BigInt::zero();
+        let mut n = 1u64;
+        while !power.is_zero() {
+            sum += &power / n;
+            power = mul(&power, &x2, w);
+            n += 2;
+        }
+        sum
+    }
+
+    // Taylor series of atan for |x| well below 1.
+    pub fn atan(x: &BigInt, w: u64) -> BigInt {
+        let x2 = mul(x, x, w);
+        let mut power = x.clone();
+        let mut sum = BigInt::zero();
+        let mut n = 1u64;
+        let mut negative = false;
+        while !power.is_zero() {
+            if negative {
+                sum -= &power / n;
+            } else {
+                sum += &power / n;
+            }
+            power = mul(&power, &x2, w);
+            n += 2;
+            negative = !negative;
+        }
+        sum
+    }
+
+    // Taylor series of exp for |x| <= 1.
+    pub fn exp(x: &BigInt, w: u64) -> BigInt {
+        let mut term = one(w);
+        let mut sum = BigInt::zero();
+        let mut n = 1u64;
+        while !term.is_zero() {
+            sum += &term;
+            term = mul(&term, x, w) / n;
+            n += 1;
+        }
+        sum
+    }
+
+    // sin and cos of |x| <= pi by their Taylor series.
+    pub fn sin_cos(x: &BigInt, w: u64) -> (BigInt, BigInt) {
+        let (mut sin, mut cos) = (BigInt::zero(), BigInt::zero());
+        let mut term = one(w);
+        let mut n = 0u64;
+        while !term.is_zero() || n < 2 {
+            // term == x^n / n!
+            match n % 4 {
+                0 => cos += &term,
+                1 => sin += &term,
+                2 => cos -= &term,
+                _ => sin -= &term,
+            }
+            n += 1;
+            term = mul(&term, x, w) / n;
+        }
+        (sin, cos)
+    }
+}
+
+impl BigFloat {
+    fn new(m: BigInt, e: i64, prec: u32) -> Self {
+        let prec = prec.max(MIN_PRECISION);
+        let bits = m.bits();
+        let (mut m, mut e) = if bits > prec as u64 {
+            let extra = bits - prec as u64;
+            (round_shift(&m, extra), e + extra as i64)
+        } else {
+            (m, e)
+        };
+        if m.bits() > prec as u64 {
+            m >>= 1;
+            e += 1;
+        }
+        if m.is_zero() {
+            return Self { m, e: 0, prec };
+        }
+        let zeros = m.trailing_zeros().unwrap_or(0);
+        Self {
+            m: m >> zeros,
+            e: e + zeros as i64,
+            prec,
+        }
+    }
+
+    pub fn precision(&self) -> u32 {
+        self.prec
+    }
+
+    // Position just above the highest set bit.
+    fn top(&self) -> i64 {
+        self.e + self.m.bits() as i64
+    }
+
+    // Bits used for intermediate results.
+    fn working_bits(&self) -> u64 {
+        self.prec as u64 + GUARD_BITS
+    }
+
+    fn to_fixed(&self, w: u64) -> BigInt {
+        shift(&self.m, self.e + w as i64)
+    }
+
+    fn from_fixed(x: BigInt, w: u64, prec: u32) -> Self {
+        BigFloat::new(x, -(w as i64), prec)
+    }
+
+    fn with_value(&self, x: BigInt, w: u64) -> Self {
+        BigFloat::from_fixed(x, w, self.prec)
+    }
+
+    fn is_negative(&self) -> bool {
+        self.m.is_negative()
+    }
+
+    pub fn sqrt(&self) -> Self {
+        // m * 2^e with an even exponent, scaled up to twice the precision
+        let extra = 2 * self.prec as i64 + 2 - self.m.bits() as i64;
+        let mut k = extra.max(0);
+        if (self.e - k) % 2 != 0 {
+            k += 1;
+        }
+        let m = (&self.m << k as u64).sqrt();
+        BigFloat::new(m, (self.e - k) / 2, self.prec)
+    }
+}
+
+impl Number for BigFloat {
+    /// Mantissa bits.
+    type Context = u32;
+
+    fn from_rational(x: &Rational, prec: u32) -> Self {
+        let (p, q) = x.reduce().into();
+        if q.is_zero() || p.is_zero() {
+            return BigFloat::new(BigInt::zero(), 0, prec);
+        }
+        // Enough bits in the quotient to round correctly
+        let k = prec as i64 + 2 + q.bits() as i64 - p.bits() as i64;
+        let m = if k >= 0 {
+            div_round(&(p << k as u64), &q)
+        } else {
+            div_round(&p, &(q << (-k) as u64))
+        };
+        BigFloat::new(m, -k, prec)
+    }
+
+    fn from_float(x: f64, prec: u32) -> Self {
+        if !x.is_finite() {
+            return BigFloat::new(BigInt::zero(), 0, prec);
+        }
+        BigFloat::from_rational(&Rational::from(x), prec)
+    }
+
+    fn context(&self) -> u32 {
+        self.prec
+    }
+
+    fn to_rational(&self) -> Option<Rational> {
+        Some(if self.e >= 0 {
+            Rational::from(&self.m << self.e as u64)
+        } else {
+            Rational::new(self.m.clone(), BigInt::one() << (-self.e) as u64).reduce()
+        })
+    }
+
+    fn to_float(&self) -> f64 {
+        self.to_rational().map(|x| x.to_float()).unwrap_or(f64::NAN)
+    }
+
+    fn to_symbolic(&self) -> Symbolic {
+        self.to_rational()
+            .unwrap_or_else(Rational::zero)
+            .inexact_if(true)
+            .into()
+    }
+
+    fn is_exact(&self) -> bool {
+        false
+    }
+
+    fn is_finite(&self) -> bool {
+        true
+    }
+
+    fn is_zero(&self) -> bool {
+        self.m.is_zero()
+    }
+
+    fn pi(prec: u32) -> Self {
+        let w = prec as u64 + GUARD_BITS;
+        BigFloat::from_fixed(fixed::pi(w), w, prec)
+    }
+
+    fn e(prec: u32) -> Self {
+        BigFloat::one(prec).exp()
+    }
+
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        if let Some(n) = x.to_rational().filter(|x| x.is_integer()) {
+            let n = n.numer().clone();
+            if n.bits() <= 64 {
+                //Repeated squaring
+                let (mut base, mut acc) = (self.clone(), BigFloat::one(self.prec));
+                let mut k = n.abs();
+                while !k.is_zero() {
+                    if k.is_odd() {
+                        acc = acc * base.clone();
+                    }
+                    k >>= 1;
+                    if !k.is_zero() {
+                        base = base.clone() * base;
+                    }
+                }
+                return Ok(if n.is_negative() {
+                    BigFloat::one(self.prec) / acc
+                } else {
+                    acc
+                });
+            }
+        }
+        if self.is_zero() {
+            return Ok(self.clone());
+        }
+        // Use the float rules to find the sign of an odd root of a negative
+        let sign = float_pow(self.to_float().signum(), x.to_float())?;
+        let out = (self.abs().ln() * x.clone()).exp();
+        Ok(if sign < 0.0 { -out } else { out })
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        self.ln() / x.ln()
+    }
+
+    fn exp(&self) -> Self {
+        let w = self.working_bits();
+        let ln2 = fixed::ln2(w);
+        // x = k*ln(2) + r with |r| <= ln(2)/2
+        let x = self.to_fixed(w);
+        let k = div_round(&x, &ln2);
+        let r = x - &k * &ln2;
+        let r = &r >> EXP_HALVINGS as u64;
+        let mut y = fixed::exp(&r, w);
+        for _ in 0..EXP_HALVINGS {
+            y = fixed::mul(&y, &y, w);
+        }
+        let k = k.to_i64().unwrap_or(i64::MAX / 2);
+        BigFloat::new(y, k - w as i64, self.prec)
+    }
+
+    fn ln(&self) -> Self {
+        if self.m.is_zero() || self.is_negative() {
+            return BigFloat::zero(self.prec);
+        }
+        let w = self.working_bits();
+        // x = f * 2^k with f in [1/2, 1)
+        let k = self.top();
+        let f = shift(&self.m, w as i64 - self.m.bits() as i64);
+        let one = fixed::one(w);
+        //ln(f) = 2 atanh((f - 1)/(f + 1))
+        let t = fixed::div(&(&f - &one), &(&f + &one), w);
+        let y = fixed::atanh(&t, w) * 2 + fixed::ln2(w) * k;
+        self.with_value(y, w)
+    }
+
+    fn sin(&self) -> Self {
+        let (sin, _) = self.sin_cos();
+        sin
+    }
+
+    fn cos(&self) -> Self {
+        let (_, cos) = self.sin_cos();
+        cos
+    }
+
+    fn tan(&self) -> Self {
+        let (sin, cos) = self.sin_cos();
+        sin / cos
+    }
+
+    fn atan(&self) -> Self {
+        let w = self.working_bits();
+        let one = fixed::one(w);
+        let x = self.to_fixed(w);
+        //atan(x) = +-pi/2 - atan(1/x)
+        if x.abs() > one {
+            let half_pi: BigInt = fixed::pi(w) / 2;
+            let y = fixed::atan(&reduce_atan(fixed::div(&one, &x, w), w), w) * 4;
+            let y = if x.is_negative() { -half_pi } else { half_pi } - y;
+            return self.with_value(y, w);
+        }
+        self.with_value(fixed::atan(&reduce_atan(x, w), w) * 4, w)
+    }
+
+    fn asin(&self) -> Self {
+        let one = BigFloat::one(self.prec);
+        let rest = one.clone() - self.clone() * self.clone();
+        if rest.is_zero() {
+            let half_pi = BigFloat::pi(self.prec) / BigFloat::from_rational(&2.into(), self.prec);
+            return if self.is_negative() {
+                -half_pi
+            } else {
+                half_pi
+            };
+        }
+        //asin(x) = atan(x / sqrt(1 - x^2))
+        (self.clone() / rest.sqrt()).atan()
+    }
+
+    fn acos(&self) -> Self {
+        let half_pi = BigFloat::pi(self.prec) / BigFloat::from_rational(&2.into(), self.prec);
+        half_pi - self.asin()
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        let pi = BigFloat::pi(self.prec);
+        if x.is_zero() {
+            let half_pi = pi / BigFloat::from_rational(&2.into(), self.prec);
+            return if self.is_negative() {
+                -half_pi
+            } else {
+                half_pi
+            };
+        }
+        let a = (self.clone() / x.clone()).atan();
+        match (x.is_negative(), self.is_negative()) {
+            (false, _) => a,
+            (true, false) => a + pi,
+            (true, true) => a - pi,
+        }
+    }
+
+    fn abs(&self) -> Self {
+        BigFloat {
+            m: self.m.abs(),
+            ..self.clone()
+        }
+    }
+}
+
+// atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))), applied twice so the series
+// converges quickly. The result has to be multiplied by 4.
+fn reduce_atan(mut x: BigInt, w: u64) -> BigInt {
+    let one = fixed::one(w);
+    for _ in 0..2 {
+        let root = fixed::sqrt(&(&one + fixed::mul(&x, &x, w)), w);
+        x = fixed::div(&x, &(&one + root), w);
+    }
+    x
+}
+
+impl BigFloat {
+    fn sin_cos(&self) -> (Self, Self) {
+        // Arguments far from zero need more bits of pi
+        let w = self.working_bits() + self.top().max(0) as u64;
+        let two_pi = fixed::pi(w) * 2;
+        let x = self.to_fixed(w);
+        let r = &x - div_round(&x, &two_pi) * &two_pi;
+        let (sin, cos) = fixed::sin_cos(&r, w);
+        (self.with_value(sin, w), self.with_value(cos, w))
+    }
+}
+
+impl PartialEq for BigFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.m == other.m && self.e == other.e
+    }
+}
+
+impl PartialOrd for BigFloat {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let diff = self.clone() - other.clone();
+        Some(diff.m.sign().cmp(&num_bigint::Sign::NoSign))
+    }
+}
+
+impl std::fmt::Debug for BigFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::fmt::Display for BigFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Digits that are all backed by the mantissa
+        let digits = (self.prec as f64 * std::f64::consts::LOG10_2) as u32;
+        let x = self.to_rational().unwrap_or_else(Rational::zero);
+        f.write_str(&x.to_significant(digits))
+    }
+}
+
+impl std::ops::Add for BigFloat {
+    type Output = BigFloat;
+    fn add(self, other: BigFloat) -> BigFloat {
+        let prec = self.prec.max(other.prec);
+        if self.is_zero() || other.is_zero() {
+            let x = if self.is_zero() { other } else { self };
+            return BigFloat::new(x.m, x.e, prec);
+        }
+        let (big, small) = if self.top() >= other.top() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        // Too small to change any kept bit of the sum
+        if small.top() < big.top() - prec as i64 - 2 {
+            return BigFloat::new(big.m, big.e, prec);
+        }
+        let e = big.e.min(small.e);
+        let m = (&big.m << (big.e - e) as u64) + (&small.m << (small.e - e) as u64);
+        BigFloat::new(m, e, prec)
+    }
+}
+
+impl std::ops::Sub for BigFloat {
+    type Output = BigFloat;
+    fn sub(self, other: BigFloat) -> BigFloat {
+        self + -other
+    }
+}
+
+impl std::ops::Mul for BigFloat {
+    type Output = BigFloat;
+    fn mul(self, other: BigFloat) -> BigFloat {
+        BigFloat::new(
+            self.m * other.m,
+            self.e + other.e,
+            self.prec.max(other.prec),
+        )
+    }
+}
+
+impl std::ops::Div for BigFloat {
+    type Output = BigFloat;
+    fn div(self, other: BigFloat) -> BigFloat {
+        let prec = self.prec.max(other.prec);
+        if other.is_zero() {
+            return BigFloat::new(BigInt::zero(), 0, prec);
+        }
+        let k = (prec as i64 + 2 + other.m.bits() as i64 - self.m.bits() as i64).max(0);
+        let m = div_round(&(self.m << k as u64), &other.m.abs()) * other.m.signum();
+        BigFloat::new(m, self.e - other.e - k, prec)
+    }
+}
+
+impl std::ops::Rem for BigFloat {
+    type Output = BigFloat;
+    fn rem(self, other: BigFloat) -> BigFloat {
+        let prec = self.prec.max(other.prec);
+        match (self.to_rational(), other.to_rational()) {
+            (Some(x), Some(y)) if !y.is_zero() => BigFloat::from_rational(&(x % y), prec),
+            _ => BigFloat::zero(prec),
+        }
+    }
+}
+
+impl std::ops::Neg for BigFloat {
+    type Output = BigFloat;
+    fn neg(self) -> BigFloat {
+        BigFloat { m: -self.m, ..self }
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::BigFloat;
+    #[cfg(test)]
+    use crate::{number::Number, rational::Rational};
+
+    #[cfg(test)]
+    fn big(x: i64) -> BigFloat {
+        BigFloat::from_rational(&x.into(), 200)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let third = big(1) / big(3);
+        assert_eq!(
+            third.to_string(),
+            "0.333333333333333333333333333333333333333333333333333333333333"
+        );
+        assert_eq!((third.clone() * big(3)).to_string(), "1");
+        assert_eq!(
+            BigFloat::from_rational(&Rational::new(1, 8), 16).to_rational(),
+            Some(Rational::new(1, 8))
+        );
+        assert!(big(2) > big(1) / big(3));
+        assert_eq!(big(2).pow(&big(-3)).unwrap().to_string(), "0.125");
+    }
+
+    #[test]
+    fn test_functions() {
+        let digits = |x: BigFloat| x.to_string()[..40].to_owned();
+        assert_eq!(
+            digits(BigFloat::pi(200)),
+            "3.14159265358979323846264338327950288419"
+        );
+        assert_eq!(
+            digits(BigFloat::e(200)),
+            "2.71828182845904523536028747135266249775"
+        );
+        assert_eq!(
+            digits(big(2).sqrt()),
+            "1.41421356237309504880168872420969807856"
+        );
+        assert_eq!(
+            digits(big(2).ln()),
+            "0.69314718055994530941723212145817656807"
+        );
+        assert_eq!(
+            digits(big(1).atan() * big(4)),
+            "3.14159265358979323846264338327950288419"
+        );
+        assert_eq!(
+            digits(big(1).sin()),
+            "0.84147098480789650665250232163029899962"
+        );
+        assert_eq!(big(0).cos().to_string(), "1");
+        assert_eq!(
+            ((BigFloat::pi(200) / big(6)).sin() * big(2)).to_string(),
+            "1"
+        );
+        assert!(big(-8)
+            .root(3)
+            .is_ok_and(|x| (x + big(2)).abs() < BigFloat::from_float(1e-50, 200)));
+        assert!(big(-8).pow(&(big(1) / big(2))).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::combinatorics;
+use crate::error::{Error, Span};
+use crate::number::Number;
+use crate::rational::Rational;
+
+/// Functions that are always available, called like user functions: `sin(x)`.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Builtin {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Exp,
+    Ln,
+    Log10,
+    Log2,
+    Floor,
+    Ceil,
+    Round,
+    Trunc,
+    Abs,
+    Sign,
+    Min,
+    Max,
+    Hypot,
+    Root,
+    Re,
+    Im,
+    Conj,
+    Arg,
+    If,
+    Gamma,
+    Ncr,
+    Npr,
+    Multinomial,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 30] = [
+        Builtin::Sin,
+        Builtin::Cos,
+        Builtin::Tan,
+        Builtin::Asin,
+        Builtin::Acos,
+        Builtin::Atan,
+        Builtin::Atan2,
+        Builtin::Exp,
+        Builtin::Ln,
+        Builtin::Log10,
+        Builtin::Log2,
+        Builtin::Floor,
+        Builtin::Ceil,
+        Builtin::Round,
+        Builtin::Trunc,
+        Builtin::Abs,
+        Builtin::Sign,
+        Builtin::Min,
+        Builtin::Max,
+        Builtin::Hypot,
+        Builtin::Root,
+        Builtin::Re,
+        Builtin::Im,
+        Builtin::Conj,
+        Builtin::Arg,
+        Builtin::If,
+        Builtin::Gamma,
+        Builtin::Ncr,
+        Builtin::Npr,
+        Builtin::Multinomial,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Builtin::ALL.into_iter().find(|x| x.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Sin => "sin",
+            Builtin::Cos => "cos",
+            Builtin::Tan => "tan",
+            Builtin::Asin => "asin",
+            Builtin::Acos => "acos",
+            Builtin::Atan => "atan",
+            Builtin::Atan2 => "atan2",
+            Builtin::Exp => "exp",
+            Builtin::Ln => "ln",
+            Builtin::Log10 => "log10",
+            Builtin::Log2 => "log2",
+            Builtin::Floor => "floor",
+            Builtin::Ceil => "ceil",
+            Builtin::Round => "round",
+            Builtin::Trunc => "trunc",
+            Builtin::Abs => "abs",
+            Builtin::Sign => "sign",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Hypot => "hypot",
+            Builtin::Root => "root",
+            Builtin::Re => "re",
+            Builtin::Im => "im",
+            Builtin::Conj => "conj",
+            Builtin::Arg => "arg",
+            Builtin::If => "if",
+            Builtin::Gamma => "gamma",
+            Builtin::Ncr => "ncr",
+            Builtin::Npr => "npr",
+            Builtin::Multinomial => "multinomial",
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Builtin::Atan2
+            | Builtin::Min
+            | Builtin::Max
+            | Builtin::Hypot
+            | Builtin::Root
+            | Builtin::Ncr
+            | Builtin::Npr
+            | Builtin::Multinomial => 2,
+            Builtin::If => 3,
+            _ => 1,
+        }
+    }
+
+    /// Whether `count` arguments can be passed, `round` takes an optional
+    /// number of decimal places and `multinomial` any number of groups.
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Builtin::Round => count == 1 || count == 2,
+            Builtin::Multinomial => count >= 1,
+            _ => count == self.arity(),
+        }
+    }
+
+    /// `Error::ArityMismatch` at `span` unless `count` arguments can be
+    /// passed.
+    pub fn check_arity(&self, count: usize, span: Span) -> Result<(), Error> {
+        if self.accepts(count) {
+            return Ok(());
+        }
+        Err(Error::ArityMismatch {
+            name: self.name().to_owned(),
+            expected: self.arity(),
+            found: count,
+            span,
+        })
+    }
+
+    /// Evaluates the function on already evaluated arguments.
+    ///
+    /// Exact results are kept when the number type has them, `sin(pi/4)`
+    /// is `sqrt(2)/2` for `Symbolic`.
+    pub fn apply<N: Number>(&self, args: &[N], span: Span) -> Result<N, Error> {
+        self.check_arity(args.len(), span)?;
+        let x = &args[0];
+        match self {
+            Builtin::Round if args.len() == 2 => args[1].check_dimensionless(),
+            Builtin::Floor
+            | Builtin::Ceil
+            | Builtin::Round
+            | Builtin::Trunc
+            | Builtin::Abs
+            | Builtin::Sign
+            | Builtin::Re
+            | Builtin::Im
+            | Builtin::Conj
+            | Builtin::Arg
+            | Builtin::If => Ok(()),
+            Builtin::Atan2 | Builtin::Min | Builtin::Max | Builtin::Hypot => {
+                x.check_compatible(&args[1])
+            }
+            //The degree of root(n, x), the other functions take plain numbers
+            _ => x.check_dimensionless(),
+        }
+        .map_err(|e| e.with_span(span))?;
+        let ctx = x.context();
+        let zero = N::zero(ctx);
+        let one = N::one(ctx);
+        let out = match self {
+            Builtin::Floor => x.floor(),
+            Builtin::Ceil => x.ceil(),
+            //round(x, n), halves away from zero like round(x)
+            Builtin::Round if args.len() == 2 => {
+                let n = args[1]
+                    .to_rational()
+                    .filter(|n| n.is_integer())
+                    .and_then(|n| n.numer().to_i32());
+                let Some(n) = n else {
+                    return Err(Error::domain("round needs a whole number of places", span));
+                };
+                let scale = N::from_rational(&Rational::from(10).pow_int(&n.into()), ctx);
+                (x.clone() * scale.clone()).round() / scale
+            }
+            Builtin::Round => x.round(),
+            Builtin::Trunc => x.trunc(),
+            Builtin::Abs => x.abs(),
+            Builtin::Sign => x.signum(),
+            Builtin::Re => x.re(),
+            Builtin::Im => x.im(),
+            Builtin::Conj => x.conj(),
+            Builtin::Arg => x.arg(),
+            //if(c, a, b), `Op::apply` only evaluates the chosen branch
+            Builtin::If => if x.is_zero() { &args[2] } else { &args[1] }.clone(),
+            Builtin::Min | Builtin::Max if x.partial_cmp(&args[1]).is_none() => {
+                return Err(Error::domain(
+                    format!("{} needs real arguments", self.name()),
+                    span,
+                ))
+            }
+            Builtin::Min => if args[1] < *x { &args[1] } else { x }.clone(),
+            Builtin::Max => if args[1] > *x { &args[1] } else { x }.clone(),
+            Builtin::Sin => x.sin(),
+            Builtin::Cos => x.cos(),
+            Builtin::Tan if x.cos().is_zero() => {
+                return Err(Error::domain("tan is undefined at pi/2 + k*pi", span))
+            }
+            Builtin::Tan => x.tan(),
+            Builtin::Asin | Builtin::Acos if x.im().is_zero() && x.abs() > one => {
+                return Err(Error::domain(
+                    format!("{} is only defined on [-1, 1]", self.name()),
+                    span,
+                ))
+            }
+            Builtin::Asin => x.asin(),
+            Builtin::Acos => x.acos(),
+            Builtin::Atan => x.atan(),
+            Builtin::Atan2 => {
+                let (y, x) = (x, &args[1]);
+                if x.is_zero() && y.is_zero() {
+                    return Err(Error::domain("atan2 of the origin", span));
+                }
+                y.atan2(x)
+            }
+            Builtin::Exp => x.exp(),
+            Builtin::Ln | Builtin::Log10 | Builtin::Log2 if *x <= zero => {
+                return Err(Error::domain("logarithm of a non-positive number", span))
+            }
+            Builtin::Ln => x.ln(),
+            Builtin::Log10 => x.log(&N::from_rational(&10.into(), ctx)),
+            Builtin::Log2 => x.log(&N::from_rational(&2.into(), ctx)),
+            //root(n, x), the n-th root of x
+            Builtin::Root => {
+                let n = x
+                    .to_rational()
+                    .filter(|n| n.is_integer() && *n > Rational::zero())
+                    .and_then(|n| n.numer().to_u32());
+                let Some(n) = n else {
+                    return Err(Error::domain(
+                        "root degree must be a positive integer",
+                        span,
+                    ));
+                };
+                args[1].root(n).map_err(|e| e.with_span(span))?
+            }
+            Builtin::Gamma => combinatorics::gamma(x).map_err(|e| e.with_span(span))?,
+            Builtin::Ncr => combinatorics::choose(x, &args[1]).map_err(|e| e.with_span(span))?,
+            Builtin::Npr => {
+                combinatorics::permutations(x, &args[1]).map_err(|e| e.with_span(span))?
+            }
+            Builtin::Multinomial => {
+                combinatorics::multinomial(args).map_err(|e| e.with_span(span))?
+            }
+            Builtin::Hypot => (x.clone() * x.clone() + args[1].clone() * args[1].clone())
+                .root(2)
+                .map_err(|e| e.with_span(span))?,
+        };
+        //Exact integers like `nCr(10000, 5000)` are finite even beyond f64
+        if !out.is_finite() && out.to_rational().is_none() {
+            return Err(Error::domain(
+                format!("{} result is out of range", self.name()),
+                span,
+            ));
+        }
+        Ok(out)
+    }
+}
+
+/// Named constants, looked up after user variables.
+pub fn constant<N: Number>(name: &str, ctx: N::Context) -> Option<N> {
+    match name {
+        "pi" => Some(N::pi(ctx)),
+        "e" => Some(N::e(ctx)),
+        "i" => N::i(ctx),
+        "true" => Some(N::from_bool(true, ctx)),
+        "false" => Some(N::from_bool(false, ctx)),
+        _ => None,
+    }
+}
+
+impl std::fmt::Display for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Builtin;
+    #[cfg(test)]
+    use crate::{error::Span, rational::Rational, symbolic::Symbolic};
+
+    #[cfg(test)]
+    fn apply(f: Builtin, args: &[Rational]) -> String {
+        let args: Vec<Symbolic> = args.iter().cloned().map(Symbolic::from).collect();
+        f.apply(&args, Span::default())
+            .map(|x| x.to_string())
+            .unwrap_or_else(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_names() {
+        for f in Builtin::ALL {
+            assert_eq!(Builtin::from_name(f.name()), Some(f));
+        }
+        assert_eq!(Builtin::from_name("foo"), None);
+    }
+
+    #[test]
+    fn test_exact() {
+        assert_eq!(apply(Builtin::Floor, &[Rational::new(-1, 3)]), "-1");
+        assert_eq!(apply(Builtin::Abs, &[Rational::new(-1, 3)]), "1/3");
+        assert_eq!(apply(Builtin::Sin, &[Rational::zero()]), "0");
+        assert_eq!(apply(Builtin::Exp, &[Rational::zero()]), "1");
+        assert_eq!(apply(Builtin::Log10, &[Rational::new(1, 1000)]), "-3");
+        assert_eq!(apply(Builtin::Log2, &[Rational::new(1024, 1)]), "10");
+        assert_eq!(
+            apply(Builtin::Root, &[Rational::new(3, 1), Rational::new(-27, 8)]),
+            "-3/2"
+        );
+        assert_eq!(
+            apply(Builtin::Max, &[Rational::new(1, 3), Rational::new(1, 2)]),
+            "1/2"
+        );
+        assert_eq!(
+            apply(Builtin::Hypot, &[Rational::new(3, 1), Rational::new(4, 1)]),
+            "5"
+        );
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let pi = |k: i64, q: i64| Symbolic::pi() * Rational::new(k, q).into();
+        let apply = |f: Builtin, x: Symbolic| f.apply(&[x], Span::default()).unwrap().to_string();
+        assert_eq!(apply(Builtin::Sin, pi(1, 4)), "sqrt(2)/2");
+        assert_eq!(apply(Builtin::Sin, pi(-5, 6)), "-1/2");
+        assert_eq!(apply(Builtin::Cos, pi(5, 3)), "1/2");
+        assert_eq!(apply(Builtin::Tan, pi(1, 3)), "sqrt(3)");
+        assert_eq!(
+            apply(Builtin::Asin, Symbolic::from(3).sqrt() / 2.into()),
+            "pi/3"
+        );
+        assert_eq!(apply(Builtin::Acos, Rational::new(-1, 2).into()), "2*pi/3");
+        assert_eq!(apply(Builtin::Atan, Symbolic::from(-1)), "-pi/4");
+        assert_eq!(apply(Builtin::Exp, 2.into()), "e^2");
+        assert_eq!(apply(Builtin::Ln, Symbolic::e().sqrt()), "1/2");
+        assert_eq!(
+            Builtin::Atan2
+                .apply(&[Symbolic::from(1), Symbolic::from(-1)], Span::default())
+                .unwrap()
+                .to_string(),
+            "3*pi/4"
+        );
+        assert!(Builtin::Tan.apply(&[pi(1, 2)], Span::default()).is_err());
+    }
+
+    #[test]
+    fn test_domain() {
+        assert_eq!(
+            apply(Builtin::Asin, &[Rational::new(2, 1)]),
+            "Domain error: asin is only defined on [-1, 1]"
+        );
+        assert_eq!(
+            apply(Builtin::Root, &[Rational::new(4, 1), Rational::new(-16, 1)]),
+            "Domain error: even root of a negative number"
+        );
+        assert_eq!(
+            apply(Builtin::Ln, &[Rational::zero()]),
+            "Domain error: logarithm of a non-positive number"
+        );
+        assert_eq!(
+            apply(Builtin::Exp, &[Rational::new(100001, 100)]),
+            "Domain error: exp result is out of range"
+        );
+        assert_eq!(
+            apply(Builtin::Min, &[Rational::zero()]),
+            "\"min\" takes 2 argument(s) but 1 were given"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive};
+
+use crate::error::{Error, Span};
+use crate::integer::integer;
+use crate::number::Number;
+use crate::rational::{Rational, MAX_EXACT_BITS};
+
+// Largest number of factors multiplied for one result, `10000!` has 35660
+// digits.
+const MAX_FACTORS: u64 = 10_000;
+
+fn domain(message: impl Into<String>) -> Error {
+    Error::domain(message, Span::default())
+}
+
+fn factor_count(n: &BigInt, name: &str) -> Result<u64, Error> {
+    n.to_u64()
+        .filter(|n| *n <= MAX_FACTORS)
+        .ok_or_else(|| domain(format!("{name} argument is too large")))
+}
+
+// `x (x - step) (x - 2 step) ...` with `count` factors. Numerator and
+// denominator are multiplied as integers, reducing after every factor would
+// make large factorials slow. Results past `MAX_EXACT_BITS` are refused, like
+// `nPr(1/10^30000, 10000)`.
+fn falling(x: &Rational, count: u64, step: i64, name: &str) -> Result<Rational, Error> {
+    let x = x.reduce();
+    let step = BigInt::from(step) * x.denom();
+    let largest = x.numer().abs() + &step.abs() * count;
+    let bits = largest.bits().max(x.denom().bits());
+    if bits.saturating_mul(count) > MAX_EXACT_BITS {
+        return Err(domain(format!("{name} result is too large")));
+    }
+    let mut factor = x.numer().clone();
+    let mut p = BigInt::one();
+    for _ in 0..count {
+        p *= &factor;
+        factor -= &step;
+    }
+    Ok(Rational::new(p, x.denom().pow(count as u32)).reduce())
+}
+
+fn count_factorial(count: u64, name: &str) -> Result<Rational, Error> {
+    falling(&BigInt::from(count).into(), count, 1, name)
+}
+
+// An exact result, approximated if one of `inputs` was.
+fn exact<N: Number>(out: Rational, inputs: &[&N]) -> N {
+    let inexact = inputs.iter().any(|x| !x.is_exact());
+    N::from_rational(&out.inexact_if(inexact), inputs[0].context())
+}
+
+fn check_dimensionless<N: Number>(args: &[&N]) -> Result<(), Error> {
+    args.iter().try_for_each(|x| x.check_dimensionless())
+}
+
+/// Gamma function, exact at the positive integers, `gamma(5)` is `24`.
+pub fn gamma<N: Number>(x: &N) -> Result<N, Error> {
+    x.check_dimensionless()?;
+    let out = match integer(x) {
+        Some(n) if !n.is_positive() => {
+            return Err(domain("gamma is undefined at 0 and the negative integers"))
+        }
+        Some(n) => {
+            let count = factor_count(&(n - 1), "gamma")?;
+            exact(count_factorial(count, "gamma")?, &[x])
+        }
+        None => x.gamma()?,
+    };
+    if !out.is_finite() {
+        return Err(domain("gamma result is out of range"));
+    }
+    Ok(out)
+}
+
+/// `x!`, other than integers go through gamma: `x! = gamma(x + 1)`.
+pub fn factorial<N: Number>(x: &N) -> Result<N, Error> {
+    x.check_dimensionless()?;
+    match integer(x) {
+        Some(n) if n.is_negative() => Err(domain("factorial of a negative integer")),
+        Some(n) => {
+            let count = factor_count(&n, "factorial")?;
+            Ok(exact(count_factorial(count, "factorial")?, &[x]))
+        }
+        None => gamma(&(x.clone() + N::one(x.context()))),
+    }
+}
+
+/// `x!!`, the product of every other integer from `x` down to 1 or 2.
+pub fn double_factorial<N: Number>(x: &N) -> Result<N, Error> {
+    x.check_dimensionless()?;
+    let n = integer(x).ok_or_else(|| domain("double factorial needs an integer"))?;
+    if n < BigInt::from(-1) {
+        return Err(domain("double factorial of an integer below -1"));
+    }
+    //`-1!!` and `0!!` are empty products
+    let count = factor_count(&((n.clone() + 1) / 2), "double factorial")?;
+    Ok(exact(
+        falling(&n.into(), count, 2, "double factorial")?,
+        &[x],
+    ))
+}
+
+/// `nCr(n, k)`, the ways to choose `k` of `n` items.
+///
+/// Exact for any rational `n` as long as `k` is an integer, through gamma
+/// otherwise.
+pub fn choose<N: Number>(n: &N, k: &N) -> Result<N, Error> {
+    check_dimensionless(&[n, k])?;
+    let (Some(a), Some(b)) = (n.to_rational(), integer(k)) else {
+        let one = N::one(n.context());
+        let den =
+            gamma(&(k.clone() + one.clone()))? * gamma(&(n.clone() - k.clone() + one.clone()))?;
+        return Ok(gamma(&(n.clone() + one))? / den);
+    };
+    //Fewer factors for `nCr(100, 98)`
+    let b = match integer(n) {
+        Some(a) if !a.is_negative() && b > a => return Ok(exact(Rational::zero(), &[n, k])),
+        Some(a) if !a.is_negative() && b.clone() * 2 > a => a - b,
+        _ => b,
+    };
+    if b.is_negative() {
+        return Ok(exact(Rational::zero(), &[n, k]));
+    }
+    let count = factor_count(&b, "nCr")?;
+    let out = falling(&a, count, 1, "nCr")? / count_factorial(count, "nCr")?;
+    Ok(exact(out.reduce(), &[n, k]))
+}
+
+/// `nPr(n, k)`, the ways to line up `k` of `n` items.
+pub fn permutations<N: Number>(n: &N, k: &N) -> Result<N, Error> {
+    check_dimensionless(&[n, k])?;
+    let (Some(a), Some(b)) = (n.to_rational(), integer(k)) else {
+        let one = N::one(n.context());
+        return Ok(gamma(&(n.clone() + one.clone()))? / gamma(&(n.clone() - k.clone() + one))?);
+    };
+    if b.is_negative() {
+        return Err(domain("nPr needs a count of at least 0"));
+    }
+    if integer(n).is_some_and(|a| !a.is_negative() && b > a) {
+        return Ok(exact(Rational::zero(), &[n, k]));
+    }
+    let count = factor_count(&b, "nPr")?;
+    Ok(exact(falling(&a, count, 1, "nPr")?, &[n, k]))
+}
+
+/// `multinomial(a, b, ...)`, `(a + b + ...)! / (a! b! ...)`: the ways to
+/// split items into groups of these sizes.
+pub fn multinomial<N: Number>(ks: &[N]) -> Result<N, Error> {
+    let ks: Vec<&N> = ks.iter().collect();
+    check_dimensionless(&ks)?;
+    let mut total = Rational::zero();
+    let mut out = Rational::one();
+    for k in &ks {
+        let k = integer(*k)
+            .filter(|k| !k.is_negative())
+            .ok_or_else(|| domain("multinomial needs integers of at least 0"))?;
+        //The product of one binomial per group
+        total = total + k.clone().into();
+        let count = factor_count(&k, "multinomial")?;
+        factor_count(total.numer(), "multinomial")?;
+        out = out
+            * (falling(&total, count, 1, "multinomial")? / count_factorial(count, "multinomial")?);
+    }
+    Ok(exact(out.reduce(), &ks))
+}
+
+mod test {
+    #[cfg(test)]
+    use super::*;
+    #[cfg(test)]
+    use crate::parser::{test::output, KnownLiterals};
+
+    #[cfg(test)]
+    fn show(x: Result<Rational, Error>) -> String {
+        x.map(|x| x.to_string()).unwrap_or_else(|e| e.to_string())
+    }
+
+    #[cfg(test)]
+    fn r(p: i64, q: i64) -> Rational {
+        Rational::new(p, q)
+    }
+
+    #[test]
+    fn test_exact() {
+        assert_eq!(show(factorial(&r(0, 1))), "1");
+        assert_eq!(show(factorial(&r(20, 1))), "2432902008176640000");
+        assert_eq!(show(double_factorial(&r(7, 1))), "105");
+        assert_eq!(show(double_factorial(&r(8, 1))), "384");
+        assert_eq!(show(double_factorial(&r(-1, 1))), "1");
+        assert_eq!(show(choose(&r(5, 1), &r(2, 1))), "10");
+        assert_eq!(show(choose(&r(100, 1), &r(98, 1))), "4950");
+        assert_eq!(show(choose(&r(3, 1), &r(5, 1))), "0");
+        assert_eq!(show(choose(&r(1, 2), &r(2, 1))), "-1/8");
+        assert_eq!(show(choose(&r(-1, 1), &r(3, 1))), "-1");
+        assert_eq!(show(permutations(&r(5, 1), &r(2, 1))), "20");
+        assert_eq!(show(multinomial(&[r(2, 1), r(1, 1), r(1, 1)])), "12");
+        assert_eq!(show(gamma(&r(5, 1))), "24");
+    }
+
+    #[test]
+    fn test_domain() {
+        assert_eq!(
+            show(factorial(&r(-3, 1))),
+            "Domain error: factorial of a negative integer"
+        );
+        assert_eq!(
+            show(gamma(&r(0, 1))),
+            "Domain error: gamma is undefined at 0 and the negative integers"
+        );
+        assert_eq!(
+            show(factorial(&r(100_001, 1))),
+            "Domain error: factorial argument is too large"
+        );
+        //Allowed factor counts, but every factor is huge
+        let tiny = Rational::new(1, 10).pow_int(&BigInt::from(30_000));
+        assert_eq!(
+            show(permutations(&tiny, &r(10_000, 1))),
+            "Domain error: nPr result is too large"
+        );
+        assert_eq!(
+            show(choose(&tiny, &r(10_000, 1))),
+            "Domain error: nCr result is too large"
+        );
+        assert_eq!(
+            show(double_factorial(&r(1, 2))),
+            "Domain error: double factorial needs an integer"
+        );
+        let x = factorial(&0.5).unwrap();
+        assert!((x - std::f64::consts::PI.sqrt() / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_eval() {
+        let known_literals = &mut KnownLiterals::new();
+        assert_eq!(output("5!", known_literals), "120");
+        assert_eq!(output("-3!", known_literals), "-6");
+        assert_eq!(output("2^3!", known_literals), "64");
+        assert_eq!(output("(1 + 2)! 2", known_literals), "12");
+        assert_eq!(output("3!!", known_literals), "3");
+        assert_eq!(output("3! == 6", known_literals), "true");
+        assert_eq!(output("nCr(52, 5)", known_literals), "2598960");
+        assert_eq!(output("nPr(10, 3)", known_literals), "720");
+        assert_eq!(output("multinomial(3, 2, 1)", known_literals), "60");
+        assert_eq!(output("gamma(6)", known_literals), "120");
+        assert_eq!(
+            output("(1/2)! > 0.886 and (1/2)! < 0.887", known_literals),
+            "true"
+        );
+        assert_eq!(
+            output("gamma(i)", known_literals),
+            "Domain error: gamma of a complex number isn't supported"
+        );
+        assert_eq!(
+            output("(-2)!", known_literals),
+            "Domain error: factorial of a negative integer"
+        );
+        assert_eq!(
+            output("(2 m)!", known_literals),
+            "Dimension mismatch: m and a plain number"
+        );
+    }
+}
@@ -0,0 +1,551 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Span};
+use crate::number::Number;
+use crate::rational::Rational;
+use crate::symbolic::Symbolic;
+
+// Largest integer power expanded by repeated multiplication.
+const MAX_INT_POWER: i64 = 64;
+
+/// A complex value `re + im*i` with exact or approximated parts.
+///
+/// This is what expressions evaluate to by default, real inputs stay real
+/// unless something like `sqrt(-4)` or `i` brings in an imaginary part.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Complex {
+    pub re: Symbolic,
+    pub im: Symbolic,
+}
+
+impl Complex {
+    pub fn new(re: Symbolic, im: Symbolic) -> Self {
+        Self { re, im }
+    }
+
+    pub fn i() -> Self {
+        Self::new(Symbolic::zero(), Symbolic::one())
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im.is_zero()
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.re.is_exact() && self.im.is_exact()
+    }
+
+    /// The value with both parts as floats: `1.5 - 0.5i`.
+    pub fn to_float_string(&self) -> String {
+        let (re, im) = (self.re.to_float(), self.im.to_float());
+        if im == 0.0 {
+            format!("{re}")
+        } else if re == 0.0 {
+            format!("{im}i")
+        } else if im < 0.0 {
+            format!("{re} - {}i", -im)
+        } else {
+            format!("{re} + {im}i")
+        }
+    }
+
+    fn abs_sqr(&self) -> Symbolic {
+        self.re.clone() * self.re.clone() + self.im.clone() * self.im.clone()
+    }
+
+    // Unit complex number at the angle `x`.
+    fn cis(x: &Symbolic) -> Self {
+        Self::new(Number::cos(x), Number::sin(x))
+    }
+
+    fn pow_int(&self, n: i64) -> Self {
+        //Repeated squaring
+        let (mut base, mut acc) = (self.clone(), Complex::from(1));
+        let mut k = n.unsigned_abs();
+        while k > 0 {
+            if k & 1 == 1 {
+                acc = acc * base.clone();
+            }
+            k >>= 1;
+            if k > 0 {
+                base = base.clone() * base;
+            }
+        }
+        if n < 0 {
+            Complex::from(1) / acc
+        } else {
+            acc
+        }
+    }
+
+    // (e^b + e^-b)/2 and (e^b - e^-b)/2
+    fn cosh_sinh(x: &Symbolic) -> (Symbolic, Symbolic) {
+        let (a, b) = (Number::exp(x), Number::exp(&-x.clone()));
+        let half = Symbolic::from(Rational::new(1, 2));
+        ((a.clone() + b.clone()) * half.clone(), (a - b) * half)
+    }
+}
+
+impl Number for Complex {
+    type Context = ();
+
+    fn from_rational(x: &Rational, _: ()) -> Self {
+        x.clone().into()
+    }
+
+    fn from_float(x: f64, _: ()) -> Self {
+        Symbolic::approx(x).into()
+    }
+
+    fn from_symbolic(x: &Symbolic, _: ()) -> Self {
+        x.clone().into()
+    }
+
+    fn from_complex(x: &Complex, _: ()) -> Option<Self> {
+        Some(x.clone())
+    }
+
+    fn context(&self) {}
+
+    fn to_rational(&self) -> Option<Rational> {
+        self.is_real().then(|| self.re.to_rational())?
+    }
+
+    fn to_float(&self) -> f64 {
+        if self.is_real() {
+            self.re.to_float()
+        } else {
+            f64::NAN
+        }
+    }
+
+    /// The real part.
+    fn to_symbolic(&self) -> Symbolic {
+        self.re.clone()
+    }
+
+    fn to_complex(&self) -> Complex {
+        self.clone()
+    }
+
+    fn is_exact(&self) -> bool {
+        Complex::is_exact(self)
+    }
+
+    fn is_finite(&self) -> bool {
+        self.re.to_float().is_finite() && self.im.to_float().is_finite()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.re.is_zero() && self.im.is_zero()
+    }
+
+    fn i(_: ()) -> Option<Self> {
+        Some(Complex::i())
+    }
+
+    fn pi(_: ()) -> Self {
+        Symbolic::pi().into()
+    }
+
+    fn e(_: ()) -> Self {
+        Symbolic::e().into()
+    }
+
+    fn re(&self) -> Self {
+        self.re.clone().into()
+    }
+
+    fn im(&self) -> Self {
+        self.im.clone().into()
+    }
+
+    fn conj(&self) -> Self {
+        Complex::new(self.re.clone(), -self.im.clone())
+    }
+
+    fn arg(&self) -> Self {
+        Number::atan2(&self.im, &self.re).into()
+    }
+
+    fn abs(&self) -> Self {
+        if self.is_real() {
+            return self.re.abs().into();
+        }
+        if self.re.is_zero() {
+            return self.im.abs().into();
+        }
+        self.abs_sqr().sqrt().into()
+    }
+
+    fn signum(&self) -> Self {
+        if self.is_zero() {
+            return self.clone();
+        }
+        self.clone() / Number::abs(self)
+    }
+
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        if self.is_zero() {
+            return Ok(if x.is_zero() {
+                Complex::from(1)
+            } else {
+                self.clone()
+            });
+        }
+        if self.is_real() && x.is_real() {
+            if let Ok(y) = Number::pow(&self.re, &x.re) {
+                return Ok(y.into());
+            }
+            //(-a)^y = a^y * (cos(y*pi) + i*sin(y*pi))
+            let a = -self.re.clone();
+            let y = &x.re;
+            return Ok(Complex::from(a.pow(y)) * Complex::cis(&(y.clone() * Symbolic::pi())));
+        }
+        let n = x.to_rational().filter(|x| x.is_integer());
+        if let Some(n) = n.and_then(|n| num_traits::ToPrimitive::to_i64(n.numer())) {
+            if n.abs() <= MAX_INT_POWER {
+                return Ok(self.pow_int(n));
+            }
+        }
+        Ok((x.clone() * self.ln()).exp())
+    }
+
+    fn root(&self, n: u32) -> Result<Self, Error> {
+        self.pow(&Complex::from(Rational::new(1, n)))
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        if self.is_real() && x.is_real() {
+            return self.re.log(&x.re).into();
+        }
+        self.ln() / x.ln()
+    }
+
+    fn floor(&self) -> Self {
+        Complex::new(self.re.floor(), self.im.floor())
+    }
+
+    fn ceil(&self) -> Self {
+        Complex::new(self.re.ceil(), self.im.ceil())
+    }
+
+    fn round(&self) -> Self {
+        Complex::new(self.re.round(), self.im.round())
+    }
+
+    fn trunc(&self) -> Self {
+        Complex::new(self.re.trunc(), self.im.trunc())
+    }
+
+    //sin(a + bi) = sin(a)cosh(b) + i cos(a)sinh(b)
+    fn sin(&self) -> Self {
+        if self.is_real() {
+            return Number::sin(&self.re).into();
+        }
+        let (cosh, sinh) = Complex::cosh_sinh(&self.im);
+        Complex::new(Number::sin(&self.re) * cosh, Number::cos(&self.re) * sinh)
+    }
+
+    //cos(a + bi) = cos(a)cosh(b) - i sin(a)sinh(b)
+    fn cos(&self) -> Self {
+        if self.is_real() {
+            return Number::cos(&self.re).into();
+        }
+        let (cosh, sinh) = Complex::cosh_sinh(&self.im);
+        Complex::new(
+            Number::cos(&self.re) * cosh,
+            -(Number::sin(&self.re) * sinh),
+        )
+    }
+
+    fn tan(&self) -> Self {
+        if self.is_real() {
+            return Number::tan(&self.re).into();
+        }
+        self.sin() / self.cos()
+    }
+
+    //asin(z) = -i ln(iz + sqrt(1 - z^2))
+    fn asin(&self) -> Self {
+        if self.is_real() {
+            return Number::asin(&self.re).into();
+        }
+        let one = Complex::from(1);
+        let root = (one - self.clone() * self.clone())
+            .root(2)
+            .unwrap_or_else(|_| Complex::from_float(f64::NAN, ()));
+        -Complex::i() * (Complex::i() * self.clone() + root).ln()
+    }
+
+    fn acos(&self) -> Self {
+        if self.is_real() {
+            return Number::acos(&self.re).into();
+        }
+        Complex::from(Symbolic::pi() / 2.into()) - self.asin()
+    }
+
+    //atan(z) = i/2 (ln(1 - iz) - ln(1 + iz))
+    fn atan(&self) -> Self {
+        if self.is_real() {
+            return Number::atan(&self.re).into();
+        }
+        let (one, iz) = (Complex::from(1), Complex::i() * self.clone());
+        let half_i = Complex::new(Symbolic::zero(), Rational::new(1, 2).into());
+        half_i * ((one.clone() - iz.clone()).ln() - (one + iz).ln())
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        if self.is_real() && x.is_real() {
+            return Number::atan2(&self.re, &x.re).into();
+        }
+        Complex::from_float(f64::NAN, ())
+    }
+
+    //e^(a + bi) = e^a (cos(b) + i sin(b))
+    fn exp(&self) -> Self {
+        let a = Complex::from(Number::exp(&self.re));
+        if self.is_real() {
+            return a;
+        }
+        a * Complex::cis(&self.im)
+    }
+
+    //ln(z) = ln|z| + i arg(z)
+    fn ln(&self) -> Self {
+        if self.is_real() && self.re > Symbolic::zero() {
+            return Number::ln(&self.re).into();
+        }
+        let abs = Number::abs(self).re;
+        Complex::new(Number::ln(&abs), Number::arg(self).re)
+    }
+
+    fn gamma(&self) -> Result<Self, Error> {
+        if !self.is_real() {
+            return Err(Error::domain(
+                "gamma of a complex number isn't supported",
+                Span::default(),
+            ));
+        }
+        Number::gamma(&self.re).map(Complex::from)
+    }
+}
+
+impl From<Symbolic> for Complex {
+    fn from(x: Symbolic) -> Self {
+        Complex::new(x, Symbolic::zero())
+    }
+}
+
+impl From<Rational> for Complex {
+    fn from(x: Rational) -> Self {
+        Symbolic::from(x).into()
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(x: f64) -> Self {
+        Symbolic::from(x).into()
+    }
+}
+
+impl From<i64> for Complex {
+    fn from(x: i64) -> Self {
+        Symbolic::from(x).into()
+    }
+}
+
+/// Complex values are only ordered when both are real.
+impl PartialOrd for Complex {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.is_real() && other.is_real() {
+            self.re.partial_cmp(&other.re)
+        } else if self == other {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Debug for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_real() {
+            return write!(f, "{}", self.re);
+        }
+        let negative = self.im < Symbolic::zero();
+        let im = if negative && !self.re.is_zero() {
+            -self.im.clone()
+        } else {
+            self.im.clone()
+        };
+        let s = im.to_string();
+        let im = match im.to_rational() {
+            Some(x) if x == Rational::one() => String::from("i"),
+            Some(x) if x == -Rational::one() => String::from("-i"),
+            Some(x) if x.is_integer() => format!("{x}i"),
+            //A sum needs parentheses, `(1 + sqrt(2))*i`
+            _ if s[1..].contains(['+', '-']) => format!("({s})*i"),
+            _ => format!("{s}*i"),
+        };
+        match (self.re.is_zero(), negative) {
+            (true, _) => write!(f, "{im}"),
+            (false, true) => write!(f, "{} - {im}", self.re),
+            (false, false) => write!(f, "{} + {im}", self.re),
+        }
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+//(a + bi)(c + di) = (ac - bd) + (ad + bc)i
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        if self.is_real() && other.is_real() {
+            return (self.re * other.re).into();
+        }
+        let (a, b, c, d) = (self.re, self.im, other.re, other.im);
+        Complex::new(a.clone() * c.clone() - b.clone() * d.clone(), a * d + b * c)
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        if other.is_real() {
+            return Complex::new(self.re / other.re.clone(), self.im / other.re);
+        }
+        let d = other.abs_sqr();
+        let n = self * other.conj();
+        Complex::new(n.re / d.clone(), n.im / d)
+    }
+}
+
+impl std::ops::Rem for Complex {
+    type Output = Complex;
+    fn rem(self, other: Complex) -> Complex {
+        if self.is_real() && other.is_real() {
+            return (self.re % other.re).into();
+        }
+        Complex::from_float(f64::NAN, ())
+    }
+}
+
+impl std::ops::Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Complex;
+    #[cfg(test)]
+    use crate::{
+        error::Error,
+        number::Number,
+        parser::{test::output, KnownLiterals, Parse},
+        rational::Rational,
+        symbolic::Symbolic,
+        tokenizer::tokenize,
+    };
+
+    #[cfg(test)]
+    fn c(re: i64, im: i64) -> Complex {
+        Complex::new(re.into(), im.into())
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(c(2, 0).to_string(), "2");
+        assert_eq!(c(0, 2).to_string(), "2i");
+        assert_eq!(c(0, -1).to_string(), "-i");
+        assert_eq!(c(5, 5).to_string(), "5 + 5i");
+        assert_eq!(c(1, -1).to_string(), "1 - i");
+        assert_eq!(
+            Complex::new(Symbolic::zero(), Symbolic::from(2).sqrt()).to_string(),
+            "sqrt(2)*i"
+        );
+        assert_eq!(
+            Complex::new(1.into(), Rational::new(-1, 2).into()).to_string(),
+            "1 - 1/2*i"
+        );
+        assert_eq!(c(1, -2).to_float_string(), "1 - 2i");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(c(1, 2) * c(3, -1), c(5, 5));
+        assert_eq!(c(5, 5) / c(3, -1), c(1, 2));
+        assert_eq!(Complex::i() * Complex::i(), c(-1, 0));
+        assert_eq!(c(-4, 0).root(2), Ok(c(0, 2)));
+        assert_eq!(c(0, 1).pow(&c(2, 0)), Ok(c(-1, 0)));
+        assert_eq!(c(3, 4).abs(), c(5, 0));
+        assert_eq!(c(3, 4).conj(), c(3, -4));
+        assert_eq!(c(-1, 1).arg().to_string(), "3*pi/4");
+        assert_eq!(c(-1, 0).ln().to_string(), "pi*i");
+        assert!(c(1, 1).partial_cmp(&c(1, 0)).is_none());
+    }
+
+    #[test]
+    fn test_exp() {
+        let i_pi = Complex::new(Symbolic::zero(), Symbolic::pi());
+        assert_eq!(i_pi.exp(), c(-1, 0));
+        assert_eq!((i_pi / c(2, 0)).exp(), c(0, 1));
+        let x = c(1, 1).sin();
+        assert!((x.re.to_float() - 1.2984575814159773).abs() < 1e-12);
+        assert!((x.im.to_float() - 0.6349639147847361).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_eval() {
+        let known_literals = &mut KnownLiterals::new();
+        assert_eq!(output("sqrt(-4)", known_literals), "2i");
+        assert_eq!(output("sqrt(-2)", known_literals), "sqrt(2)*i");
+        assert_eq!(output("(1+2i)*(3-i)", known_literals), "5 + 5i");
+        assert_eq!(output("e^(i*pi)", known_literals), "-1");
+        assert_eq!(output("i^2", known_literals), "-1");
+        assert_eq!(output("1/i", known_literals), "-i");
+        assert_eq!(output("(-8)^(1/3)", known_literals), "-2");
+        assert_eq!(output("z = 3 + 4i", known_literals), "3 + 4i");
+        assert_eq!(output("abs(z)", known_literals), "5");
+        assert_eq!(output("re(z) + im(z)", known_literals), "7");
+        assert_eq!(output("conj(z)", known_literals), "3 - 4i");
+        assert_eq!(output("arg(i)", known_literals), "pi/2");
+        assert_eq!(output("arg(-1)", known_literals), "pi");
+        assert_eq!(
+            output("min(i, 1)", known_literals),
+            "Domain error: min needs real arguments"
+        );
+
+        let z = tokenize("z").parse_as::<f64>(known_literals, ());
+        assert!(matches!(z, Err(Error::Domain { .. })));
+        assert!(matches!(
+            tokenize("sqrt(-4)").parse_as::<f64>(known_literals, ()),
+            Err(Error::Domain { .. })
+        ));
+    }
+}
@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+
+use crate::bigfloat::BigFloat;
+use crate::error::Error;
+use crate::number::Number;
+use crate::rational::Rational;
+use crate::symbolic::Symbolic;
+
+// Mantissa bits per decimal place used for functions computed with `BigFloat`,
+// a little above log2(10).
+const BITS_PER_PLACE: f64 = 3.33;
+
+// Extra mantissa bits for functions computed with `BigFloat`.
+const EXTRA_BITS: u32 = 64;
+
+/// Fixed point decimal `units / 10^places`.
+///
+/// Results are rounded half to even to the number of places, values that
+/// had to be rounded are marked as inexact.
+#[derive(Clone)]
+pub struct Decimal {
+    units: BigInt,
+    places: u32,
+    inexact: bool,
+}
+
+// Mantissa size of a `BigFloat` that covers all the places.
+fn bits(places: u32) -> u32 {
+    (places as f64 * BITS_PER_PLACE) as u32 + EXTRA_BITS
+}
+
+fn scale(places: u32) -> BigInt {
+    BigInt::from(10).pow(places)
+}
+
+// `a / b` rounded half to even, `b` must be positive. Also returns whether
+// anything was rounded off.
+fn div_round(a: &BigInt, b: &BigInt) -> (BigInt, bool) {
+    let (q, r) = a.abs().div_rem(b);
+    let twice = &r * 2;
+    let rounded = !r.is_zero();
+    let q = if twice > *b || (twice == *b && q.is_odd()) {
+        q + 1
+    } else {
+        q
+    };
+    (if a.is_negative() { -q } else { q }, rounded)
+}
+
+impl Decimal {
+    pub fn places(&self) -> u32 {
+        self.places
+    }
+
+    fn rescale(&self, places: u32) -> BigInt {
+        &self.units * scale(places - self.places)
+    }
+
+    // Both operands on the larger number of places.
+    fn align(&self, other: &Decimal) -> (BigInt, BigInt, u32) {
+        let places = self.places.max(other.places);
+        (self.rescale(places), other.rescale(places), places)
+    }
+
+    fn to_big_float(&self) -> BigFloat {
+        BigFloat::from_rational(&self.exact(), bits(self.places))
+    }
+
+    fn from_big_float(x: BigFloat, places: u32) -> Decimal {
+        let x = x.to_rational().unwrap_or_else(Rational::zero);
+        Decimal::from_rational(&x.inexact_if(true), places)
+    }
+
+    fn via_big_float(&self, f: fn(&BigFloat) -> BigFloat) -> Decimal {
+        Decimal::from_big_float(f(&self.to_big_float()), self.places)
+    }
+
+    // The value as a fraction, ignoring whether it is rounded.
+    fn exact(&self) -> Rational {
+        Rational::new(self.units.clone(), scale(self.places)).reduce()
+    }
+}
+
+impl Number for Decimal {
+    /// Decimal places.
+    type Context = u32;
+
+    fn from_rational(x: &Rational, places: u32) -> Self {
+        let (units, rounded) = if x.denom().is_zero() {
+            (BigInt::zero(), true)
+        } else {
+            let q = x.denom();
+            let p = x.numer() * scale(places);
+            if q.is_negative() {
+                div_round(&-p, &-q)
+            } else {
+                div_round(&p, q)
+            }
+        };
+        Decimal {
+            units,
+            places,
+            inexact: rounded || !x.is_exact(),
+        }
+    }
+
+    fn from_float(x: f64, places: u32) -> Self {
+        if !x.is_finite() {
+            return Decimal {
+                units: BigInt::zero(),
+                places,
+                inexact: true,
+            };
+        }
+        Decimal::from_rational(&Rational::approx(x), places)
+    }
+
+    fn context(&self) -> u32 {
+        self.places
+    }
+
+    fn to_rational(&self) -> Option<Rational> {
+        Some(self.exact().inexact_if(self.inexact))
+    }
+
+    fn to_float(&self) -> f64 {
+        self.exact().to_float()
+    }
+
+    fn to_symbolic(&self) -> Symbolic {
+        self.exact().inexact_if(self.inexact).into()
+    }
+
+    fn is_exact(&self) -> bool {
+        !self.inexact
+    }
+
+    fn is_finite(&self) -> bool {
+        true
+    }
+
+    fn is_zero(&self) -> bool {
+        self.units.is_zero()
+    }
+
+    fn pi(places: u32) -> Self {
+        Decimal::from_big_float(BigFloat::pi(bits(places)), places)
+    }
+
+    fn e(places: u32) -> Self {
+        Decimal::from_big_float(BigFloat::e(bits(places)), places)
+    }
+
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        let places = self.places.max(x.places);
+        if let Some(n) = x
+            .to_rational()
+            .filter(|x| x.is_integer() && !self.is_zero())
+        {
+            let out = self.exact().pow_int(n.numer());
+            let inexact = self.inexact || x.inexact || !out.is_exact();
+            return Ok(Decimal::from_rational(&out.inexact_if(inexact), places));
+        }
+        let out = self.to_big_float().pow(&x.to_big_float())?;
+        Ok(Decimal::from_big_float(out, self.places))
+    }
+
+    fn root(&self, n: u32) -> Result<Self, Error> {
+        if let Some(y) = self.exact().nth_root(n) {
+            return Ok(Decimal::from_rational(
+                &y.inexact_if(self.inexact),
+                self.places,
+            ));
+        }
+        let out = self.to_big_float().root(n)?;
+        Ok(Decimal::from_big_float(out, self.places))
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        Decimal::from_big_float(self.to_big_float().log(&x.to_big_float()), self.places)
+    }
+
+    fn abs(&self) -> Self {
+        Decimal {
+            units: self.units.abs(),
+            ..self.clone()
+        }
+    }
+
+    fn sin(&self) -> Self {
+        self.via_big_float(BigFloat::sin)
+    }
+
+    fn cos(&self) -> Self {
+        self.via_big_float(BigFloat::cos)
+    }
+
+    fn tan(&self) -> Self {
+        self.via_big_float(BigFloat::tan)
+    }
+
+    fn asin(&self) -> Self {
+        self.via_big_float(BigFloat::asin)
+    }
+
+    fn acos(&self) -> Self {
+        self.via_big_float(BigFloat::acos)
+    }
+
+    fn atan(&self) -> Self {
+        self.via_big_float(BigFloat::atan)
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        Decimal::from_big_float(self.to_big_float().atan2(&x.to_big_float()), self.places)
+    }
+
+    fn exp(&self) -> Self {
+        self.via_big_float(BigFloat::exp)
+    }
+
+    fn ln(&self) -> Self {
+        self.via_big_float(BigFloat::ln)
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        let (x, y, _) = self.align(other);
+        x == y
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let (x, y, _) = self.align(other);
+        x.partial_cmp(&y)
+    }
+}
+
+impl std::fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = self.units.abs().to_string();
+        let places = self.places as usize;
+        let digits = format!("{digits:0>width$}", width = places + 1);
+        let (int, frac) = digits.split_at(digits.len() - places);
+        let sign = if self.units.is_negative() { "-" } else { "" };
+        if frac.is_empty() {
+            write!(f, "{sign}{int}")
+        } else {
+            write!(f, "{sign}{int}.{frac}")
+        }
+    }
+}
+
+impl std::ops::Add for Decimal {
+    type Output = Decimal;
+    fn add(self, other: Decimal) -> Decimal {
+        let (x, y, places) = self.align(&other);
+        Decimal {
+            units: x + y,
+            places,
+            inexact: self.inexact || other.inexact,
+        }
+    }
+}
+
+impl std::ops::Sub for Decimal {
+    type Output = Decimal;
+    fn sub(self, other: Decimal) -> Decimal {
+        self + -other
+    }
+}
+
+impl std::ops::Mul for Decimal {
+    type Output = Decimal;
+    fn mul(self, other: Decimal) -> Decimal {
+        let places = self.places.max(other.places);
+        let (units, rounded) = div_round(
+            &(&self.units * &other.units * scale(places)),
+            &scale(self.places + other.places),
+        );
+        Decimal {
+            units,
+            places,
+            inexact: rounded || self.inexact || other.inexact,
+        }
+    }
+}
+
+impl std::ops::Div for Decimal {
+    type Output = Decimal;
+    fn div(self, other: Decimal) -> Decimal {
+        let places = self.places.max(other.places);
+        if other.is_zero() {
+            return Decimal::from_float(f64::NAN, places);
+        }
+        let x = self.exact() / other.exact();
+        Decimal::from_rational(&x.inexact_if(self.inexact || other.inexact), places)
+    }
+}
+
+impl std::ops::Rem for Decimal {
+    type Output = Decimal;
+    fn rem(self, other: Decimal) -> Decimal {
+        let (x, y, places) = self.align(&other);
+        if y.is_zero() {
+            return Decimal::from_float(f64::NAN, places);
+        }
+        Decimal {
+            units: x % y,
+            places,
+            inexact: self.inexact || other.inexact,
+        }
+    }
+}
+
+impl std::ops::Neg for Decimal {
+    type Output = Decimal;
+    fn neg(self) -> Decimal {
+        Decimal {
+            units: -self.units,
+            ..self
+        }
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Decimal;
+    #[cfg(test)]
+    use crate::{number::Number, rational::Rational};
+
+    #[cfg(test)]
+    fn dec(p: i64, q: i64, places: u32) -> Decimal {
+        Decimal::from_rational(&Rational::new(p, q), places)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(dec(1, 3, 2).to_string(), "0.33");
+        assert!(!dec(1, 3, 2).is_exact());
+        assert!(dec(1, 4, 2).is_exact());
+        assert_eq!(dec(-1, 20, 2).to_string(), "-0.05");
+        assert_eq!(dec(25, 2, 2).to_string(), "12.50");
+        assert_eq!(dec(7, 1, 0).to_string(), "7");
+        //Half to even
+        assert_eq!(dec(5, 1000, 2).to_string(), "0.00");
+        assert_eq!(dec(15, 1000, 2).to_string(), "0.02");
+        assert_eq!((dec(1, 10, 2) + dec(2, 10, 2)).to_string(), "0.30");
+        assert_eq!((dec(1, 3, 4) * dec(3, 1, 4)).to_string(), "0.9999");
+        assert_eq!((dec(1, 1, 4) / dec(3, 1, 4)).to_string(), "0.3333");
+        assert_eq!((dec(7, 2, 1) % dec(1, 1, 1)).to_string(), "0.5");
+        assert!(dec(1, 3, 2) < dec(1, 2, 2));
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(Decimal::pi(20).to_string(), "3.14159265358979323846");
+        assert_eq!(dec(2, 1, 10).root(2).unwrap().to_string(), "1.4142135624");
+        assert_eq!(dec(9, 4, 3).root(2).unwrap().to_string(), "1.500");
+        assert!(dec(9, 4, 3).root(2).unwrap().is_exact());
+        assert_eq!(
+            dec(3, 2, 3).pow(&dec(3, 1, 3)).unwrap().to_string(),
+            "3.375"
+        );
+        assert_eq!(dec(1, 1, 8).exp().to_string(), "2.71828183");
+        assert_eq!(dec(100, 1, 4).log(&dec(10, 1, 4)).to_string(), "2.0000");
+    }
+}
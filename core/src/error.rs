@@ -0,0 +1,175 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::tokenizer::Token;
+
+/// Byte range in the original input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Tokenize {
+        lexeme: String,
+        span: Span,
+    },
+    UnexpectedToken {
+        token: Token,
+        span: Span,
+    },
+    UnexpectedEnd {
+        span: Span,
+    },
+    UnbalancedParentheses {
+        span: Span,
+    },
+    UnknownLiteral {
+        name: String,
+        span: Span,
+    },
+    Domain {
+        message: String,
+        span: Span,
+    },
+    DivisionByZero {
+        span: Span,
+    },
+    NotAFunction {
+        name: String,
+        span: Span,
+    },
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    RecursionLimit {
+        name: String,
+        span: Span,
+    },
+    /// Values with different units were combined, `3 m + 2 s`.
+    DimensionMismatch {
+        left: String,
+        right: String,
+        span: Span,
+    },
+}
+
+impl Error {
+    pub fn span(&self) -> Span {
+        match self {
+            Error::Tokenize { span, .. }
+            | Error::UnexpectedToken { span, .. }
+            | Error::UnexpectedEnd { span }
+            | Error::UnbalancedParentheses { span }
+            | Error::UnknownLiteral { span, .. }
+            | Error::Domain { span, .. }
+            | Error::DivisionByZero { span }
+            | Error::NotAFunction { span, .. }
+            | Error::ArityMismatch { span, .. }
+            | Error::RecursionLimit { span, .. }
+            | Error::DimensionMismatch { span, .. } => *span,
+        }
+    }
+
+    pub fn with_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            Error::Tokenize { span, .. }
+            | Error::UnexpectedToken { span, .. }
+            | Error::UnexpectedEnd { span }
+            | Error::UnbalancedParentheses { span }
+            | Error::UnknownLiteral { span, .. }
+            | Error::Domain { span, .. }
+            | Error::DivisionByZero { span }
+            | Error::NotAFunction { span, .. }
+            | Error::ArityMismatch { span, .. }
+            | Error::RecursionLimit { span, .. }
+            | Error::DimensionMismatch { span, .. } => *span = new_span,
+        }
+        self
+    }
+
+    /// Name of the variant for programs reading errors, `division_by_zero`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Tokenize { .. } => "tokenize",
+            Error::UnexpectedToken { .. } => "unexpected_token",
+            Error::UnexpectedEnd { .. } => "unexpected_end",
+            Error::UnbalancedParentheses { .. } => "unbalanced_parentheses",
+            Error::UnknownLiteral { .. } => "unknown_literal",
+            Error::Domain { .. } => "domain",
+            Error::DivisionByZero { .. } => "division_by_zero",
+            Error::NotAFunction { .. } => "not_a_function",
+            Error::ArityMismatch { .. } => "arity_mismatch",
+            Error::RecursionLimit { .. } => "recursion_limit",
+            Error::DimensionMismatch { .. } => "dimension_mismatch",
+        }
+    }
+
+    pub fn domain(message: impl Into<String>, span: Span) -> Self {
+        Error::Domain {
+            message: message.into(),
+            span,
+        }
+    }
+
+    pub fn division_by_zero(span: Span) -> Self {
+        Error::DivisionByZero { span }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Tokenize { lexeme, .. } => write!(f, "Invalid token: {lexeme:?}"),
+            Error::UnexpectedToken { token, .. } => write!(f, "Unexpected token: {token}"),
+            Error::UnexpectedEnd { .. } => write!(f, "Unexpected end of input"),
+            Error::UnbalancedParentheses { .. } => write!(f, "Unbalanced parentheses"),
+            Error::UnknownLiteral { name, .. } => write!(f, "Unknown literal: {name:?}"),
+            Error::Domain { message, .. } => write!(f, "Domain error: {message}"),
+            Error::DivisionByZero { .. } => write!(f, "Division by zero"),
+            Error::NotAFunction { name, .. } => write!(f, "{name:?} is not a function"),
+            Error::ArityMismatch {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "{name:?} takes {expected} argument(s) but {found} were given"
+            ),
+            Error::RecursionLimit { name, .. } => {
+                write!(f, "Recursion limit reached while calling {name:?}")
+            }
+            Error::DimensionMismatch { left, right, .. } => {
+                write!(f, "Dimension mismatch: {left} and {right}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
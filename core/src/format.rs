@@ -0,0 +1,132 @@
+use crate::bigfloat::BigFloat;
+use crate::complex::Complex;
+use crate::error::{Error, Span};
+use crate::number::Number;
+use crate::rational::{Rational, Rounding};
+use crate::symbolic::Symbolic;
+
+/// Significant digits of the formats that take them when none are given.
+pub const DEFAULT_DIGITS: u32 = 10;
+
+/// How results are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Fractions and exact forms, `sqrt(2)/2`.
+    #[default]
+    Exact,
+    Float,
+    /// Decimal places and how the dropped digits are rounded, `0.33`.
+    Fixed(u32, Rounding),
+    /// Whole part and proper fraction, `1 1/2`.
+    Mixed,
+    /// Significant digits with an exponent, `1.5e3`.
+    Scientific(u32),
+    /// Like `Scientific`, but the exponent is a multiple of three, `15e3`.
+    Engineering(u32),
+    /// Significant digits, `0.333`.
+    Significant(u32),
+    /// The repeating digits in parentheses, `0.(3)`.
+    Repeating,
+    /// Integers in base 16, 8 or 2, `0xff`.
+    Radix(u32),
+}
+
+impl Format {
+    /// Both parts of `x`, errors when the format can't show the value, like
+    /// a fraction in hex.
+    pub fn complex(&self, x: &Complex) -> Result<String, Error> {
+        match self {
+            Format::Exact => return Ok(x.to_string()),
+            Format::Float => return Ok(x.to_float_string()),
+            _ => {}
+        }
+        let re = self.real(&x.re)?;
+        if x.im.is_zero() {
+            return Ok(re);
+        }
+        let im = self.real(&x.im)?;
+        Ok(if x.re.is_zero() {
+            format!("{im}i")
+        } else if let Some(im) = im.strip_prefix('-') {
+            format!("{re} - {im}i")
+        } else {
+            format!("{re} + {im}i")
+        })
+    }
+
+    fn real(&self, x: &Symbolic) -> Result<String, Error> {
+        //Irrational values are evaluated with enough bits for the digits shown
+        let approx = |digits: u32| {
+            x.to_rational().or_else(|| {
+                let y: BigFloat = x.eval(digits * 4 + 64);
+                y.to_rational()
+            })
+        };
+        if let Format::Fixed(digits, _)
+        | Format::Scientific(digits)
+        | Format::Engineering(digits)
+        | Format::Significant(digits) = *self
+        {
+            Rational::check_places(digits)?;
+        }
+        let out = match *self {
+            Format::Exact => Some(x.to_string()),
+            Format::Float => Some(x.to_float().to_string()),
+            Format::Fixed(places, mode) => approx(places)
+                .map(|x| x.to_fixed(places, mode))
+                .transpose()?,
+            Format::Scientific(digits) => approx(digits).map(|x| x.to_scientific(digits, false)),
+            Format::Engineering(digits) => approx(digits).map(|x| x.to_scientific(digits, true)),
+            Format::Significant(digits) => approx(digits).map(|x| x.to_significant(digits)),
+            Format::Mixed => x.to_rational().map(|x| x.to_mixed()),
+            Format::Repeating => x.to_rational().map(|x| x.to_repeating()),
+            Format::Radix(radix) => {
+                return x
+                    .to_rational()
+                    .and_then(|x| x.to_radix(radix))
+                    .ok_or_else(|| {
+                        Error::domain(
+                            format!("{} output needs an integer", radix_name(radix)),
+                            Span::default(),
+                        )
+                    })
+            }
+        };
+        Ok(out.unwrap_or_else(|| x.to_float().to_string()))
+    }
+}
+
+fn radix_name(radix: u32) -> &'static str {
+    match radix {
+        16 => "hex",
+        8 => "octal",
+        2 => "binary",
+        _ => "radix",
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Format;
+    #[cfg(test)]
+    use crate::{complex::Complex, rational::Rational, rational::Rounding, symbolic::Symbolic};
+
+    #[test]
+    fn test_complex() {
+        let c = |x: Complex, f: Format| f.complex(&x).unwrap_or_else(|e| e.to_string());
+        let r = |p: i64, q: i64| Complex::from(Rational::new(p, q));
+        let sqrt2 = Complex::from(Symbolic::from(Rational::new(2, 1)).sqrt());
+        assert_eq!(c(r(3, 2), Format::Mixed), "1 1/2");
+        assert_eq!(c(r(1, 3), Format::Repeating), "0.(3)");
+        assert_eq!(c(r(255, 1), Format::Radix(16)), "0xff");
+        assert_eq!(
+            c(r(1, 2), Format::Radix(2)),
+            "Domain error: binary output needs an integer"
+        );
+        assert_eq!(c(sqrt2.clone(), Format::Significant(5)), "1.4142");
+        assert_eq!(c(sqrt2.clone(), Format::Scientific(3)), "1.41e0");
+        assert_eq!(c(sqrt2, Format::Fixed(3, Rounding::Floor)), "1.414");
+        let z = Complex::new(Rational::new(1, 3).into(), Rational::new(-3, 2).into());
+        assert_eq!(c(z, Format::Fixed(2, Rounding::HalfEven)), "0.33 - 1.50i");
+    }
+}
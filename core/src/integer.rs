@@ -0,0 +1,180 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::error::{Error, Span};
+use crate::number::Number;
+use crate::op::OpType;
+use crate::rational::Rational;
+
+// Largest shift `<<` and `>>` accept.
+const MAX_SHIFT: u64 = 1 << 16;
+
+/// Width integers wrap around to, like the registers of a CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordSize {
+    bits: u32,
+    signed: bool,
+}
+
+impl WordSize {
+    /// `None` unless `bits` is 8, 16, 32 or 64.
+    pub fn new(bits: u32, signed: bool) -> Option<Self> {
+        matches!(bits, 8 | 16 | 32 | 64).then_some(Self { bits, signed })
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    /// `x` reduced to the word, in two's complement when it is signed.
+    pub fn wrap_int(&self, x: &BigInt) -> BigInt {
+        let modulus = BigInt::one() << self.bits;
+        let x = x.mod_floor(&modulus);
+        if self.signed && x.bit(self.bits as u64 - 1) {
+            x - modulus
+        } else {
+            x
+        }
+    }
+
+    /// Wraps integer values, anything else is left alone.
+    pub fn wrap<N: Number>(&self, x: N) -> N {
+        match integer(&x) {
+            Some(n) if !x.is_bool() => {
+                let out = Rational::from(self.wrap_int(&n)).inexact_if(!x.is_exact());
+                N::from_rational(&out, x.context())
+            }
+            _ => x,
+        }
+    }
+}
+
+impl std::fmt::Display for WordSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.signed { "i" } else { "u" };
+        write!(f, "{sign}{}", self.bits)
+    }
+}
+
+/// The value as an integer, `None` for fractions and values with a unit.
+pub fn integer<N: Number>(x: &N) -> Option<BigInt> {
+    x.check_dimensionless().ok()?;
+    x.to_rational()
+        .filter(|x| x.is_integer())
+        .map(|x| x.reduce().numer().clone())
+}
+
+fn shift_amount(y: &BigInt, span: Span) -> Result<usize, Error> {
+    y.to_u64()
+        .filter(|y| *y <= MAX_SHIFT)
+        .map(|y| y as usize)
+        .ok_or_else(|| Error::domain("shift amount out of range", span))
+}
+
+/// Applies an operator that only takes integers: `&`, `|`, `xor`, `~`,
+/// `<<`, `>>`, `//`, `gcd` and `lcm`. `y` is `None` for `~`.
+pub fn apply<N: Number>(op_type: &OpType, x: &N, y: Option<&N>, span: Span) -> Result<N, Error> {
+    let needs_integers = || Error::domain(format!("{op_type} needs integer operands"), span);
+    let a = integer(x).ok_or_else(needs_integers)?;
+    let b = match y {
+        Some(y) => integer(y).ok_or_else(needs_integers)?,
+        None => BigInt::zero(),
+    };
+    let out = match op_type {
+        OpType::BitAnd => a & b,
+        OpType::BitOr => a | b,
+        OpType::Xor => a ^ b,
+        OpType::BitNot => !a,
+        OpType::Shl => a << shift_amount(&b, span)?,
+        //Rounds down, so negative numbers shift like in two's complement
+        OpType::Shr => a >> shift_amount(&b, span)?,
+        OpType::IntDiv if b.is_zero() => return Err(Error::division_by_zero(span)),
+        //Rounds down like Python's `//`
+        OpType::IntDiv => a.div_floor(&b),
+        OpType::Gcd => a.gcd(&b),
+        OpType::Lcm => a.lcm(&b).abs(),
+        _ => unreachable!("{op_type} isn't an integer operator"),
+    };
+    let exact = x.is_exact() && y.is_none_or(|y| y.is_exact());
+    Ok(N::from_rational(
+        &Rational::from(out).inexact_if(!exact),
+        x.context(),
+    ))
+}
+
+mod test {
+    #[cfg(test)]
+    use super::WordSize;
+    #[cfg(test)]
+    use crate::{
+        parser::{test::output, KnownLiterals, Parse},
+        quantity::Value,
+        tokenizer::tokenize,
+    };
+    #[cfg(test)]
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_wrap() {
+        let wrap = |bits, signed, x: i64| {
+            WordSize::new(bits, signed)
+                .unwrap()
+                .wrap_int(&BigInt::from(x))
+                .to_string()
+        };
+        assert_eq!(wrap(8, false, 256), "0");
+        assert_eq!(wrap(8, false, -1), "255");
+        assert_eq!(wrap(8, true, 128), "-128");
+        assert_eq!(wrap(8, true, 255), "-1");
+        assert_eq!(wrap(16, true, -32768), "-32768");
+        assert_eq!(wrap(32, false, 1 << 32), "0");
+        assert_eq!(wrap(64, true, i64::MIN), i64::MIN.to_string());
+        assert_eq!(WordSize::new(12, true), None);
+        assert_eq!(WordSize::new(16, false).unwrap().to_string(), "u16");
+    }
+
+    #[test]
+    fn test_eval() {
+        let known_literals = &mut KnownLiterals::new();
+        assert_eq!(output("12 & 10", known_literals), "8");
+        assert_eq!(output("12 | 3", known_literals), "15");
+        assert_eq!(output("6 xor 3", known_literals), "5");
+        assert_eq!(output("~5", known_literals), "-6");
+        assert_eq!(output("1 << 4", known_literals), "16");
+        assert_eq!(output("-16 >> 2", known_literals), "-4");
+        assert_eq!(output("7 // 2", known_literals), "3");
+        assert_eq!(output("-7 // 2", known_literals), "-4");
+        assert_eq!(output("12 gcd 18", known_literals), "6");
+        assert_eq!(output("4 lcm 6", known_literals), "12");
+        assert_eq!(output("1 + 2 << 3", known_literals), "24");
+        assert_eq!(output("1 | 2 & 3", known_literals), "3");
+        assert_eq!(output("2 ~1", known_literals), "-4");
+        assert_eq!(
+            output("3/2 & 1", known_literals),
+            "Domain error: & needs integer operands"
+        );
+        assert_eq!(
+            output("2 m | 1", known_literals),
+            "Domain error: | needs integer operands"
+        );
+        assert_eq!(output("1 // 0", known_literals), "Division by zero");
+
+        let mut wrapped = |s: &str, bits: u32, signed: bool| {
+            tokenize(s)
+                .parse_with::<Value>(known_literals, (), WordSize::new(bits, signed))
+                .map(|x| x.expect("expression").to_string())
+                .unwrap_or_else(|e| e.to_string())
+        };
+        assert_eq!(wrapped("255 + 1", 8, false), "0");
+        assert_eq!(wrapped("~0", 8, false), "255");
+        assert_eq!(wrapped("127 + 1", 8, true), "-128");
+        assert_eq!(wrapped("0xffff", 16, true), "-1");
+        assert_eq!(wrapped("1 << 40", 32, false), "0");
+        assert_eq!(wrapped("1/2", 32, false), "1/2");
+    }
+}
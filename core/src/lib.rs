@@ -1,4 +1,21 @@
+pub mod bigfloat;
+pub mod builtin;
+pub mod combinatorics;
+pub mod complex;
+pub mod decimal;
+pub mod error;
+pub mod format;
+pub mod integer;
+pub mod number;
 pub mod op;
 pub mod parser;
+pub mod pretty;
+pub mod quantity;
 pub mod rational;
+pub mod report;
+pub mod serial;
+pub mod symbolic;
 pub mod tokenizer;
+pub mod unit;
+
+pub use error::Error;
@@ -0,0 +1,427 @@
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use crate::complex::Complex;
+use crate::error::{Error, Span};
+use crate::quantity::Value;
+use crate::rational::Rational;
+use crate::symbolic::Symbolic;
+use crate::unit::Unit;
+
+// Largest odd denominator recognised in a float exponent, `(-8)^(1/3)`.
+const MAX_ODD_ROOT: u32 = 15;
+
+/// A numeric domain an expression can be evaluated in.
+///
+/// The same `Node` can be evaluated exactly with `Symbolic`, quickly with
+/// `f64`, with a fixed number of places with `Decimal` or with a chosen
+/// mantissa size with `BigFloat`. Operations without a real result return
+/// an `Error` with an empty span, the caller fills in where it happened.
+pub trait Number:
+    Sized
+    + Clone
+    + PartialOrd
+    + Display
+    + Debug
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + Neg<Output = Self>
+{
+    /// Settings shared by all values of one evaluation, like the number of
+    /// decimal places of a `Decimal`.
+    type Context: Copy + Debug;
+
+    fn from_rational(x: &Rational, ctx: Self::Context) -> Self;
+
+    /// Result of a float approximation.
+    fn from_float(x: f64, ctx: Self::Context) -> Self;
+
+    fn from_symbolic(x: &Symbolic, ctx: Self::Context) -> Self {
+        x.eval(ctx)
+    }
+
+    /// `None` when the value has an imaginary part the type can't hold.
+    fn from_complex(x: &Complex, ctx: Self::Context) -> Option<Self> {
+        x.is_real().then(|| Self::from_symbolic(&x.re, ctx))
+    }
+
+    /// A stored value, `None` for units outside of a `Quantity`.
+    fn from_quantity(x: &Value, ctx: Self::Context) -> Option<Self> {
+        if !x.dim.is_none() {
+            return None;
+        }
+        Self::from_complex(&x.value, ctx)
+    }
+
+    fn from_unit(_: &Unit, _: Self::Context) -> Result<Self, Error> {
+        Err(Error::domain(
+            "units need an evaluation with quantities",
+            Span::default(),
+        ))
+    }
+
+    /// `1` for true and `0` for false, types that can tell booleans apart
+    /// mark them as such.
+    fn from_bool(x: bool, ctx: Self::Context) -> Self {
+        if x {
+            Self::one(ctx)
+        } else {
+            Self::zero(ctx)
+        }
+    }
+
+    /// Reads a literal like `1.5e3` or `0x1F` exactly, then rounds it like
+    /// `from_rational`.
+    fn parse(s: &str, ctx: Self::Context) -> Result<Self, Error> {
+        let x = s.parse::<Rational>()?;
+        Ok(Self::from_rational(&x, ctx))
+    }
+
+    fn context(&self) -> Self::Context;
+
+    /// The exact value, `None` if it is irrational or not finite.
+    fn to_rational(&self) -> Option<Rational>;
+
+    fn to_float(&self) -> f64;
+
+    fn to_symbolic(&self) -> Symbolic;
+
+    fn to_complex(&self) -> Complex {
+        self.to_symbolic().into()
+    }
+
+    /// How the value is kept between evaluations in `KnownLiterals`.
+    fn to_quantity(&self) -> Value {
+        self.to_complex().into()
+    }
+
+    /// False once the value went through an approximation.
+    fn is_exact(&self) -> bool;
+
+    fn is_finite(&self) -> bool {
+        self.to_float().is_finite()
+    }
+
+    fn zero(ctx: Self::Context) -> Self {
+        Self::from_rational(&Rational::zero(), ctx)
+    }
+
+    fn one(ctx: Self::Context) -> Self {
+        Self::from_rational(&Rational::one(), ctx)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.to_rational().is_some_and(|x| x.is_zero())
+    }
+
+    /// Whether the value is the result of a comparison, not a number.
+    fn is_bool(&self) -> bool {
+        false
+    }
+
+    /// Errors unless both values have the same dimension, so they can be
+    /// added or compared.
+    fn check_compatible(&self, _: &Self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Errors unless the value is a plain number, like the argument of `sin`.
+    fn check_dimensionless(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The value to be shown in the unit `target`, called `name`.
+    fn convert(&self, _: &Self, _: &str) -> Result<Self, Error> {
+        Err(Error::domain(
+            "units need an evaluation with quantities",
+            Span::default(),
+        ))
+    }
+
+    /// The imaginary unit, real number types don't have one.
+    fn i(_: Self::Context) -> Option<Self> {
+        None
+    }
+
+    fn pi(ctx: Self::Context) -> Self {
+        Self::from_float(std::f64::consts::PI, ctx)
+    }
+
+    fn e(ctx: Self::Context) -> Self {
+        Self::from_float(std::f64::consts::E, ctx)
+    }
+
+    /// `self^x`, errors when the result isn't real.
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        float_pow(self.to_float(), x.to_float()).map(|y| Self::from_float(y, self.context()))
+    }
+
+    /// `n`th root, negative values only have odd roots.
+    fn root(&self, n: u32) -> Result<Self, Error> {
+        let ctx = self.context();
+        let zero = Self::zero(ctx);
+        if *self < zero && n.is_multiple_of(2) {
+            return Err(Error::domain(
+                "even root of a negative number",
+                Span::default(),
+            ));
+        }
+        let x = Self::from_rational(&Rational::new(1, n), ctx);
+        if *self < zero {
+            return Ok(-(-self.clone()).pow(&x)?);
+        }
+        self.pow(&x)
+    }
+
+    /// Logarithm of `self` in base `x`, both must be positive.
+    fn log(&self, x: &Self) -> Self {
+        Self::from_float(self.to_float().ln() / x.to_float().ln(), self.context())
+    }
+
+    fn floor(&self) -> Self {
+        self.integer_part(Rational::floor, f64::floor)
+    }
+
+    fn ceil(&self) -> Self {
+        self.integer_part(Rational::ceil, f64::ceil)
+    }
+
+    //Halves are rounded away from zero
+    fn round(&self) -> Self {
+        self.integer_part(Rational::round, f64::round)
+    }
+
+    fn trunc(&self) -> Self {
+        self.integer_part(Rational::trunc, f64::trunc)
+    }
+
+    fn integer_part(&self, exact: fn(&Rational) -> Rational, float: fn(f64) -> f64) -> Self {
+        match self.to_rational() {
+            Some(x) => Self::from_rational(&exact(&x), self.context()),
+            None => self.float_op(float),
+        }
+    }
+
+    fn abs(&self) -> Self {
+        if *self < Self::zero(self.context()) {
+            -self.clone()
+        } else {
+            self.clone()
+        }
+    }
+
+    fn signum(&self) -> Self {
+        let ctx = self.context();
+        match self.partial_cmp(&Self::zero(ctx)) {
+            Some(std::cmp::Ordering::Less) => -Self::one(ctx),
+            Some(std::cmp::Ordering::Greater) => Self::one(ctx),
+            _ => Self::zero(ctx),
+        }
+    }
+
+    fn re(&self) -> Self {
+        self.clone()
+    }
+
+    fn im(&self) -> Self {
+        Self::zero(self.context())
+    }
+
+    fn conj(&self) -> Self {
+        self.clone()
+    }
+
+    /// Angle to the positive real axis, `pi` for negative numbers.
+    fn arg(&self) -> Self {
+        Self::zero(self.context()).atan2(self)
+    }
+
+    fn float_op(&self, f: fn(f64) -> f64) -> Self {
+        Self::from_float(f(self.to_float()), self.context())
+    }
+
+    fn sin(&self) -> Self {
+        self.float_op(f64::sin)
+    }
+
+    fn cos(&self) -> Self {
+        self.float_op(f64::cos)
+    }
+
+    fn tan(&self) -> Self {
+        self.float_op(f64::tan)
+    }
+
+    fn asin(&self) -> Self {
+        self.float_op(f64::asin)
+    }
+
+    fn acos(&self) -> Self {
+        self.float_op(f64::acos)
+    }
+
+    fn atan(&self) -> Self {
+        self.float_op(f64::atan)
+    }
+
+    /// Angle of the point `(x, self)`.
+    fn atan2(&self, x: &Self) -> Self {
+        Self::from_float(self.to_float().atan2(x.to_float()), self.context())
+    }
+
+    fn exp(&self) -> Self {
+        self.float_op(f64::exp)
+    }
+
+    fn ln(&self) -> Self {
+        self.float_op(f64::ln)
+    }
+
+    /// Gamma function away from the integers, which `combinatorics::gamma`
+    /// handles exactly. Errors when the type can't evaluate it.
+    fn gamma(&self) -> Result<Self, Error> {
+        Ok(self.float_op(float_gamma))
+    }
+}
+
+// Lanczos approximation with g = 7.
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Gamma function on floats, with about 15 correct digits.
+pub fn float_gamma(x: f64) -> f64 {
+    //Reflection formula, the approximation only holds for x >= 1/2
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return pi / ((pi * x).sin() * float_gamma(1.0 - x));
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = LANCZOS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS[0], |sum, (k, c)| sum + c / (x + k as f64 + 1.0));
+    (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum
+}
+
+/// `x^y` on floats, negative bases are allowed for exponents with a small
+/// odd denominator.
+pub fn float_pow(x: f64, y: f64) -> Result<f64, Error> {
+    if x >= 0.0 || y.fract() == 0.0 {
+        return Ok(x.powf(y));
+    }
+    for q in (1..=MAX_ODD_ROOT).step_by(2) {
+        let p = y * q as f64;
+        if (p - p.round()).abs() < 1e-9 {
+            let out = (-x).powf(y);
+            return Ok(if p.round() % 2.0 == 0.0 { out } else { -out });
+        }
+    }
+    Err(Error::domain(
+        "fractional power of a negative number",
+        Span::default(),
+    ))
+}
+
+impl Number for f64 {
+    type Context = ();
+
+    fn from_rational(x: &Rational, _: ()) -> Self {
+        x.to_float()
+    }
+
+    fn from_float(x: f64, _: ()) -> Self {
+        x
+    }
+
+    fn from_symbolic(x: &Symbolic, _: ()) -> Self {
+        x.to_float()
+    }
+
+    fn context(&self) {}
+
+    fn to_rational(&self) -> Option<Rational> {
+        self.is_finite().then(|| Rational::from(*self))
+    }
+
+    fn to_float(&self) -> f64 {
+        *self
+    }
+
+    fn to_symbolic(&self) -> Symbolic {
+        Symbolic::approx(*self)
+    }
+
+    fn is_exact(&self) -> bool {
+        false
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == 0.0
+    }
+
+    fn pi(_: ()) -> Self {
+        std::f64::consts::PI
+    }
+
+    fn e(_: ()) -> Self {
+        std::f64::consts::E
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        f64::log(*self, *x)
+    }
+
+    fn integer_part(&self, _: fn(&Rational) -> Rational, float: fn(f64) -> f64) -> Self {
+        float(*self)
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::{float_pow, Number};
+    #[cfg(test)]
+    use crate::{bigfloat::BigFloat, decimal::Decimal, rational::Rational};
+
+    #[test]
+    fn test_float() {
+        assert_eq!(float_pow(-8.0, 1.0 / 3.0).map(|x| x.round()), Ok(-2.0));
+        assert_eq!(float_pow(-8.0, 2.0 / 3.0).map(|x| x.round()), Ok(4.0));
+        assert!(float_pow(-8.0, 0.5).is_err());
+        assert_eq!(Number::root(&-27.0, 3).map(|x: f64| x.round()), Ok(-3.0));
+        assert_eq!(Number::signum(&-2.5), -1.0);
+        assert_eq!(Number::round(&-2.5), -3.0);
+    }
+
+    #[test]
+    fn test_literals() {
+        //Literals are read exactly and only rounded by the backend
+        let long = "123456789012345678901234567890";
+        let exact: Rational = long.parse().unwrap();
+        assert_eq!(Rational::parse(long, ()), Ok(exact.clone()));
+        let x = Decimal::parse(long, 2).unwrap();
+        assert_eq!(x.to_rational(), Some(exact.clone()));
+        let x = BigFloat::parse(long, 200).unwrap();
+        assert_eq!(x.to_rational(), Some(exact));
+        assert_eq!(Decimal::parse("0.125", 2).unwrap().to_string(), "0.12");
+        for s in ["0x10", "0o20", "0b1_0000"] {
+            assert_eq!(Rational::parse(s, ()), Ok(Rational::from(16)));
+            assert_eq!(Decimal::parse(s, 2).unwrap().to_string(), "16.00");
+            assert_eq!(f64::parse(s, ()), Ok(16.0));
+        }
+        assert!(Rational::parse("0x", ()).is_err());
+        assert!(f64::parse("1e", ()).is_err());
+    }
+}
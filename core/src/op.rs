@@ -1,6 +1,111 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::builtin;
+use crate::builtin::Builtin;
+use crate::combinatorics;
+use crate::error::{Error, Span};
+use crate::integer::{self, WordSize};
+use crate::number::Number;
+use crate::parser::{KnownLiterals, Literal};
+use crate::quantity::Value;
 use crate::rational::Rational;
+use crate::unit::Unit;
+
+/// How deep user functions may call each other before evaluation gives up.
+pub const MAX_CALL_DEPTH: usize = 128;
+
+/// Names visible while evaluating a node: the arguments of the function
+/// currently being called, then everything in `known_literals`.
+pub struct Scope<'a, N: Number> {
+    pub known_literals: &'a KnownLiterals,
+    pub context: N::Context,
+    /// Integer results wrap around to this width when it is set.
+    pub word: Option<WordSize>,
+    locals: HashMap<String, N>,
+    depth: usize,
+}
+
+impl<'a, N: Number> Scope<'a, N> {
+    pub fn new(known_literals: &'a KnownLiterals, context: N::Context) -> Self {
+        Self {
+            known_literals,
+            context,
+            word: None,
+            locals: HashMap::new(),
+            depth: 0,
+        }
+    }
+
+    fn lookup(&self, name: &str, span: Span) -> Result<N, Error> {
+        if let Some(val) = self.locals.get(name) {
+            return Ok(val.clone());
+        }
+        match self.known_literals.get(name) {
+            Some(Literal::Value(val)) => N::from_quantity(val, self.context).ok_or_else(|| {
+                Error::domain(format!("{name} can't be held by this number type"), span)
+            }),
+            //A bare function name is a call without arguments
+            Some(Literal::Function(_)) => self.call(name, &[], span),
+            None => builtin::constant(name, self.context).ok_or(Error::UnknownLiteral {
+                name: name.to_owned(),
+                span,
+            }),
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Node], span: Span) -> Result<N, Error> {
+        let function = match self.known_literals.get(name) {
+            Some(Literal::Function(function)) => function,
+            Some(Literal::Value(_)) => {
+                return Err(Error::NotAFunction {
+                    name: name.to_owned(),
+                    span,
+                })
+            }
+            None => {
+                return Err(Error::UnknownLiteral {
+                    name: name.to_owned(),
+                    span,
+                })
+            }
+        };
+        if function.params.len() != args.len() {
+            return Err(Error::ArityMismatch {
+                name: name.to_owned(),
+                expected: function.params.len(),
+                found: args.len(),
+                span,
+            });
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(Error::RecursionLimit {
+                name: name.to_owned(),
+                span,
+            });
+        }
+        let mut locals = HashMap::new();
+        for (param, arg) in function.params.iter().zip(args) {
+            locals.insert(param.clone(), arg.eval(self)?);
+        }
+        let scope = Scope {
+            known_literals: self.known_literals,
+            context: self.context,
+            word: self.word,
+            locals,
+            depth: self.depth + 1,
+        };
+        // The body was parsed from another line, so its spans would point at
+        // the wrong place, report the failure at the call instead.
+        function.body.eval(&scope).map_err(|e| match e {
+            Error::RecursionLimit { .. } => e,
+            e => e.with_span(span),
+        })
+    }
+}
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum OpType {
     Mul,
     Div,
@@ -10,6 +115,27 @@ pub enum OpType {
     Root,
     Log,
     Mod,
+    Neg,
+    BitAnd,
+    BitOr,
+    Xor,
+    BitNot,
+    Shl,
+    Shr,
+    IntDiv,
+    Gcd,
+    Lcm,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+    Not,
+    Factorial,
+    DoubleFactorial,
 }
 
 impl OpType {
@@ -23,15 +149,94 @@ impl OpType {
             OpType::Root => true,
             OpType::Log => true,
             OpType::Mod => false,
+            OpType::Neg => true,
+            OpType::BitNot => true,
+            OpType::Not => true,
+            OpType::Factorial | OpType::DoubleFactorial => false,
+            OpType::BitAnd
+            | OpType::BitOr
+            | OpType::Xor
+            | OpType::Shl
+            | OpType::Shr
+            | OpType::IntDiv
+            | OpType::Gcd
+            | OpType::Lcm => false,
+            OpType::Equal
+            | OpType::NotEqual
+            | OpType::Less
+            | OpType::LessEqual
+            | OpType::Greater
+            | OpType::GreaterEqual
+            | OpType::And
+            | OpType::Or => false,
         }
     }
+    //Bitwise operators bind looser than arithmetic, like in C
     pub fn get_order(&self) -> u8 {
         match self {
-            OpType::Pow | OpType::Root | OpType::Log => 3,
-            OpType::Mul | OpType::Div | OpType::Mod => 2,
-            OpType::Add | OpType::Sub => 1,
+            OpType::Factorial | OpType::DoubleFactorial => 13,
+            OpType::Pow | OpType::Root | OpType::Log => 12,
+            OpType::Neg | OpType::BitNot => 11,
+            OpType::Mul
+            | OpType::Div
+            | OpType::Mod
+            | OpType::IntDiv
+            | OpType::Gcd
+            | OpType::Lcm => 10,
+            OpType::Add | OpType::Sub => 9,
+            OpType::Shl | OpType::Shr => 8,
+            OpType::BitAnd => 7,
+            OpType::Xor => 6,
+            OpType::BitOr => 5,
+            OpType::Equal
+            | OpType::NotEqual
+            | OpType::Less
+            | OpType::LessEqual
+            | OpType::Greater
+            | OpType::GreaterEqual => 4,
+            //`not x > 1` is `not (x > 1)`, like in Python
+            OpType::Not => 3,
+            OpType::And => 2,
+            OpType::Or => 1,
         }
     }
+    /// Operators that only take integers.
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            OpType::BitAnd
+                | OpType::BitOr
+                | OpType::Xor
+                | OpType::BitNot
+                | OpType::Shl
+                | OpType::Shr
+                | OpType::IntDiv
+                | OpType::Gcd
+                | OpType::Lcm
+        )
+    }
+    /// Comparisons and `and`, `or` and `not`, which give a boolean.
+    pub fn is_boolean(&self) -> bool {
+        matches!(
+            self,
+            OpType::Equal
+                | OpType::NotEqual
+                | OpType::Less
+                | OpType::LessEqual
+                | OpType::Greater
+                | OpType::GreaterEqual
+                | OpType::And
+                | OpType::Or
+                | OpType::Not
+        )
+    }
+    /// Written after the operand, `5!`.
+    pub fn is_postfix(&self) -> bool {
+        matches!(self, OpType::Factorial | OpType::DoubleFactorial)
+    }
+    pub fn is_right_assoc(&self) -> bool {
+        matches!(self, OpType::Pow)
+    }
     pub fn get_consume_count(&self) -> u8 {
         match self {
             OpType::Mul => 2,
@@ -42,26 +247,175 @@ impl OpType {
             OpType::Root => 1,
             OpType::Log => 2,
             OpType::Mod => 2,
+            OpType::Neg => 1,
+            OpType::BitNot => 1,
+            OpType::Not => 1,
+            OpType::Factorial | OpType::DoubleFactorial => 1,
+            OpType::BitAnd
+            | OpType::BitOr
+            | OpType::Xor
+            | OpType::Shl
+            | OpType::Shr
+            | OpType::IntDiv
+            | OpType::Gcd
+            | OpType::Lcm => 2,
+            OpType::Equal
+            | OpType::NotEqual
+            | OpType::Less
+            | OpType::LessEqual
+            | OpType::Greater
+            | OpType::GreaterEqual
+            | OpType::And
+            | OpType::Or => 2,
         }
     }
 }
-#[derive(Debug, Clone, PartialEq)]
+impl std::fmt::Display for OpType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OpType::Mul => "*",
+            OpType::Div => "/",
+            OpType::Add => "+",
+            OpType::Sub => "-",
+            OpType::Pow => "^",
+            OpType::Root => "sqrt",
+            OpType::Log => "log",
+            OpType::Mod => "%",
+            OpType::Neg => "-",
+            OpType::BitAnd => "&",
+            OpType::BitOr => "|",
+            OpType::Xor => "xor",
+            OpType::BitNot => "~",
+            OpType::Shl => "<<",
+            OpType::Shr => ">>",
+            OpType::IntDiv => "//",
+            OpType::Gcd => "gcd",
+            OpType::Lcm => "lcm",
+            OpType::Equal => "==",
+            OpType::NotEqual => "!=",
+            OpType::Less => "<",
+            OpType::LessEqual => "<=",
+            OpType::Greater => ">",
+            OpType::GreaterEqual => ">=",
+            OpType::And => "and",
+            OpType::Or => "or",
+            OpType::Not => "not",
+            OpType::Factorial => "!",
+            OpType::DoubleFactorial => "!!",
+        })
+    }
+}
+
+/// An `Op` together with the part of the input it was parsed from.
+///
+/// Spans are ignored when comparing nodes, so trees parsed from differently
+/// spaced input compare equal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    pub op: Op,
+    pub span: Span,
+}
+
+impl Node {
+    pub fn new(op: Op, span: Span) -> Self {
+        Self { op, span }
+    }
+
+    pub fn apply(&self, known_literals: &KnownLiterals) -> Result<Value, Error> {
+        self.apply_as(known_literals, ())
+    }
+
+    /// Evaluates the node with another number type, `ctx` holds its
+    /// settings like the precision.
+    pub fn apply_as<N: Number>(
+        &self,
+        known_literals: &KnownLiterals,
+        ctx: N::Context,
+    ) -> Result<N, Error> {
+        self.apply_with(known_literals, ctx, None)
+    }
+
+    /// Like `apply_as`, integer results wrap around to `word` when it is set.
+    pub fn apply_with<N: Number>(
+        &self,
+        known_literals: &KnownLiterals,
+        ctx: N::Context,
+        word: Option<WordSize>,
+    ) -> Result<N, Error> {
+        let mut scope = Scope::new(known_literals, ctx);
+        scope.word = word;
+        self.eval(&scope)
+    }
+
+    pub fn eval<N: Number>(&self, scope: &Scope<N>) -> Result<N, Error> {
+        self.op.apply(self.span, scope)
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.op == other.op
+    }
+}
+
+impl From<Op> for Node {
+    fn from(op: Op) -> Self {
+        Node::new(op, Span::default())
+    }
+}
+
+impl From<Op> for Box<Node> {
+    fn from(op: Op) -> Self {
+        Box::new(Node::from(op))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Op {
-    Mul(Box<Op>, Box<Op>),
-    Div(Box<Op>, Box<Op>),
-    Add(Box<Op>, Box<Op>),
-    Sub(Box<Op>, Box<Op>),
-    Pow(Box<Op>, Box<Op>),
-    Root(Box<Op>),
-    Log(Box<Op>, Box<Op>),
-    Mod(Box<Op>, Box<Op>),
+    Mul(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    Root(Box<Node>),
+    Log(Box<Node>, Box<Node>),
+    Mod(Box<Node>, Box<Node>),
+    Neg(Box<Node>),
+    BitAnd(Box<Node>, Box<Node>),
+    BitOr(Box<Node>, Box<Node>),
+    Xor(Box<Node>, Box<Node>),
+    BitNot(Box<Node>),
+    Shl(Box<Node>, Box<Node>),
+    Shr(Box<Node>, Box<Node>),
+    IntDiv(Box<Node>, Box<Node>),
+    Gcd(Box<Node>, Box<Node>),
+    Lcm(Box<Node>, Box<Node>),
+    Equal(Box<Node>, Box<Node>),
+    NotEqual(Box<Node>, Box<Node>),
+    Less(Box<Node>, Box<Node>),
+    LessEqual(Box<Node>, Box<Node>),
+    Greater(Box<Node>, Box<Node>),
+    GreaterEqual(Box<Node>, Box<Node>),
+    /// The right side is only evaluated when the left one is true.
+    And(Box<Node>, Box<Node>),
+    /// The right side is only evaluated when the left one is false.
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Factorial(Box<Node>),
+    DoubleFactorial(Box<Node>),
     Number(Rational),
+    Literal(String),
+    Call(String, Vec<Node>),
+    Builtin(Builtin, Vec<Node>),
+    Unit(Unit),
+    /// The value in the unit on the right, which is shown with its name.
+    Convert(Box<Node>, Box<Node>, String),
 }
 
 impl Op {
-    pub fn from_type(t: OpType, x: Option<Box<Op>>, y: Option<Box<Op>>) -> Self {
-        let x = x.unwrap_or(Box::new(Op::Number(Rational::zero())));
-        let y = y.unwrap_or(Box::new(Op::Number(Rational::zero())));
+    pub fn from_type(t: OpType, x: Option<Box<Node>>, y: Option<Box<Node>>) -> Self {
+        let x = x.unwrap_or(Op::Number(Rational::zero()).into());
+        let y = y.unwrap_or(Op::Number(Rational::zero()).into());
         match t {
             OpType::Mul => Op::Mul(x, y),
             OpType::Div => Op::Div(x, y),
@@ -71,48 +425,105 @@ impl Op {
             OpType::Root => Op::Root(x),
             OpType::Log => Op::Log(x, y),
             OpType::Mod => Op::Mod(x, y),
+            OpType::Neg => Op::Neg(x),
+            OpType::BitAnd => Op::BitAnd(x, y),
+            OpType::BitOr => Op::BitOr(x, y),
+            OpType::Xor => Op::Xor(x, y),
+            OpType::BitNot => Op::BitNot(x),
+            OpType::Shl => Op::Shl(x, y),
+            OpType::Shr => Op::Shr(x, y),
+            OpType::IntDiv => Op::IntDiv(x, y),
+            OpType::Gcd => Op::Gcd(x, y),
+            OpType::Lcm => Op::Lcm(x, y),
+            OpType::Equal => Op::Equal(x, y),
+            OpType::NotEqual => Op::NotEqual(x, y),
+            OpType::Less => Op::Less(x, y),
+            OpType::LessEqual => Op::LessEqual(x, y),
+            OpType::Greater => Op::Greater(x, y),
+            OpType::GreaterEqual => Op::GreaterEqual(x, y),
+            OpType::And => Op::And(x, y),
+            OpType::Or => Op::Or(x, y),
+            OpType::Not => Op::Not(x),
+            OpType::Factorial => Op::Factorial(x),
+            OpType::DoubleFactorial => Op::DoubleFactorial(x),
         }
     }
 
-    pub fn apply(&self) -> Rational {
+    /// Evaluates the operation, errors raised by this node point at `span`.
+    pub fn apply<N: Number>(&self, span: Span, scope: &Scope<N>) -> Result<N, Error> {
         let out = match self {
-            Op::Number(x) => *x,
-            Op::Mul(x, y) => x.apply() * y.apply(),
-            Op::Div(x, y) => x.apply() / y.apply(),
-            Op::Add(x, y) => x.apply() + y.apply(),
-            Op::Sub(x, y) => x.apply() - y.apply(),
-            Op::Pow(x, y) => x.apply().pow(y.apply()),
-            Op::Root(x) => x.apply().sqrt(),
-            Op::Log(x, y) => y.apply().log(x.apply()),
-            Op::Mod(x, y) => x.apply() % y.apply(),
+            Op::Number(x) => N::from_rational(x, scope.context),
+            Op::Unit(x) => N::from_unit(x, scope.context).map_err(|e| e.with_span(span))?,
+            Op::Convert(x, y, name) => {
+                let (x, y) = (x.eval(scope)?, y.eval(scope)?);
+                x.convert(&y, name).map_err(|e| e.with_span(span))?
+            }
+            Op::Literal(name) => scope.lookup(name, span)?,
+            Op::Call(name, args) => scope.call(name, args, span)?,
+            //Only the chosen branch is evaluated, `if(x == 0, 0, 1/x)`
+            Op::Builtin(Builtin::If, args) => {
+                //Trees loaded with `serial` haven't been through the parser
+                Builtin::If.check_arity(args.len(), span)?;
+                let branch = if args[0].eval(scope)?.is_zero() { 2 } else { 1 };
+                args[branch].eval(scope)?
+            }
+            Op::And(x, y) | Op::Or(x, y) => {
+                let x = x.eval(scope)?.is_zero();
+                //`x` alone decides when it is false for `and` or true for `or`
+                let out = match self {
+                    Op::And(..) if x => false,
+                    Op::Or(..) if !x => true,
+                    _ => !y.eval(scope)?.is_zero(),
+                };
+                N::from_bool(out, scope.context)
+            }
+            Op::Builtin(f, args) => {
+                let args = args
+                    .iter()
+                    .map(|x| x.eval(scope))
+                    .collect::<Result<Vec<_>, _>>()?;
+                f.apply(&args, span)?
+            }
+            Op::Root(x)
+            | Op::Neg(x)
+            | Op::BitNot(x)
+            | Op::Not(x)
+            | Op::Factorial(x)
+            | Op::DoubleFactorial(x) => {
+                let op_type = self.get_type().expect("operator");
+                operate(op_type, x.eval(scope)?, None, span, scope.context)?
+            }
+            Op::Mul(x, y)
+            | Op::Div(x, y)
+            | Op::Add(x, y)
+            | Op::Sub(x, y)
+            | Op::Pow(x, y)
+            | Op::Log(x, y)
+            | Op::Mod(x, y)
+            | Op::BitAnd(x, y)
+            | Op::BitOr(x, y)
+            | Op::Xor(x, y)
+            | Op::Shl(x, y)
+            | Op::Shr(x, y)
+            | Op::IntDiv(x, y)
+            | Op::Gcd(x, y)
+            | Op::Lcm(x, y)
+            | Op::Equal(x, y)
+            | Op::NotEqual(x, y)
+            | Op::Less(x, y)
+            | Op::LessEqual(x, y)
+            | Op::Greater(x, y)
+            | Op::GreaterEqual(x, y) => {
+                let op_type = self.get_type().expect("operator");
+                let (x, y) = (x.eval(scope)?, y.eval(scope)?);
+                operate(op_type, x, Some(y), span, scope.context)?
+            }
         };
         // println!("apply: self: {self:?} out: {out:?}");
-        out
-    }
-
-    pub fn get_y(&self) -> Option<Box<Op>> {
-        match self.clone() {
-            Op::Pow(_, y)
-            | Op::Log(_, y)
-            | Op::Mul(_, y)
-            | Op::Div(_, y)
-            | Op::Add(_, y)
-            | Op::Sub(_, y) => Some(y),
-            _ => None,
-        }
-    }
-    pub fn change_y(&mut self, new_y: Box<Op>) {
-        match self {
-            Op::Pow(_, ref mut y)
-            | Op::Log(_, ref mut y)
-            | Op::Mul(_, ref mut y)
-            | Op::Div(_, ref mut y)
-            | Op::Add(_, ref mut y)
-            | Op::Sub(_, ref mut y) => {
-                *y = new_y;
-            }
-            _ => {}
-        }
+        Ok(match scope.word {
+            Some(word) => word.wrap(out),
+            None => out,
+        })
     }
 
     pub fn get_type(&self) -> Option<OpType> {
@@ -125,7 +536,71 @@ impl Op {
             Op::Root(_) => Some(OpType::Root),
             Op::Log(_, _) => Some(OpType::Log),
             Op::Mod(_, _) => Some(OpType::Mod),
-            Op::Number(_) => None,
+            Op::Neg(_) => Some(OpType::Neg),
+            Op::BitAnd(_, _) => Some(OpType::BitAnd),
+            Op::BitOr(_, _) => Some(OpType::BitOr),
+            Op::Xor(_, _) => Some(OpType::Xor),
+            Op::BitNot(_) => Some(OpType::BitNot),
+            Op::Shl(_, _) => Some(OpType::Shl),
+            Op::Shr(_, _) => Some(OpType::Shr),
+            Op::IntDiv(_, _) => Some(OpType::IntDiv),
+            Op::Gcd(_, _) => Some(OpType::Gcd),
+            Op::Lcm(_, _) => Some(OpType::Lcm),
+            Op::Equal(_, _) => Some(OpType::Equal),
+            Op::NotEqual(_, _) => Some(OpType::NotEqual),
+            Op::Less(_, _) => Some(OpType::Less),
+            Op::LessEqual(_, _) => Some(OpType::LessEqual),
+            Op::Greater(_, _) => Some(OpType::Greater),
+            Op::GreaterEqual(_, _) => Some(OpType::GreaterEqual),
+            Op::And(_, _) => Some(OpType::And),
+            Op::Or(_, _) => Some(OpType::Or),
+            Op::Not(_) => Some(OpType::Not),
+            Op::Factorial(_) => Some(OpType::Factorial),
+            Op::DoubleFactorial(_) => Some(OpType::DoubleFactorial),
+            Op::Number(_)
+            | Op::Literal(_)
+            | Op::Call(_, _)
+            | Op::Builtin(_, _)
+            | Op::Unit(_)
+            | Op::Convert(_, _, _) => None,
+        }
+    }
+
+    /// The nodes the operation works on, the arguments of calls.
+    pub fn operands(&self) -> Vec<&Node> {
+        match self {
+            Op::Number(_) | Op::Literal(_) | Op::Unit(_) => Vec::new(),
+            Op::Call(_, args) | Op::Builtin(_, args) => args.iter().collect(),
+            Op::Root(x)
+            | Op::Neg(x)
+            | Op::BitNot(x)
+            | Op::Not(x)
+            | Op::Factorial(x)
+            | Op::DoubleFactorial(x) => vec![x],
+            Op::Convert(x, y, _)
+            | Op::Mul(x, y)
+            | Op::Div(x, y)
+            | Op::Add(x, y)
+            | Op::Sub(x, y)
+            | Op::Pow(x, y)
+            | Op::Log(x, y)
+            | Op::Mod(x, y)
+            | Op::BitAnd(x, y)
+            | Op::BitOr(x, y)
+            | Op::Xor(x, y)
+            | Op::Shl(x, y)
+            | Op::Shr(x, y)
+            | Op::IntDiv(x, y)
+            | Op::Gcd(x, y)
+            | Op::Lcm(x, y)
+            | Op::Equal(x, y)
+            | Op::NotEqual(x, y)
+            | Op::Less(x, y)
+            | Op::LessEqual(x, y)
+            | Op::Greater(x, y)
+            | Op::GreaterEqual(x, y)
+            | Op::And(x, y)
+            | Op::Or(x, y) => vec![x, y],
         }
     }
 
@@ -137,3 +612,98 @@ impl Op {
         }
     }
 }
+
+// An operator on its evaluated operands. Kept out of `Op::apply`, which
+// recursive functions go through, so its stack frames stay small.
+fn operate<N: Number>(
+    op_type: OpType,
+    x: N,
+    y: Option<N>,
+    span: Span,
+    ctx: N::Context,
+) -> Result<N, Error> {
+    let zero = N::zero(ctx);
+    if op_type.is_integer() {
+        return integer::apply(&op_type, &x, y.as_ref(), span);
+    }
+    if op_type.is_boolean() {
+        return compare(&op_type, &x, y.as_ref(), span).map(|out| N::from_bool(out, ctx));
+    }
+    let out = match (op_type, y) {
+        (OpType::Neg, _) => -x,
+        (OpType::Root, _) => x.root(2).map_err(|e| e.with_span(span))?,
+        (OpType::Factorial, _) => combinatorics::factorial(&x).map_err(|e| e.with_span(span))?,
+        (OpType::DoubleFactorial, _) => {
+            combinatorics::double_factorial(&x).map_err(|e| e.with_span(span))?
+        }
+        (OpType::Mul, Some(y)) => x * y,
+        (OpType::Div, Some(y)) => {
+            if y.is_zero() {
+                return Err(Error::division_by_zero(span));
+            }
+            x / y
+        }
+        (OpType::Add, Some(y)) => {
+            x.check_compatible(&y).map_err(|e| e.with_span(span))?;
+            x + y
+        }
+        (OpType::Sub, Some(y)) => {
+            x.check_compatible(&y).map_err(|e| e.with_span(span))?;
+            x - y
+        }
+        (OpType::Pow, Some(y)) => {
+            if x.is_zero() && y < zero {
+                return Err(Error::division_by_zero(span));
+            }
+            x.pow(&y).map_err(|e| e.with_span(span))?
+        }
+        (OpType::Log, Some(y)) => {
+            x.check_dimensionless()
+                .and(y.check_dimensionless())
+                .map_err(|e| e.with_span(span))?;
+            if x <= zero || x == N::one(ctx) {
+                return Err(Error::domain(
+                    "logarithm base must be positive and not 1",
+                    span,
+                ));
+            }
+            if y <= zero {
+                return Err(Error::domain("logarithm of a non-positive number", span));
+            }
+            y.log(&x)
+        }
+        (OpType::Mod, Some(y)) => {
+            x.check_compatible(&y).map_err(|e| e.with_span(span))?;
+            if y.is_zero() {
+                return Err(Error::division_by_zero(span));
+            }
+            x % y
+        }
+        (_, None) => unreachable!("binary operator without a right operand"),
+        (op_type, Some(_)) => unreachable!("{op_type} has its own evaluation"),
+    };
+    Ok(out)
+}
+
+// A comparison or `not`, values with units are only compared to values of
+// the same dimension.
+fn compare<N: Number>(op_type: &OpType, x: &N, y: Option<&N>, span: Span) -> Result<bool, Error> {
+    let Some(y) = y else {
+        return Ok(x.is_zero());
+    };
+    x.check_compatible(y).map_err(|e| e.with_span(span))?;
+    let equal = x == y || (x.clone() - y.clone()).is_zero();
+    let order = || {
+        x.partial_cmp(y)
+            .ok_or_else(|| Error::domain("complex numbers can't be ordered", span))
+    };
+    Ok(match op_type {
+        OpType::Equal => equal,
+        OpType::NotEqual => !equal,
+        OpType::Less => !equal && order()?.is_lt(),
+        OpType::LessEqual => equal || order()?.is_lt(),
+        OpType::Greater => !equal && order()?.is_gt(),
+        OpType::GreaterEqual => equal || order()?.is_gt(),
+        _ => unreachable!("{op_type} isn't a comparison"),
+    })
+}
@@ -1,313 +1,1232 @@
 use std::collections::HashMap;
 
-use crate::op::{Op, OpType};
-use crate::{rational::Rational, tokenizer::DbgDisplay, tokenizer::Token, tokenizer::TokenType};
+use serde::{Deserialize, Serialize};
 
-pub type KnownLiterals = HashMap<String, Rational>;
+use crate::builtin::Builtin;
+use crate::error::{Error, Span};
+use crate::integer::WordSize;
+use crate::number::Number;
+use crate::op::{Node, Op, OpType};
+use crate::quantity::Value;
+use crate::unit::Unit;
+use crate::{tokenizer::SpannedToken, tokenizer::Token};
 
-pub trait Parse {
-    fn parse(self, known_literals: &mut KnownLiterals) -> Result<Rational, String>;
+/// A user defined function, `f(x, y) = x^2 + y`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: Node,
 }
 
-impl Parse for Vec<Token> {
-    fn parse(self, known_literals: &mut KnownLiterals) -> Result<Rational, String> {
-        Ok(parse_to_operations(sanitase(self)?, known_literals)?.apply())
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Literal {
+    Value(Value),
+    Function(Function),
+}
+
+pub type KnownLiterals = HashMap<String, Literal>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Statement {
+    Expression(Node),
+    Assignment(String, Node),
+    Definition(String, Function),
+}
+
+impl Statement {
+    /// Parses a line without running it, `known_literals` tells functions
+    /// apart from variables and units.
+    pub fn new(data: Vec<SpannedToken>, known_literals: &KnownLiterals) -> Result<Self, Error> {
+        parse_statement(sanitase(data, known_literals)?)
+    }
+
+    /// The variable or function the statement defines.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Statement::Expression(_) => None,
+            Statement::Assignment(name, _) | Statement::Definition(name, _) => Some(name),
+        }
+    }
+
+    /// Runs the statement like `Parse::parse_with`.
+    pub fn run<N: Number>(
+        self,
+        known_literals: &mut KnownLiterals,
+        ctx: N::Context,
+        word: Option<WordSize>,
+    ) -> Result<Option<N>, Error> {
+        match self {
+            Statement::Expression(node) => Ok(Some(node.apply_with(known_literals, ctx, word)?)),
+            Statement::Assignment(name, node) => {
+                let val: N = node.apply_with(known_literals, ctx, word)?;
+                known_literals.insert(name, Literal::Value(val.to_quantity()));
+                Ok(Some(val))
+            }
+            Statement::Definition(name, function) => {
+                known_literals.insert(name, Literal::Function(function));
+                Ok(None)
+            }
+        }
     }
 }
 
-impl Parse for Vec<(String, Token)> {
-    fn parse(self, known_literals: &mut KnownLiterals) -> Result<Rational, String> {
-        println!(
-            "{}",
-            self.dbg()
-                .unwrap_or(String::from("Failed to display token stream"))
-        );
-        let out = self.into_iter().map(|x| x.1).collect();
-        Ok(parse_to_operations(sanitase(out)?, known_literals)?.apply())
+pub trait Parse: Sized {
+    /// Runs the statement, definitions don't produce a value.
+    fn parse(self, known_literals: &mut KnownLiterals) -> Result<Option<Value>, Error> {
+        self.parse_as(known_literals, ())
     }
+
+    /// Like `parse`, but evaluates with the number type `N`.
+    fn parse_as<N: Number>(
+        self,
+        known_literals: &mut KnownLiterals,
+        ctx: N::Context,
+    ) -> Result<Option<N>, Error> {
+        self.parse_with(known_literals, ctx, None)
+    }
+
+    /// Like `parse_as`, integer results wrap around to `word` when it is set.
+    fn parse_with<N: Number>(
+        self,
+        known_literals: &mut KnownLiterals,
+        ctx: N::Context,
+        word: Option<WordSize>,
+    ) -> Result<Option<N>, Error>;
 }
 
-fn sanitase(mut data: Vec<Token>) -> Result<Vec<Token>, String> {
-    if data.len() == 1 || data.is_empty() {
-        return Ok(data);
-    }
-    if data.contains(&Token::Invalid) {
-        return Err(String::from("Stream contains invalid tokens"));
-    }
-    let mut prev_type = TokenType::Invalid;
-    for (i, j) in data.clone().iter().enumerate() {
-        let type_ = j.get_type();
-        let op_append = false;
-        // if type_ == TokenType::Op {
-        //     if j.as_op().unwrap().is_forward() {
-        //         op_append = true;
-        //     }
-        // }
-        if (type_ == TokenType::Number || type_ == TokenType::Literal || op_append)
-            && (prev_type == TokenType::Number || prev_type == TokenType::Literal)
+impl Parse for Vec<SpannedToken> {
+    fn parse_with<N: Number>(
+        self,
+        known_literals: &mut KnownLiterals,
+        ctx: N::Context,
+        word: Option<WordSize>,
+    ) -> Result<Option<N>, Error> {
+        Statement::new(self, known_literals)?.run(known_literals, ctx, word)
+    }
+}
+
+fn invalid_token(data: &[SpannedToken]) -> Option<Error> {
+    data.iter()
+        .find(|x| x.token == Token::Invalid)
+        .map(|x| Error::Tokenize {
+            lexeme: x.lexeme.clone(),
+            span: x.span,
+        })
+}
+
+// Name of the function a `f(x, y) = ...` statement defines.
+fn defined_function(data: &[SpannedToken]) -> Option<&str> {
+    match data {
+        [SpannedToken {
+            token: Token::Literal(name),
+            ..
+        }, SpannedToken {
+            token: Token::OpenP,
+            ..
+        }, ..]
+            if data.iter().any(|x| x.token == Token::Eq) =>
         {
-            if i >= 2 {
-                if !(data[i - 2].get_type() == TokenType::Op
-                    && data[i - 2].as_op_type().unwrap().is_forward())
-                {
-                    data.insert(i, Token::Op(OpType::Mul));
+            Some(name)
+        }
+        _ => None,
+    }
+}
+
+// Names in a statement that must stay literals even if they spell a unit:
+// the variable being assigned and the name and parameters of a function.
+fn bound_names(data: &[SpannedToken]) -> Vec<&str> {
+    if let [SpannedToken {
+        token: Token::Literal(name),
+        ..
+    }, SpannedToken {
+        token: Token::Eq, ..
+    }, ..] = data
+    {
+        return vec![name];
+    }
+    let Some(name) = defined_function(data) else {
+        return Vec::new();
+    };
+    let mut names = vec![name];
+    for x in data[2..].iter().take_while(|x| x.token != Token::CloseP) {
+        if let Token::Literal(param) = &x.token {
+            names.push(param);
+        }
+    }
+    names
+}
+
+// Whether `data` starts with a parenthesized list of more than one argument.
+fn is_arg_list(data: &[SpannedToken]) -> bool {
+    if data.first().is_none_or(|x| x.token != Token::OpenP) {
+        return false;
+    }
+    let mut depth = 0;
+    for x in data {
+        match x.token {
+            Token::OpenP => depth += 1,
+            Token::CloseP => {
+                depth -= 1;
+                if depth == 0 {
+                    return false;
                 }
-            } else {
-                data.insert(i, Token::Op(OpType::Mul));
             }
+            Token::Comma if depth == 1 => return true,
+            _ => {}
         }
-        prev_type = type_;
     }
+    false
+}
 
-    // println!("After sanitase:\n{}", data.dbg().unwrap());
-    Ok(data)
+// Turns literals into units and `in`/`to` into conversions. Anything with a
+// known name keeps being a literal, so variables shadow units.
+fn resolve_units(data: Vec<SpannedToken>, known_literals: &KnownLiterals) -> Vec<SpannedToken> {
+    let bound: Vec<String> = bound_names(&data).into_iter().map(str::to_owned).collect();
+    let is_convert =
+        |x: &SpannedToken| matches!(&x.token, Token::Literal(name) if name == "in" || name == "to");
+    let mut ret = Vec::with_capacity(data.len());
+    for (pos, token) in data.iter().enumerate() {
+        let Token::Literal(name) = &token.token else {
+            ret.push(token.clone());
+            continue;
+        };
+        let next = data.get(pos + 1);
+        let known = known_literals.contains_key(name) || bound.contains(name);
+        if known || matches!(name.as_str(), "pi" | "e" | "i") {
+            ret.push(token.clone());
+            continue;
+        }
+        //`root 16` and `root(16)` are square roots, `root(3, 8)` the builtin
+        if name == "root" && !is_arg_list(&data[pos + 1..]) {
+            ret.push(SpannedToken::new(
+                Token::Op(OpType::Root),
+                token.span,
+                &token.lexeme,
+            ));
+            continue;
+        }
+        //A conversion needs a unit after it, otherwise it is inches. In
+        //`12 in to cm` the first `in` is inches, so is `2 in 3`.
+        if is_convert(token)
+            && pos > 0
+            && next.is_some_and(|x| match &x.token {
+                Token::OpenP | Token::Unit(_) => true,
+                Token::Literal(name) => {
+                    !is_convert(x)
+                        && !known_literals.contains_key(name)
+                        && !bound.contains(name)
+                        && Unit::parse(&x.lexeme).is_some()
+                }
+                _ => false,
+            })
+        {
+            ret.push(SpannedToken::new(Token::Convert, token.span, &token.lexeme));
+            continue;
+        }
+        //Calls stay calls, `h(1)` is an unknown function and not hours
+        if next.is_some_and(|x| x.token == Token::OpenP) {
+            ret.push(token.clone());
+            continue;
+        }
+        match Unit::parse(&token.lexeme) {
+            Some(unit) => ret.push(SpannedToken::new(
+                Token::Unit(Box::new(unit)),
+                token.span,
+                &token.lexeme,
+            )),
+            None => ret.push(token.clone()),
+        }
+    }
+    ret
 }
 
-macro_rules! next_token {
-    ($data: expr,$idx: expr,$i:expr) => {
-        Box::new(Op::Number(
-            $data
-                .get($idx + $i)
-                .ok_or(format!("Expected token at: {}", $idx + $i))?
-                .as_nr()
-                .ok_or(String::from("Number expected."))?,
-        ))
+fn sanitase(
+    data: Vec<SpannedToken>,
+    known_literals: &KnownLiterals,
+) -> Result<Vec<SpannedToken>, Error> {
+    if let Some(e) = invalid_token(&data) {
+        return Err(e);
+    }
+    let data = resolve_units(data, known_literals);
+    let defined = defined_function(&data).map(str::to_owned);
+    let is_function = |token: &Token| match token {
+        Token::Literal(name) => {
+            matches!(known_literals.get(name), Some(Literal::Function(_)))
+                || defined.as_ref() == Some(name)
+                || Builtin::from_name(name).is_some()
+        }
+        _ => false,
     };
+    let mut ret: Vec<SpannedToken> = Vec::with_capacity(data.len());
+    for token in data {
+        //Implicit multiplication between two neighbouring operands
+        if starts_operand(&token.token)
+            && ret.last().is_some_and(|x| ends_operand(&x.token))
+            && !awaits_operand(&ret)
+            && !(token.token == Token::OpenP && ret.last().is_some_and(|x| is_function(&x.token)))
+        {
+            let span = Span::new(token.span.start, token.span.start);
+            ret.push(SpannedToken::new(Token::Op(OpType::Mul), span, ""));
+        }
+        ret.push(token);
+    }
+
+    // println!("After sanitase:\n{}", ret.dbg().unwrap());
+    Ok(ret)
 }
 
-macro_rules! prev_token {
-    ($prev_token: expr) => {
-        Box::new(Op::Number($prev_token.as_nr().ok_or("Number expected.")?))
-    };
+fn starts_operand(token: &Token) -> bool {
+    match token {
+        Token::Number(_) | Token::Literal(_) | Token::Unit(_) | Token::OpenP => true,
+        Token::Op(op_type) => op_type.is_forward(),
+        _ => false,
+    }
 }
 
-fn parse_to_operations(data: Vec<Token>, known_literals: &mut KnownLiterals) -> Result<Op, String> {
-    // println!("parse_to_operations:");
-    if data.contains(&Token::Invalid) {
-        return Err(String::from("Stream contains invalid tokens"));
+fn ends_operand(token: &Token) -> bool {
+    match token {
+        Token::Number(_) | Token::Literal(_) | Token::Unit(_) | Token::CloseP => true,
+        Token::Op(op_type) => op_type.is_postfix(),
+        _ => false,
     }
+}
 
-    let data = data
-        .iter()
-        .map(|token| {
-            //Proactive literal lookup
-            if let Token::Literal(lit) = token.clone() {
-                if let Some(val) = known_literals.get(&lit) {
-                    return Token::Number(*val);
-                }
+// Whether the last operand in `data` is an argument of a forward operator
+// that still expects another one, e.g. the `2` in `log 2 8`.
+fn awaits_operand(data: &[SpannedToken]) -> bool {
+    let mut start = data.len() - 1;
+    if data[start].token == Token::CloseP {
+        let mut depth = 0;
+        loop {
+            match data[start].token {
+                Token::CloseP => depth += 1,
+                Token::OpenP => depth -= 1,
+                _ => {}
             }
-            return token.clone();
-        })
-        .collect::<Vec<_>>();
-
-    if data.len() <= 1 {
-        return Ok(Op::Number(
-            data.first()
-                .ok_or("Stream empty")?
-                .as_nr()
-                .ok_or("Failed to parse stream.")?,
-        ));
+            if depth == 0 || start == 0 {
+                break;
+            }
+            start -= 1;
+        }
     }
+    start > 0
+        && data[start - 1]
+            .token
+            .as_op_type()
+            .is_some_and(|x| x.is_forward() && x.get_consume_count() > 1)
+}
 
-    let mut prev_token = Token::Invalid;
-    let mut skip = 0;
-    let mut ret = Op::Number(Rational::zero());
+struct Parser<'a> {
+    data: &'a [SpannedToken],
+    pos: usize,
+}
 
-    for i in 0..data.len() {
-        let token = data.get(i).unwrap();
-        if skip > 0 {
-            skip -= 1;
-            prev_token = token.clone();
-            continue;
+impl<'a> Parser<'a> {
+    fn new(data: &'a [SpannedToken]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a SpannedToken> {
+        self.data.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<&'a SpannedToken, Error> {
+        let token = self.data.get(self.pos).ok_or(Error::UnexpectedEnd {
+            span: self.end_span(),
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<&'a SpannedToken, Error> {
+        let token = self.next()?;
+        if token.token != expected {
+            return Err(Error::UnexpectedToken {
+                token: token.token.clone(),
+                span: token.span,
+            });
         }
+        Ok(token)
+    }
 
-        if let Some(op_type) = token.as_op_type() {
-            let reverse = ret.get_order() < op_type.get_order() && ret.get_order() != 0;
+    fn end_span(&self) -> Span {
+        let end = self.data.last().map(|x| x.span.end).unwrap_or_default();
+        Span::new(end, end)
+    }
 
-            skip += op_type.get_consume_count();
-            if op_type.is_forward() {
-                if reverse {
-                    todo!();
-                }
-                if op_type == OpType::Root {
-                    ret = Op::from_type(op_type, Some(next_token!(data, i, 1)), None);
-                } else {
-                    ret = Op::from_type(
-                        op_type,
-                        Some(next_token!(data, i, 1)),
-                        Some(next_token!(data, i, 2)),
-                    );
-                }
+    //Precedence climbing, only operators of at least `min_order` are consumed
+    fn expression(&mut self, min_order: u8) -> Result<Node, Error> {
+        let mut lhs = self.operand()?;
+        while let Some(op_type) = self.peek().and_then(|x| x.token.as_op_type()) {
+            let order = op_type.get_order();
+            if op_type.is_forward() || op_type.is_postfix() || order < min_order {
+                break;
+            }
+            self.pos += 1;
+            let next_order = if op_type.is_right_assoc() {
+                order
             } else {
-                skip -= 1;
-                let mut prev = if ret != Op::Number(Rational::zero()) {
-                    Box::new(ret.clone())
-                } else {
-                    prev_token!(prev_token)
-                };
+                order + 1
+            };
+            let rhs = self.expression(next_order)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Node::new(
+                Op::from_type(op_type, Some(Box::new(lhs)), Some(Box::new(rhs))),
+                span,
+            );
+        }
+        Ok(lhs)
+    }
+
+    // Comma separated expressions up to the closing parenthesis, the opening
+    // one is already consumed.
+    fn arguments(&mut self) -> Result<(Vec<Node>, Span), Error> {
+        let mut args = Vec::new();
+        if let Some(close) = self.peek().filter(|x| x.token == Token::CloseP) {
+            self.pos += 1;
+            return Ok((args, close.span));
+        }
+        loop {
+            args.push(self.expression(1)?);
+            let token = self.next()?;
+            match token.token {
+                Token::Comma => {}
+                Token::CloseP => return Ok((args, token.span)),
+                _ => {
+                    return Err(Error::UnexpectedToken {
+                        token: token.token.clone(),
+                        span: token.span,
+                    })
+                }
+            }
+        }
+    }
+
+    // An operand with the postfix operators after it, `3!`.
+    fn operand(&mut self) -> Result<Node, Error> {
+        let mut x = self.primary()?;
+        while let Some((op_type, span)) = self
+            .peek()
+            .and_then(|x| Some((x.token.as_op_type()?, x.span)))
+            .filter(|x| x.0.is_postfix())
+        {
+            self.pos += 1;
+            let span = x.span.to(span);
+            x = Node::new(Op::from_type(op_type, Some(Box::new(x)), None), span);
+        }
+        Ok(x)
+    }
 
-                if reverse {
-                    if let Some(y) = prev.get_y() {
-                        prev.change_y(Box::new(Op::from_type(
-                            op_type,
-                            Some(y),
-                            Some(next_token!(data, i, 1)),
-                        )));
-                        ret = *prev.clone();
+    fn primary(&mut self) -> Result<Node, Error> {
+        let SpannedToken { token, span, .. } = self.next()?;
+        match token {
+            Token::Number(x) => Ok(Node::new(Op::Number(x.clone()), *span)),
+            Token::Unit(x) => Ok(Node::new(Op::Unit(*x.clone()), *span)),
+            Token::Literal(lit) => {
+                if self.peek().is_some_and(|x| x.token == Token::OpenP) {
+                    self.pos += 1;
+                    let (args, close) = self.arguments()?;
+                    let span = span.to(close);
+                    let Some(f) = Builtin::from_name(lit) else {
+                        return Ok(Node::new(Op::Call(lit.clone(), args), span));
+                    };
+                    if !f.accepts(args.len()) {
+                        return Err(Error::ArityMismatch {
+                            name: lit.clone(),
+                            expected: f.arity(),
+                            found: args.len(),
+                            span,
+                        });
                     }
-                } else {
-                    ret = Op::from_type(op_type, Some(prev), Some(next_token!(data, i, 1)));
+                    return Ok(Node::new(Op::Builtin(f, args), span));
                 }
+                Ok(Node::new(Op::Literal(lit.clone()), *span))
             }
-        } else if token.get_type() == TokenType::Eq {
-            if let Token::Literal(prev) = prev_token.clone() {
-                let next_token = data
-                    .get(i + 1)
-                    .ok_or(format!("Expected token at: {}", i + 1))?;
-                if let Token::Literal(next) = next_token {
-                    if let Some(next) = known_literals.get(next) {
-                        ret = Op::Number(next.clone());
-                        known_literals.insert(prev, *next);
+            Token::OpenP => {
+                let mut ret = self.expression(1)?;
+                match self.peek() {
+                    Some(SpannedToken {
+                        token: Token::CloseP,
+                        span: close,
+                        ..
+                    }) => {
+                        self.pos += 1;
+                        ret.span = span.to(*close);
+                        Ok(ret)
                     }
-                } else if let Token::Number(next) = next_token {
-                    ret = Op::Number(next.clone());
-                    known_literals.insert(prev, *next);
+                    Some(SpannedToken { token, span, .. }) => Err(Error::UnexpectedToken {
+                        token: token.clone(),
+                        span: *span,
+                    }),
+                    None => Err(Error::UnbalancedParentheses { span: *span }),
                 }
             }
-        }
-        //Retroactive literal lookup
-        if let Token::Literal(lit) = token.clone() {
-            if let Some(val) = known_literals.get(&lit) {
-                prev_token = Token::Number(*val);
-                ret = Op::Number(*val);
-            } else {
-                if i + 1 > data.len() - 1 {
-                    return Err(format!("Unknown literal: {lit:?}"));
+            //Unary minus binds looser than `^`, so `-2^2` is `-(2^2)`
+            Token::Op(OpType::Sub) | Token::Op(OpType::Add) => {
+                let x = self.expression(OpType::Neg.get_order() + 1)?;
+                let span = span.to(x.span);
+                if *token == Token::Op(OpType::Add) {
+                    return Ok(Node::new(x.op, span));
                 }
-                prev_token = token.clone();
+                Ok(Node::new(
+                    Op::from_type(OpType::Neg, Some(Box::new(x)), None),
+                    span,
+                ))
+            }
+            Token::Op(op_type) if op_type.is_forward() => {
+                let x = self.expression(op_type.get_order())?;
+                let mut end = x.span;
+                let y = if op_type.get_consume_count() > 1 {
+                    let y = self.expression(op_type.get_order())?;
+                    end = y.span;
+                    Some(Box::new(y))
+                } else {
+                    None
+                };
+                Ok(Node::new(
+                    Op::from_type(op_type.clone(), Some(Box::new(x)), y),
+                    span.to(end),
+                ))
             }
+            token => Err(Error::UnexpectedToken {
+                token: token.clone(),
+                span: *span,
+            }),
+        }
+    }
+
+    // `name(a, b) =`, the name is already consumed.
+    fn params(&mut self) -> Result<Vec<String>, Error> {
+        self.expect(Token::OpenP)?;
+        let mut params: Vec<String> = Vec::new();
+        if self.peek().is_some_and(|x| x.token == Token::CloseP) {
+            self.pos += 1;
         } else {
-            prev_token = token.clone();
+            loop {
+                let token = self.next()?;
+                match &token.token {
+                    Token::Literal(name) if !params.contains(name) => params.push(name.clone()),
+                    _ => {
+                        return Err(Error::UnexpectedToken {
+                            token: token.token.clone(),
+                            span: token.span,
+                        })
+                    }
+                }
+                let token = self.next()?;
+                match token.token {
+                    Token::Comma => {}
+                    Token::CloseP => break,
+                    _ => {
+                        return Err(Error::UnexpectedToken {
+                            token: token.token.clone(),
+                            span: token.span,
+                        })
+                    }
+                }
+            }
         }
+        self.expect(Token::Eq)?;
+        Ok(params)
+    }
+}
+
+fn parse_statement(data: Vec<SpannedToken>) -> Result<Statement, Error> {
+    if let Some(e) = invalid_token(&data) {
+        return Err(e);
+    }
+
+    if let [SpannedToken {
+        token: Token::Literal(lit),
+        ..
+    }, SpannedToken {
+        token: Token::Eq,
+        span,
+        ..
+    }, rest @ ..] = data.as_slice()
+    {
+        if rest.is_empty() {
+            return Err(Error::UnexpectedEnd {
+                span: Span::new(span.end, span.end),
+            });
+        }
+        let node = parse_to_operations(rest.to_vec())?;
+        return Ok(Statement::Assignment(lit.clone(), node));
+    }
+
+    if let Some(name) = defined_function(&data) {
+        //Builtins always win over user functions, so one could never be called
+        if Builtin::from_name(name).is_some() {
+            return Err(Error::UnexpectedToken {
+                token: data[0].token.clone(),
+                span: data[0].span,
+            });
+        }
+        let mut parser = Parser::new(&data);
+        parser.pos = 1;
+        let params = parser.params()?;
+        let rest = &data[parser.pos..];
+        if rest.is_empty() {
+            return Err(Error::UnexpectedEnd {
+                span: parser.end_span(),
+            });
+        }
+        let body = parse_to_operations(rest.to_vec())?;
+        return Ok(Statement::Definition(
+            name.to_owned(),
+            Function { params, body },
+        ));
+    }
+
+    Ok(Statement::Expression(parse_to_operations(data)?))
+}
+
+fn parse_to_operations(data: Vec<SpannedToken>) -> Result<Node, Error> {
+    if let Some(e) = invalid_token(&data) {
+        return Err(e);
+    }
+
+    let mut parser = Parser::new(&data);
+    let mut ret = parser.expression(1)?;
+    //`5 km/h in m/s`, the conversion applies to the whole expression
+    if parser.peek().is_some_and(|x| x.token == Token::Convert) {
+        parser.pos += 1;
+        let start = parser.pos;
+        let target = parser.expression(1)?;
+        let name = unit_text(&data[start..parser.pos]);
+        let span = ret.span.to(target.span);
+        ret = Node::new(Op::Convert(Box::new(ret), Box::new(target), name), span);
+    }
+    if let Some(SpannedToken { token, span, .. }) = parser.peek() {
+        if *token == Token::CloseP {
+            return Err(Error::UnbalancedParentheses { span: *span });
+        }
+        return Err(Error::UnexpectedToken {
+            token: token.clone(),
+            span: *span,
+        });
     }
     Ok(ret)
 }
 
-mod test {
+// The unit as it was typed, spaces are only kept between two operands, so
+// `m / s` is shown as `m/s` and `N m` stays `N m`.
+fn unit_text(data: &[SpannedToken]) -> String {
+    let mut ret = String::new();
+    let mut last: Option<&SpannedToken> = None;
+    for x in data.iter().filter(|x| !x.lexeme.is_empty()) {
+        if last.is_some_and(|l| {
+            l.span.end < x.span.start && ends_operand(&l.token) && starts_operand(&x.token)
+        }) {
+            ret.push(' ');
+        }
+        ret.push_str(&x.lexeme);
+        last = Some(x);
+    }
+    ret
+}
+
+pub(crate) mod test {
+    #[cfg(test)]
+    use super::{
+        parse_to_operations, sanitase, Builtin, Error, KnownLiterals, Node, Op, OpType, Parse,
+        Span, SpannedToken, Token,
+    };
+    #[cfg(test)]
+    use crate::{integer::WordSize, quantity::Value, tokenizer::tokenize};
+
+    #[cfg(test)]
+    pub(crate) fn eval(s: &str, known_literals: &mut KnownLiterals) -> Result<Value, Error> {
+        tokenize(s)
+            .parse(known_literals)
+            .map(|x| x.expect("expression"))
+    }
 
+    // What the prompt shows for `s`, the result or the error. Definitions
+    // show nothing.
     #[cfg(test)]
-    use super::{parse_to_operations, KnownLiterals, Op, OpType, Token};
+    pub(crate) fn output(s: &str, known_literals: &mut KnownLiterals) -> String {
+        tokenize(s)
+            .parse(known_literals)
+            .map(|x| x.map(|x| x.to_string()).unwrap_or_default())
+            .unwrap_or_else(|e| e.to_string())
+    }
+
+    #[cfg(test)]
+    fn spanned(data: Vec<Token>) -> Vec<SpannedToken> {
+        data.into_iter()
+            .map(|x| SpannedToken::new(x, Span::default(), ""))
+            .collect()
+    }
 
     #[test]
     fn test_parse_to_operations() {
-        let mut known_literals = KnownLiterals::new();
         assert_eq!(
-            parse_to_operations(
-                vec![
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Mul),
-                    Token::Number(2.0.into()),
-                ],
-                &mut known_literals
-            )
+            parse_to_operations(spanned(vec![
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Mul),
+                Token::Number(2.0.into()),
+            ]))
             .unwrap(),
-            Op::Mul(
-                Box::new(Op::Number(2.0.into())),
-                Box::new(Op::Number(2.0.into()))
-            )
-        );
-        assert_eq!(
-            parse_to_operations(
-                vec![
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Div),
-                    Token::Number(2.0.into()),
-                ],
-                &mut known_literals
-            )
+            Node::from(Op::Mul(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Number(2.0.into())))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(spanned(vec![
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Div),
+                Token::Number(2.0.into()),
+            ]))
             .unwrap(),
-            Op::Div(
-                Box::new(Op::Number(2.0.into())),
-                Box::new(Op::Number(2.0.into()))
-            )
-        );
-        assert_eq!(
-            parse_to_operations(
-                vec![
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Add),
-                    Token::Number(2.0.into()),
-                ],
-                &mut known_literals
-            )
+            Node::from(Op::Div(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Number(2.0.into())))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(spanned(vec![
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Add),
+                Token::Number(2.0.into()),
+            ]))
             .unwrap(),
-            Op::Add(
-                Box::new(Op::Number(2.0.into())),
-                Box::new(Op::Number(2.0.into()))
-            )
-        );
-        assert_eq!(
-            parse_to_operations(
-                vec![
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Sub),
-                    Token::Number(2.0.into()),
-                ],
-                &mut known_literals
-            )
+            Node::from(Op::Add(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Number(2.0.into())))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(spanned(vec![
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Sub),
+                Token::Number(2.0.into()),
+            ]))
             .unwrap(),
-            Op::Sub(
-                Box::new(Op::Number(2.0.into())),
-                Box::new(Op::Number(2.0.into()))
-            )
-        );
-        assert_eq!(
-            parse_to_operations(
-                vec![
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Sub),
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Div),
-                    Token::Number(3.0.into()),
-                ],
-                &mut known_literals
-            )
+            Node::from(Op::Sub(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Number(2.0.into())))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(spanned(vec![
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Sub),
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Div),
+                Token::Number(3.0.into()),
+            ]))
             .unwrap(),
-            Op::Sub(
-                Box::new(Op::Number(2.0.into())),
-                Box::new(Op::Div(
-                    Box::new(Op::Number(2.0.into())),
-                    Box::new(Op::Number(3.0.into()))
-                ))
-            )
-        );
-        assert_eq!(
-            parse_to_operations(
-                vec![
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Mul),
-                    Token::Number(2.0.into()),
-                    Token::Op(OpType::Div),
-                    Token::Number(3.0.into()),
-                ],
-                &mut known_literals
-            )
+            Node::from(Op::Sub(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Div(
+                    Box::new(Node::from(Op::Number(2.0.into()))),
+                    Box::new(Node::from(Op::Number(3.0.into())))
+                )))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(spanned(vec![
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Mul),
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Div),
+                Token::Number(3.0.into()),
+            ]))
             .unwrap(),
-            Op::Div(
-                Box::new(Op::Mul(
-                    Box::new(Op::Number(2.0.into())),
-                    Box::new(Op::Number(2.0.into()))
-                )),
-                Box::new(Op::Number(3.0.into()))
-            )
-        );
-        assert_eq!(
-            parse_to_operations(
-                vec![Token::Op(OpType::Root), Token::Number(2.0.into())],
-                &mut known_literals
-            )
+            Node::from(Op::Div(
+                Box::new(Node::from(Op::Mul(
+                    Box::new(Node::from(Op::Number(2.0.into()))),
+                    Box::new(Node::from(Op::Number(2.0.into())))
+                ))),
+                Box::new(Node::from(Op::Number(3.0.into())))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(spanned(vec![
+                Token::Op(OpType::Root),
+                Token::Number(2.0.into())
+            ]))
             .unwrap(),
-            Op::Root(Box::new(Op::Number(2.0.into())))
+            Node::from(Op::Root(Box::new(Node::from(Op::Number(2.0.into())))))
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(
+            parse_to_operations(tokenize("1+2*3^4")).unwrap(),
+            Node::from(Op::Add(
+                Box::new(Node::from(Op::Number(1.0.into()))),
+                Box::new(Node::from(Op::Mul(
+                    Box::new(Node::from(Op::Number(2.0.into()))),
+                    Box::new(Node::from(Op::Pow(
+                        Box::new(Node::from(Op::Number(3.0.into()))),
+                        Box::new(Node::from(Op::Number(4.0.into())))
+                    )))
+                )))
+            ))
+        );
+        assert_eq!(
+            parse_to_operations(tokenize("2^3^2")).unwrap(),
+            Node::from(Op::Pow(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Pow(
+                    Box::new(Node::from(Op::Number(3.0.into()))),
+                    Box::new(Node::from(Op::Number(2.0.into())))
+                )))
+            ))
+        );
+        assert_eq!(eval("8-3-2", &mut known_literals), Ok(3.0.into()));
+        assert_eq!(eval("24/4/2", &mut known_literals), Ok(3.0.into()));
+        assert_eq!(eval("2^3^2", &mut known_literals), Ok(512.0.into()));
+        assert_eq!(eval("1+2*3-4/2", &mut known_literals), Ok(5.0.into()));
+        assert_eq!(eval("7%4*2", &mut known_literals), Ok(6.0.into()));
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(eval("(1+2)*3", &mut known_literals), Ok(9.0.into()));
+        assert_eq!(eval("2*(3+(4-1)*2)", &mut known_literals), Ok(18.0.into()));
+        assert_eq!(eval("((2))", &mut known_literals), Ok(2.0.into()));
+        assert_eq!(eval("2(3+1)", &mut known_literals), Ok(8.0.into()));
+        assert_eq!(eval("(1+1)(3)", &mut known_literals), Ok(6.0.into()));
+        assert!(eval("(1+2", &mut known_literals).is_err());
+        assert!(eval("1+2)", &mut known_literals).is_err());
+        assert!(eval("()", &mut known_literals).is_err());
+    }
+
+    #[test]
+    fn test_forward_ops() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(eval("sqrt 16", &mut known_literals), Ok(4.0.into()));
+        assert_eq!(eval("2*sqrt 16 + 1", &mut known_literals), Ok(9.0.into()));
+        assert_eq!(eval("2 sqrt 16", &mut known_literals), Ok(8.0.into()));
+        assert_eq!(eval("sqrt (9+7)", &mut known_literals), Ok(4.0.into()));
+        assert_eq!(eval("log 2 8", &mut known_literals), Ok(3.0.into()));
+        assert_eq!(eval("1 + log 2 8 * 2", &mut known_literals), Ok(7.0.into()));
+        assert_eq!(eval("log (1+1) (4*2)", &mut known_literals), Ok(3.0.into()));
+        assert!(eval("log 2", &mut known_literals).is_err());
+    }
+
+    #[test]
+    fn test_sanitase() {
+        assert_eq!(
+            sanitase(tokenize("1 2 3"), &KnownLiterals::new())
+                .unwrap()
+                .into_iter()
+                .map(|x| x.token)
+                .collect::<Vec<_>>(),
+            vec![
+                Token::Number(1.0.into()),
+                Token::Op(OpType::Mul),
+                Token::Number(2.0.into()),
+                Token::Op(OpType::Mul),
+                Token::Number(3.0.into())
+            ]
+        );
+        assert_eq!(
+            sanitase(tokenize("log 2 8"), &KnownLiterals::new()).unwrap(),
+            tokenize("log 2 8")
+        );
+        assert!(sanitase(spanned(vec![Token::Invalid]), &KnownLiterals::new()).is_err());
+    }
+
+    #[test]
+    fn test_literals() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(eval("a = 2+3", &mut known_literals), Ok(5.0.into()));
+        assert_eq!(eval("b = a", &mut known_literals), Ok(5.0.into()));
+        assert_eq!(eval("2 a + b", &mut known_literals), Ok(15.0.into()));
+        assert_eq!(eval("a = a * 2", &mut known_literals), Ok(10.0.into()));
+        assert_eq!(eval("a", &mut known_literals), Ok(10.0.into()));
+        assert!(eval("c + 1", &mut known_literals).is_err());
+        assert!(eval("a =", &mut known_literals).is_err());
+        assert!(eval("1 = 2", &mut known_literals).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(
+            eval("", &mut known_literals),
+            Err(Error::UnexpectedEnd {
+                span: Span::new(0, 0)
+            })
+        );
+        assert_eq!(
+            eval("1 + $", &mut known_literals),
+            Err(Error::Tokenize {
+                lexeme: String::from("$"),
+                span: Span::new(4, 5)
+            })
+        );
+        assert_eq!(
+            eval("2 * (1 + 2", &mut known_literals),
+            Err(Error::UnbalancedParentheses {
+                span: Span::new(4, 5)
+            })
+        );
+        assert_eq!(
+            eval("1 + 2)", &mut known_literals),
+            Err(Error::UnbalancedParentheses {
+                span: Span::new(5, 6)
+            })
+        );
+        assert_eq!(
+            eval("1 + * 2", &mut known_literals),
+            Err(Error::UnexpectedToken {
+                token: Token::Op(OpType::Mul),
+                span: Span::new(4, 5)
+            })
+        );
+        assert_eq!(
+            eval("1 +", &mut known_literals),
+            Err(Error::UnexpectedEnd {
+                span: Span::new(3, 3)
+            })
+        );
+        assert_eq!(
+            eval("2 * foo", &mut known_literals),
+            Err(Error::UnknownLiteral {
+                name: String::from("foo"),
+                span: Span::new(4, 7)
+            })
+        );
+        assert_eq!(
+            eval(" 1 / (2 - 2)", &mut known_literals),
+            Err(Error::DivisionByZero {
+                span: Span::new(1, 12)
+            })
+        );
+        assert_eq!(
+            eval("4 % 0", &mut known_literals),
+            Err(Error::DivisionByZero {
+                span: Span::new(0, 5)
+            })
+        );
+        assert!(matches!(
+            eval("asin(0 - 4)", &mut known_literals),
+            Err(Error::Domain { .. })
+        ));
+        assert!(matches!(
+            eval("log 1 5", &mut known_literals),
+            Err(Error::Domain { .. })
+        ));
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut known_literals = KnownLiterals::new();
+        let node = parse_to_operations(
+            sanitase(tokenize("2 * (3 + 4) ^ 2"), &KnownLiterals::new()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(node.span, Span::new(0, 15));
+        let Op::Mul(x, y) = node.op else {
+            panic!("Expected multiplication")
+        };
+        assert_eq!(x.span, Span::new(0, 1));
+        assert_eq!(y.span, Span::new(4, 15));
+        let Op::Pow(x, _) = y.op else {
+            panic!("Expected power")
+        };
+        assert_eq!(x.span, Span::new(4, 11));
+
+        let node =
+            parse_to_operations(sanitase(tokenize("log 2 8"), &KnownLiterals::new()).unwrap())
+                .unwrap();
+        assert_eq!(node.span, Span::new(0, 7));
+        assert_eq!(
+            eval("1 + asin(2 - 4)", &mut known_literals)
+                .unwrap_err()
+                .span(),
+            Span::new(4, 15)
+        );
+    }
+
+    #[test]
+    fn test_unary() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(eval("-3 * 2", &mut known_literals), Ok((-6.0).into()));
+        assert_eq!(eval("2 * -3", &mut known_literals), Ok((-6.0).into()));
+        assert_eq!(eval("-2^2", &mut known_literals), Ok((-4.0).into()));
+        assert_eq!(eval("(-2)^2", &mut known_literals), Ok(4.0.into()));
+        assert_eq!(eval("2^-2", &mut known_literals), Ok(0.25.into()));
+        assert_eq!(eval("1 - -1", &mut known_literals), Ok(2.0.into()));
+        assert_eq!(eval("--3", &mut known_literals), Ok(3.0.into()));
+        assert_eq!(eval("+3 - +1", &mut known_literals), Ok(2.0.into()));
+        assert_eq!(eval("-(1 + 2) * 2", &mut known_literals), Ok((-6.0).into()));
+        assert_eq!(eval("x = -5", &mut known_literals), Ok((-5.0).into()));
+        assert_eq!(eval("-x", &mut known_literals), Ok(5.0.into()));
+        assert_eq!(
+            parse_to_operations(sanitase(tokenize("-2^2"), &KnownLiterals::new()).unwrap())
+                .unwrap(),
+            Node::from(Op::Neg(Box::new(Node::from(Op::Pow(
+                Box::new(Node::from(Op::Number(2.0.into()))),
+                Box::new(Node::from(Op::Number(2.0.into())))
+            )))))
+        );
+        assert!(eval("2 * -", &mut known_literals).is_err());
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(
+            tokenize("f(x, y) = x^2 + y").parse(&mut known_literals),
+            Ok(None)
+        );
+        assert_eq!(eval("f(3, 4)", &mut known_literals), Ok(13.0.into()));
+        assert_eq!(eval("2 f(1, 1) + 1", &mut known_literals), Ok(5.0.into()));
+        assert_eq!(eval("f(f(1, 0), 1)", &mut known_literals), Ok(2.0.into()));
+
+        //Parameters shadow variables only inside the body
+        eval("x = 10", &mut known_literals).unwrap();
+        assert_eq!(eval("f(2, x)", &mut known_literals), Ok(14.0.into()));
+        assert_eq!(eval("x", &mut known_literals), Ok(10.0.into()));
+
+        tokenize("g() = x + 1").parse(&mut known_literals).unwrap();
+        assert_eq!(eval("g()", &mut known_literals), Ok(11.0.into()));
+        assert_eq!(eval("g", &mut known_literals), Ok(11.0.into()));
+
+        assert_eq!(
+            eval("f(1)", &mut known_literals),
+            Err(Error::ArityMismatch {
+                name: String::from("f"),
+                expected: 2,
+                found: 1,
+                span: Span::new(0, 4)
+            })
+        );
+        //Values keep implicit multiplication
+        assert_eq!(eval("x(1)", &mut known_literals), Ok(10.0.into()));
+        tokenize("u() = f(1, 1)")
+            .parse(&mut known_literals)
+            .unwrap();
+        eval("f = 2", &mut known_literals).unwrap();
+        assert_eq!(
+            eval("u()", &mut known_literals),
+            Err(Error::NotAFunction {
+                name: String::from("f"),
+                span: Span::new(0, 3)
+            })
+        );
+        assert!(matches!(
+            eval("h(1)", &mut known_literals),
+            Err(Error::UnknownLiteral { .. })
+        ));
+        assert!(tokenize("k(x, x) = x").parse(&mut known_literals).is_err());
+        assert!(tokenize("k(x) =").parse(&mut known_literals).is_err());
+
+        tokenize("r(n) = r(n + 1)")
+            .parse(&mut known_literals)
+            .unwrap();
+        assert!(matches!(
+            eval("r(0)", &mut known_literals),
+            Err(Error::RecursionLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_builtins() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(eval("abs(0 - 3) + 1", &mut known_literals), Ok(4.0.into()));
+        assert_eq!(eval("2 floor(7/2)", &mut known_literals), Ok(6.0.into()));
+        assert_eq!(
+            eval("max(1, min(5, 3))", &mut known_literals),
+            Ok(3.0.into())
+        );
+        assert_eq!(eval("round(-5/2)", &mut known_literals), Ok((-3.0).into()));
+        assert_eq!(
+            eval("round(1/8, 2)", &mut known_literals).map(|x| x.to_string()),
+            Ok(String::from("13/100"))
+        );
+        assert_eq!(
+            eval("round(1234.5, 0 - 2)", &mut known_literals),
+            Ok(1200.0.into())
+        );
+        assert!(eval("round(1, 1/2)", &mut known_literals).is_err());
+        assert_eq!(eval("sin(0) + cos(0)", &mut known_literals), Ok(1.0.into()));
+        assert_eq!(eval("log10(1000)", &mut known_literals), Ok(3.0.into()));
+
+        tokenize("f(x) = sign(x) x")
+            .parse(&mut known_literals)
+            .unwrap();
+        assert_eq!(eval("f(0 - 4)", &mut known_literals), Ok(4.0.into()));
+
+        assert_eq!(
+            eval("atan2(1)", &mut known_literals),
+            Err(Error::ArityMismatch {
+                name: String::from("atan2"),
+                expected: 2,
+                found: 1,
+                span: Span::new(0, 8)
+            })
+        );
+        assert!(matches!(
+            eval("1 + ln(0)", &mut known_literals),
+            Err(Error::Domain {
+                span: Span { start: 4, end: 9 },
+                ..
+            })
+        ));
+        assert!(tokenize("sin(x) = x").parse(&mut known_literals).is_err());
+    }
+
+    #[test]
+    fn test_exact_powers() {
+        let mut known_literals = KnownLiterals::new();
+        assert_eq!(eval("8^(1/3)", &mut known_literals), Ok(2.0.into()));
+        assert_eq!(eval("(-8)^(1/3)", &mut known_literals), Ok((-2.0).into()));
+        assert_eq!(eval("2^-2", &mut known_literals), Ok(0.25.into()));
+        assert_eq!(eval("root(3, 125/8)", &mut known_literals), Ok(2.5.into()));
+        assert_eq!(eval("root 16", &mut known_literals), Ok(4.0.into()));
+        assert_eq!(eval("root(16)", &mut known_literals), Ok(4.0.into()));
+        assert_eq!(eval("root(max(1, 9))", &mut known_literals), Ok(3.0.into()));
+        assert_eq!(
+            eval("root(3, 8) + root 9", &mut known_literals),
+            Ok(5.0.into())
+        );
+        assert!(eval("sqrt 2", &mut known_literals).is_ok_and(|x| x.is_exact()));
+        assert!(eval("sin(1)", &mut known_literals).is_ok_and(|x| !x.is_exact()));
+        assert!(eval("sqrt 9", &mut known_literals).is_ok_and(|x| x.is_exact()));
+        assert_eq!(
+            eval("(-4)^(1/2)", &mut known_literals).map(|x| x.to_string()),
+            Ok(String::from("2i"))
+        );
+    }
+
+    #[test]
+    fn test_symbolic() {
+        let known_literals = &mut KnownLiterals::new();
+        assert_eq!(output("sqrt 8", known_literals), "2*sqrt(2)");
+        assert_eq!(output("sqrt(2)^2", known_literals), "2");
+        assert_eq!(output("sqrt(8) / sqrt(2)", known_literals), "2");
+        assert_eq!(output("2 pi/6", known_literals), "pi/3");
+        assert_eq!(output("e^2 e", known_literals), "e^3");
+        assert_eq!(output("(1 + sqrt 3)^2", known_literals), "4 + 2*sqrt(3)");
+        assert_eq!(output("cos(pi/4)^2", known_literals), "1/2");
+        assert_eq!(output("log 2 sqrt(2)", known_literals), "1/2");
+        assert_eq!(output("root(3, 16)", known_literals), "2*root(3, 2)");
+
+        //Variables shadow the constants
+        assert_eq!(output("e = 3", known_literals), "3");
+        assert_eq!(output("e^2", known_literals), "9");
+    }
+
+    #[test]
+    fn test_backends() {
+        use crate::{bigfloat::BigFloat, decimal::Decimal};
+        let mut known_literals = KnownLiterals::new();
+        let mut run = |s: &str, places: u32| {
+            let x: Option<Decimal> = tokenize(s).parse_as(&mut known_literals, places).unwrap();
+            x.unwrap().to_string()
+        };
+        assert_eq!(run("1/3", 2), "0.33");
+        assert_eq!(run("x = 2/3", 4), "0.6667");
+        //Assignments keep the rounded value
+        assert_eq!(run("x * 3", 4), "2.0001");
+        assert_eq!(run("sqrt 2", 20), "1.41421356237309504880");
+        assert_eq!(run("sin(pi/6)", 10), "0.5000000000");
+
+        let mut known_literals = KnownLiterals::new();
+        let float: Option<f64> = tokenize("f(x) = x^2")
+            .parse_as(&mut known_literals, ())
+            .unwrap();
+        assert_eq!(float, None);
+        let float: Option<f64> = tokenize("f(3) / 4")
+            .parse_as(&mut known_literals, ())
+            .unwrap();
+        assert_eq!(float, Some(2.25));
+        let big: Option<BigFloat> = tokenize("4 atan(1)")
+            .parse_as(&mut known_literals, 200)
+            .unwrap();
+        assert!(big
+            .unwrap()
+            .to_string()
+            .starts_with("3.14159265358979323846264338327950288419716939937510"));
+        assert!(matches!(
+            tokenize("(-4)^(1/2)").parse_as::<f64>(&mut known_literals, ()),
+            Err(Error::Domain { .. })
+        ));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let known_literals = &mut KnownLiterals::new();
+        //A unit or parentheses after `in` make it a conversion
+        assert_eq!(output("5 km in m", known_literals), "5000 m");
+        assert_eq!(output("12 in to cm", known_literals), "762/25 cm");
+        assert_eq!(output("1 h in (min)", known_literals), "60 (min)");
+        //Anything else multiplies by inches
+        assert_eq!(output("2 in 3", known_literals), "381/2500 m");
+        assert_eq!(output("5 m in 1/s", known_literals), "127/1000 m^2/s");
+        assert_eq!(
+            output("5 m in -2", known_literals),
+            "Dimension mismatch: m^2 and a plain number"
+        );
+        assert_eq!(output("3 in + 1 in", known_literals), "127/1250 m");
+        assert_eq!(output("2 m in^2", known_literals), "16129/12500000 m^3");
+        assert_eq!(output("x = 2", known_literals), "2");
+        assert_eq!(output("3 in x", known_literals), "381/2500 m");
+    }
+
+    #[test]
+    fn test_booleans() {
+        let known_literals = &mut KnownLiterals::new();
+        assert_eq!(output("1/2 == 2/4", known_literals), "true");
+        assert_eq!(output("1 != 1", known_literals), "false");
+        assert_eq!(output("sqrt(2) < 3/2", known_literals), "true");
+        assert_eq!(output("2 <= 2 and 3 >= 4", known_literals), "false");
+        assert_eq!(output("x = 5", known_literals), "5");
+        assert_eq!(output("x > 0 and x < 10", known_literals), "true");
+        assert_eq!(output("not x > 0 or x == 5", known_literals), "true");
+        assert_eq!(output("1 + 1 == 2", known_literals), "true");
+        assert_eq!(output("(1 < 2) + 1", known_literals), "2");
+        assert_eq!(output("1 km > 999 m", known_literals), "true");
+        assert_eq!(
+            output("1 km > 1 s", known_literals),
+            "Dimension mismatch: m and s"
+        );
+        assert_eq!(
+            output("i < 1", known_literals),
+            "Domain error: complex numbers can't be ordered"
+        );
+        assert_eq!(output("i == sqrt(-1)", known_literals), "true");
+        assert_eq!(
+            output("x != 0 and 1/0 > 1 or true", known_literals),
+            "Division by zero"
+        );
+        assert_eq!(output("x == 0 and 1/0 > 1", known_literals), "false");
+        assert_eq!(output("f(x) = if(x < 0, -x, x)", known_literals), "");
+        assert_eq!(output("f(-3)", known_literals), "3");
+        assert_eq!(output("if(x == 0, 0, 1/x)", known_literals), "1/5");
+        assert_eq!(output("if(0, 1/0, 2)", known_literals), "2");
+        assert_eq!(output("b = 2 > 1", known_literals), "true");
+        assert_eq!(output("b or false", known_literals), "true");
+        assert_eq!(
+            output("if(1, 2)", known_literals),
+            "\"if\" takes 3 argument(s) but 2 were given"
+        );
+        let node = Node::new(Op::Builtin(Builtin::If, Vec::new()), Span::new(0, 4));
+        assert_eq!(
+            node.apply(known_literals),
+            Err(Error::ArityMismatch {
+                name: String::from("if"),
+                expected: 3,
+                found: 0,
+                span: Span::new(0, 4),
+            })
+        );
+        assert_eq!(
+            tokenize("3 > 2")
+                .parse_with::<Value>(known_literals, (), WordSize::new(8, false))
+                .map(|x| x.expect("expression").to_string()),
+            Ok(String::from("true"))
         );
     }
 }
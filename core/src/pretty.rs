@@ -0,0 +1,286 @@
+use std::fmt::Display;
+
+use crate::op::{Node, Op, OpType};
+use crate::parser::Statement;
+use crate::rational::Rational;
+
+// Binding strength of numbers, names and calls, above every operator.
+const ATOM: u8 = 14;
+
+/// How `Op::render` writes an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    /// The usual notation with only the parentheses precedence needs,
+    /// `2 * (x + 1)`. This is what `Display` shows.
+    #[default]
+    Infix,
+    /// Every operation in parentheses, `(2 * (x + 1))`.
+    Parenthesized,
+    /// Operators first like in Lisp, `(* 2 (+ x 1))`.
+    Prefix,
+    /// Reverse Polish notation, `2 x 1 + *`.
+    Rpn,
+}
+
+// The parts of an operation the styles are built from.
+enum Shape<'a> {
+    Atom(String, u8),
+    Operator(OpType, Vec<&'a Op>),
+    Call(String, Vec<&'a Op>),
+    /// The value, the unit and the unit's name as it was typed.
+    Convert(&'a Op, &'a Op, &'a str),
+}
+
+// `2`, `0.25` or `1/3`, with how strongly the text binds.
+fn number(x: &Rational) -> (String, u8) {
+    let decimal = x.to_repeating();
+    let text = if x.is_integer() || decimal.contains('(') {
+        x.to_string()
+    } else {
+        decimal
+    };
+    let order = if text.contains('/') {
+        OpType::Div.get_order()
+    } else if text.starts_with('-') {
+        OpType::Neg.get_order()
+    } else {
+        ATOM
+    };
+    (text, order)
+}
+
+fn nodes<'a>(x: impl IntoIterator<Item = &'a Node>) -> Vec<&'a Op> {
+    x.into_iter().map(|x| &x.op).collect()
+}
+
+fn shape(op: &Op) -> Shape<'_> {
+    match op {
+        Op::Number(x) => {
+            let (text, order) = number(x);
+            Shape::Atom(text, order)
+        }
+        Op::Literal(name) => Shape::Atom(name.clone(), ATOM),
+        Op::Unit(unit) => Shape::Atom(unit.name.clone(), ATOM),
+        Op::Call(name, args) => Shape::Call(name.clone(), nodes(args)),
+        Op::Builtin(f, args) => Shape::Call(f.name().to_owned(), nodes(args)),
+        Op::Convert(x, unit, name) => Shape::Convert(&x.op, &unit.op, name),
+        _ => Shape::Operator(op.get_type().expect("operator"), nodes(op.operands())),
+    }
+}
+
+// `op` in infix with how strongly it binds, its operands are put in
+// parentheses when they bind looser than the parser expects there. With
+// `all` every operation gets them.
+fn infix(op: &Op, all: bool) -> (String, u8) {
+    let operand = |x: &Op, min_order: u8| {
+        let (text, order) = infix(x, all);
+        if order < min_order {
+            format!("({text})")
+        } else {
+            text
+        }
+    };
+    let (text, order) = match shape(op) {
+        Shape::Atom(text, order) => return (text, order),
+        Shape::Call(name, args) => {
+            let args: Vec<String> = args.into_iter().map(|x| operand(x, 1)).collect();
+            return (format!("{name}({})", args.join(", ")), ATOM);
+        }
+        //Only valid for a whole line, `in` can't be nested
+        Shape::Convert(x, _, name) => (format!("{} in {name}", operand(x, 1)), 0),
+        Shape::Operator(op_type, args) => {
+            let order = op_type.get_order();
+            let text = match (&op_type, args.as_slice()) {
+                //`3!!` would be a double factorial, so `(3!)!`
+                (_, [x]) if op_type.is_postfix() => format!("{}{op_type}", operand(x, ATOM)),
+                //Their operand is read up to the next operator binding looser
+                //than `^`, so `sqrt(x)^2` is `sqrt(x^2)`
+                (OpType::Root, [x]) => format!("sqrt({})", operand(x, 1)),
+                (OpType::Log, [x, y]) => format!("log({})({})", operand(x, 1), operand(y, 1)),
+                (OpType::Neg, [x]) => format!("-{}", operand(x, order + 1)),
+                (OpType::Not, [x]) => format!("not {}", operand(x, order)),
+                (_, [x]) => format!("{op_type}{}", operand(x, order)),
+                (_, [x, y]) => {
+                    let (left, right) = if op_type.is_right_assoc() {
+                        (order + 1, order)
+                    } else {
+                        (order, order + 1)
+                    };
+                    let (x, y) = (operand(x, left), operand(y, right));
+                    match op_type {
+                        OpType::Pow => format!("{x}^{y}"),
+                        _ => format!("{x} {op_type} {y}"),
+                    }
+                }
+                _ => unreachable!("{op_type} with {} operands", args.len()),
+            };
+            (text, order)
+        }
+    };
+    if all {
+        (format!("({text})"), ATOM)
+    } else {
+        (text, order)
+    }
+}
+
+// `(* 2 (+ x 1))`
+fn prefix(op: &Op) -> String {
+    let list = |head: String, args: Vec<&Op>| {
+        let args: Vec<String> = args.into_iter().map(prefix).collect();
+        format!("({head} {})", args.join(" "))
+    };
+    match shape(op) {
+        Shape::Atom(text, _) => text,
+        Shape::Call(name, args) if args.is_empty() => format!("({name})"),
+        Shape::Call(name, args) => list(name, args),
+        Shape::Convert(x, unit, _) => list(String::from("in"), vec![x, unit]),
+        Shape::Operator(op_type, args) => list(op_type.to_string(), args),
+    }
+}
+
+// `2 x 1 + *`, functions taking another number of arguments than usual
+// tell it, `1 2 3 multinomial/3`.
+fn rpn(op: &Op) -> String {
+    let list = |args: Vec<&Op>, last: String| {
+        let mut out: Vec<String> = args.into_iter().map(rpn).collect();
+        out.push(last);
+        out.join(" ")
+    };
+    match (shape(op), op) {
+        (Shape::Atom(text, _), _) => text,
+        (Shape::Call(name, args), Op::Builtin(f, _)) if args.len() != f.arity() => {
+            let last = format!("{name}/{}", args.len());
+            list(args, last)
+        }
+        (Shape::Call(name, args), _) => list(args, name),
+        (Shape::Convert(x, unit, _), _) => list(vec![x, unit], String::from("in")),
+        //`-` alone would be a subtraction
+        (Shape::Operator(OpType::Neg, args), _) => list(args, String::from("neg")),
+        (Shape::Operator(op_type, args), _) => list(args, op_type.to_string()),
+    }
+}
+
+impl Op {
+    /// The expression as text, see `Style`.
+    pub fn render(&self, style: Style) -> String {
+        match style {
+            Style::Infix => infix(self, false).0,
+            Style::Parenthesized => infix(self, true).0,
+            Style::Prefix => prefix(self),
+            Style::Rpn => rpn(self),
+        }
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(Style::Infix))
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.op.fmt(f)
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statement::Expression(x) => write!(f, "{x}"),
+            Statement::Assignment(name, x) => write!(f, "{name} = {x}"),
+            Statement::Definition(name, function) => {
+                write!(
+                    f,
+                    "{name}({}) = {}",
+                    function.params.join(", "),
+                    function.body
+                )
+            }
+        }
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Style;
+    #[cfg(test)]
+    use crate::{
+        parser::{KnownLiterals, Statement},
+        tokenizer::tokenize,
+    };
+
+    #[cfg(test)]
+    fn statement(s: &str) -> Statement {
+        Statement::new(tokenize(s), &KnownLiterals::new()).unwrap()
+    }
+
+    #[test]
+    fn test_infix() {
+        let show = |s: &str| statement(s).to_string();
+        assert_eq!(show("1+2*3"), "1 + 2 * 3");
+        assert_eq!(show("(1+2)*3"), "(1 + 2) * 3");
+        assert_eq!(show("1-(2-3)"), "1 - (2 - 3)");
+        assert_eq!(show("(1-2)-3"), "1 - 2 - 3");
+        assert_eq!(show("2^3^4"), "2^3^4");
+        assert_eq!(show("(2^3)^4"), "(2^3)^4");
+        assert_eq!(show("-2^2"), "-2^2");
+        assert_eq!(show("(-2)^2"), "(-2)^2");
+        assert_eq!(show("2 x (y+1)"), "2 * x * (y + 1)");
+        assert_eq!(show("5 km/h in m/s"), "5 * km / h in m/s");
+        assert_eq!(show("sqrt 4^2 + log 2 8"), "sqrt(4^2) + log(2)(8)");
+        assert_eq!(show("(sqrt 4)^2"), "(sqrt(4))^2");
+        assert_eq!(show("(3!)! + -3!!"), "(3!)! + -3!!");
+        assert_eq!(show("not a < b and c or d"), "not a < b and c or d");
+        assert_eq!(show("(not a) < b"), "(not a) < b");
+        assert_eq!(show("~5 & 3 | 1 << 2"), "~5 & 3 | 1 << 2");
+        assert_eq!(show("0.25 x + 2^0.5"), "0.25 * x + 2^0.5");
+        assert_eq!(show("f(x, y) = max(x, y) / 2"), "f(x, y) = max(x, y) / 2");
+        assert_eq!(show("x = if(a, 1, -1)"), "x = if(a, 1, -1)");
+    }
+
+    #[test]
+    fn test_styles() {
+        let show = |s: &str, style| match statement(s) {
+            Statement::Expression(x) => x.op.render(style),
+            x => panic!("{x:?}"),
+        };
+        let s = "2(x + 1) - -y!";
+        assert_eq!(show(s, Style::Parenthesized), "((2 * (x + 1)) - (-(y!)))");
+        assert_eq!(show(s, Style::Prefix), "(- (* 2 (+ x 1)) (- (! y)))");
+        assert_eq!(show(s, Style::Rpn), "2 x 1 + * y ! neg -");
+        assert_eq!(
+            show("sin(pi) + multinomial(1, 2, 3)", Style::Prefix),
+            "(+ (sin pi) (multinomial 1 2 3))"
+        );
+        assert_eq!(
+            show("sin(pi) + multinomial(1, 2, 3)", Style::Rpn),
+            "pi sin 1 2 3 multinomial/3 +"
+        );
+        assert_eq!(show("3 ft in m", Style::Rpn), "3 ft * m in");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        //Printing and parsing again gives the same tree
+        for s in [
+            "1 - (2 - 3) * -4 / 5 % 6",
+            "2^-3^4!",
+            "(sqrt(x))^2 + log(2)(x + 1)",
+            "a < b == (c > d) and not (e or f)",
+            "~x & 3 xor 4 | 1 << 2 >> (3 // 2) gcd 4",
+            "-(1/3) + (1/3)! - max(0.5, -2)",
+            "--x",
+        ] {
+            let x = statement(s);
+            for style in [Style::Infix, Style::Parenthesized] {
+                let printed = match &x {
+                    Statement::Expression(node) => node.op.render(style),
+                    x => panic!("{x:?}"),
+                };
+                assert_eq!(statement(&printed), x, "{s} printed as {printed}");
+            }
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::complex::Complex;
+use crate::error::{Error, Span};
+use crate::format::Format;
+use crate::number::Number;
+use crate::rational::Rational;
+use crate::symbolic::Symbolic;
+use crate::unit::{Dimension, Unit};
+
+/// What expressions evaluate to by default.
+pub type Value = Quantity<Complex>;
+
+/// A number with a physical dimension, `5 km` is kept as `5000` metres.
+///
+/// After a conversion, `5 km in mi`, the target unit is remembered so the
+/// value is shown in it, arithmetic drops it again.
+///
+/// Comparisons give `1` or `0` marked as a boolean, shown as `true` and
+/// `false` until arithmetic turns them back into numbers.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Quantity<N> {
+    pub value: N,
+    pub dim: Dimension,
+    unit: Option<Box<(N, String)>>,
+    boolean: bool,
+}
+
+fn mismatch(left: &Dimension, right: &Dimension) -> Error {
+    let show = |x: &Dimension| {
+        if x.is_none() {
+            String::from("a plain number")
+        } else {
+            x.to_string()
+        }
+    };
+    Error::DimensionMismatch {
+        left: show(left),
+        right: show(right),
+        span: Span::default(),
+    }
+}
+
+// `5 m`, values with a space in them get parentheses: `(1 + i) m`.
+fn with_unit(value: String, unit: &str) -> String {
+    if value.contains(' ') {
+        format!("({value}) {unit}")
+    } else {
+        format!("{value} {unit}")
+    }
+}
+
+impl<N: Number> Quantity<N> {
+    pub fn new(value: N, dim: Dimension) -> Self {
+        Self {
+            value,
+            dim,
+            unit: None,
+            boolean: false,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.value.is_exact()
+    }
+
+    // A plain number, used for results of functions like `sin`.
+    fn plain(value: N) -> Self {
+        Self::new(value, Dimension::default())
+    }
+
+    fn keep(&self, value: N) -> Self {
+        Self::new(value, self.dim)
+    }
+
+    // `true` or `false` when the value is a boolean.
+    fn bool_name(&self) -> Option<&'static str> {
+        self.boolean.then(|| {
+            if self.value.is_zero() {
+                "false"
+            } else {
+                "true"
+            }
+        })
+    }
+
+    /// The value in the unit it is shown in and the name of that unit.
+    pub fn in_unit(&self) -> (N, Option<String>) {
+        match &self.unit {
+            Some(unit) => (self.value.clone() / unit.0.clone(), Some(unit.1.clone())),
+            None if self.dim.is_none() => (self.value.clone(), None),
+            None => (self.value.clone(), Some(self.dim.to_string())),
+        }
+    }
+}
+
+impl Quantity<Complex> {
+    /// Like `Display`, but with the value as a float.
+    pub fn to_float_string(&self) -> String {
+        if let Some(name) = self.bool_name() {
+            return name.to_owned();
+        }
+        match self.in_unit() {
+            (x, Some(unit)) => with_unit(x.to_float_string(), &unit),
+            (x, None) => x.to_float_string(),
+        }
+    }
+
+    /// Shown in `format`, errors when it can't show the value.
+    pub fn to_format_string(&self, format: Format) -> Result<String, Error> {
+        if let Some(name) = self.bool_name() {
+            return Ok(name.to_owned());
+        }
+        match self.in_unit() {
+            (x, Some(unit)) => Ok(with_unit(format.complex(&x)?, &unit)),
+            (x, None) => format.complex(&x),
+        }
+    }
+}
+
+impl<N: Number> Number for Quantity<N> {
+    type Context = N::Context;
+
+    fn from_rational(x: &Rational, ctx: N::Context) -> Self {
+        Self::plain(N::from_rational(x, ctx))
+    }
+
+    fn from_float(x: f64, ctx: N::Context) -> Self {
+        Self::plain(N::from_float(x, ctx))
+    }
+
+    fn from_symbolic(x: &Symbolic, ctx: N::Context) -> Self {
+        Self::plain(N::from_symbolic(x, ctx))
+    }
+
+    fn from_complex(x: &Complex, ctx: N::Context) -> Option<Self> {
+        N::from_complex(x, ctx).map(Self::plain)
+    }
+
+    fn from_quantity(x: &Value, ctx: N::Context) -> Option<Self> {
+        let unit = match &x.unit {
+            Some(unit) => Some(Box::new((N::from_complex(&unit.0, ctx)?, unit.1.clone()))),
+            None => None,
+        };
+        Some(Self {
+            value: N::from_complex(&x.value, ctx)?,
+            dim: x.dim,
+            unit,
+            boolean: x.boolean,
+        })
+    }
+
+    fn from_unit(x: &Unit, ctx: N::Context) -> Result<Self, Error> {
+        Ok(Self::new(N::from_rational(&x.factor, ctx), x.dim))
+    }
+
+    fn from_bool(x: bool, ctx: N::Context) -> Self {
+        Self {
+            boolean: true,
+            ..Self::plain(N::from_bool(x, ctx))
+        }
+    }
+
+    fn context(&self) -> N::Context {
+        self.value.context()
+    }
+
+    fn to_rational(&self) -> Option<Rational> {
+        self.value.to_rational().filter(|_| self.dim.is_none())
+    }
+
+    fn to_float(&self) -> f64 {
+        self.value.to_float()
+    }
+
+    fn to_symbolic(&self) -> Symbolic {
+        self.value.to_symbolic()
+    }
+
+    fn to_complex(&self) -> Complex {
+        self.value.to_complex()
+    }
+
+    fn to_quantity(&self) -> Value {
+        Quantity {
+            value: self.value.to_complex(),
+            dim: self.dim,
+            unit: self
+                .unit
+                .as_ref()
+                .map(|x| Box::new((x.0.to_complex(), x.1.clone()))),
+            boolean: self.boolean,
+        }
+    }
+
+    fn is_exact(&self) -> bool {
+        self.value.is_exact()
+    }
+
+    fn is_finite(&self) -> bool {
+        self.value.is_finite()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    fn is_bool(&self) -> bool {
+        self.boolean
+    }
+
+    fn check_compatible(&self, other: &Self) -> Result<(), Error> {
+        if self.dim != other.dim {
+            return Err(mismatch(&self.dim, &other.dim));
+        }
+        Ok(())
+    }
+
+    fn check_dimensionless(&self) -> Result<(), Error> {
+        self.check_compatible(&Self::one(self.context()))
+    }
+
+    fn convert(&self, target: &Self, name: &str) -> Result<Self, Error> {
+        self.check_compatible(target)?;
+        if target.is_zero() {
+            return Err(Error::division_by_zero(Span::default()));
+        }
+        Ok(Self {
+            value: self.value.clone(),
+            dim: self.dim,
+            unit: Some(Box::new((target.value.clone(), name.to_owned()))),
+            boolean: false,
+        })
+    }
+
+    fn i(ctx: N::Context) -> Option<Self> {
+        N::i(ctx).map(Self::plain)
+    }
+
+    fn pi(ctx: N::Context) -> Self {
+        Self::plain(N::pi(ctx))
+    }
+
+    fn e(ctx: N::Context) -> Self {
+        Self::plain(N::e(ctx))
+    }
+
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        x.check_dimensionless()?;
+        let value = self.value.pow(&x.value)?;
+        if self.dim.is_none() {
+            return Ok(Self::plain(value));
+        }
+        let dim = x
+            .value
+            .to_rational()
+            .and_then(|r| self.dim.pow(&r))
+            .ok_or_else(|| {
+                Error::domain(
+                    format!("{} can't be raised to this power", self.dim),
+                    Span::default(),
+                )
+            })?;
+        Ok(Self::new(value, dim))
+    }
+
+    fn root(&self, n: u32) -> Result<Self, Error> {
+        let dim = self.dim.root(n).ok_or_else(|| {
+            Error::domain(
+                format!("{} has no root of degree {n}", self.dim),
+                Span::default(),
+            )
+        })?;
+        Ok(Self::new(self.value.root(n)?, dim))
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        Self::plain(self.value.log(&x.value))
+    }
+
+    fn floor(&self) -> Self {
+        self.keep(self.value.floor())
+    }
+
+    fn ceil(&self) -> Self {
+        self.keep(self.value.ceil())
+    }
+
+    fn round(&self) -> Self {
+        self.keep(self.value.round())
+    }
+
+    fn trunc(&self) -> Self {
+        self.keep(self.value.trunc())
+    }
+
+    fn abs(&self) -> Self {
+        self.keep(self.value.abs())
+    }
+
+    fn signum(&self) -> Self {
+        Self::plain(self.value.signum())
+    }
+
+    fn re(&self) -> Self {
+        self.keep(self.value.re())
+    }
+
+    fn im(&self) -> Self {
+        self.keep(self.value.im())
+    }
+
+    fn conj(&self) -> Self {
+        self.keep(self.value.conj())
+    }
+
+    fn arg(&self) -> Self {
+        Self::plain(self.value.arg())
+    }
+
+    fn sin(&self) -> Self {
+        Self::plain(self.value.sin())
+    }
+
+    fn cos(&self) -> Self {
+        Self::plain(self.value.cos())
+    }
+
+    fn tan(&self) -> Self {
+        Self::plain(self.value.tan())
+    }
+
+    fn asin(&self) -> Self {
+        Self::plain(self.value.asin())
+    }
+
+    fn acos(&self) -> Self {
+        Self::plain(self.value.acos())
+    }
+
+    fn atan(&self) -> Self {
+        Self::plain(self.value.atan())
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        Self::plain(self.value.atan2(&x.value))
+    }
+
+    fn exp(&self) -> Self {
+        Self::plain(self.value.exp())
+    }
+
+    fn ln(&self) -> Self {
+        Self::plain(self.value.ln())
+    }
+
+    fn gamma(&self) -> Result<Self, Error> {
+        self.value.gamma().map(Self::plain)
+    }
+}
+
+impl From<Complex> for Value {
+    fn from(x: Complex) -> Self {
+        Quantity::plain(x)
+    }
+}
+
+impl From<Symbolic> for Value {
+    fn from(x: Symbolic) -> Self {
+        Complex::from(x).into()
+    }
+}
+
+impl From<Rational> for Value {
+    fn from(x: Rational) -> Self {
+        Complex::from(x).into()
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Complex::from(x).into()
+    }
+}
+
+impl From<i64> for Value {
+    fn from(x: i64) -> Self {
+        Complex::from(x).into()
+    }
+}
+
+/// The shown unit is ignored, `1 km` equals `1000 m`.
+impl<N: PartialEq> PartialEq for Quantity<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.dim == other.dim && self.value == other.value && self.boolean == other.boolean
+    }
+}
+
+/// Only values of the same dimension are ordered.
+impl<N: PartialOrd> PartialOrd for Quantity<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.dim != other.dim {
+            return None;
+        }
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<N: Number> std::fmt::Debug for Quantity<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl<N: Number> Display for Quantity<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = self.bool_name() {
+            return f.write_str(name);
+        }
+        match self.in_unit() {
+            (x, Some(unit)) => f.write_str(&with_unit(x.to_string(), &unit)),
+            (x, None) => write!(f, "{x}"),
+        }
+    }
+}
+
+impl<N: Number> std::ops::Add for Quantity<N> {
+    type Output = Quantity<N>;
+    fn add(self, other: Quantity<N>) -> Quantity<N> {
+        Self::new(self.value + other.value, self.dim)
+    }
+}
+
+impl<N: Number> std::ops::Sub for Quantity<N> {
+    type Output = Quantity<N>;
+    fn sub(self, other: Quantity<N>) -> Quantity<N> {
+        Self::new(self.value - other.value, self.dim)
+    }
+}
+
+impl<N: Number> std::ops::Mul for Quantity<N> {
+    type Output = Quantity<N>;
+    fn mul(self, other: Quantity<N>) -> Quantity<N> {
+        Self::new(self.value * other.value, self.dim.mul(&other.dim))
+    }
+}
+
+impl<N: Number> std::ops::Div for Quantity<N> {
+    type Output = Quantity<N>;
+    fn div(self, other: Quantity<N>) -> Quantity<N> {
+        Self::new(self.value / other.value, self.dim.div(&other.dim))
+    }
+}
+
+impl<N: Number> std::ops::Rem for Quantity<N> {
+    type Output = Quantity<N>;
+    fn rem(self, other: Quantity<N>) -> Quantity<N> {
+        Self::new(self.value % other.value, self.dim)
+    }
+}
+
+impl<N: Number> std::ops::Neg for Quantity<N> {
+    type Output = Quantity<N>;
+    fn neg(self) -> Quantity<N> {
+        Self {
+            value: -self.value,
+            boolean: false,
+            ..self
+        }
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Quantity;
+    #[cfg(test)]
+    use crate::{
+        error::Error,
+        number::Number,
+        parser::{
+            test::{eval, output},
+            KnownLiterals, Parse,
+        },
+        tokenizer::tokenize,
+        unit::Unit,
+    };
+
+    #[cfg(test)]
+    fn unit(name: &str) -> Quantity<f64> {
+        Quantity::from_unit(&Unit::parse(name).unwrap(), ()).unwrap()
+    }
+
+    #[test]
+    fn test_quantity() {
+        let speed = unit("km") * Quantity::from_rational(&5.into(), ()) / unit("h");
+        assert_eq!(speed.dim.to_string(), "m/s");
+        assert!(matches!(
+            unit("m").check_compatible(&unit("s")),
+            Err(Error::DimensionMismatch { .. })
+        ));
+        let x = speed.convert(&(unit("m") / unit("s")), "m/s").unwrap();
+        assert!((x.in_unit().0 - 25.0 / 18.0).abs() < 1e-12);
+        assert_eq!(unit("N").to_string(), "1 N");
+        assert_eq!((unit("N") * unit("m")).to_string(), "1 J");
+        assert_eq!(
+            (unit("m") * unit("m")).root(2).map(|x| x.dim),
+            Ok(unit("m").dim)
+        );
+        assert!(unit("m").root(2).is_err());
+        assert!(unit("m").sin().check_dimensionless().is_ok());
+    }
+
+    #[test]
+    fn test_eval() {
+        let known_literals = &mut KnownLiterals::new();
+        assert!(matches!(
+            eval("3 m + 2 s", known_literals),
+            Err(Error::DimensionMismatch { .. })
+        ));
+        tokenize("f(s) = s^2").parse(known_literals).unwrap();
+        assert_eq!(output("5 km/h in m/s", known_literals), "25/18 m/s");
+        assert_eq!(output("2 N * 3 m", known_literals), "6 J");
+        assert_eq!(output("12 in to cm", known_literals), "762/25 cm");
+        assert_eq!(output("1 mi in km", known_literals), "25146/15625 km");
+        assert_eq!(output("3 m + 50 cm", known_literals), "7/2 m");
+        assert_eq!(output("sqrt(9 m^2)", known_literals), "3 m");
+        assert_eq!(output("1 kg * 1 m/s^2 in N", known_literals), "1 N");
+        assert_eq!(output("6 Pa in hPa", known_literals), "3/50 hPa");
+        assert_eq!(output("(3 m)/(2 m)", known_literals), "3/2");
+        assert_eq!(
+            output("2 h in s m", known_literals),
+            "Dimension mismatch: s and m*s"
+        );
+        assert_eq!(
+            output("sin(5 m)", known_literals),
+            "Dimension mismatch: m and a plain number"
+        );
+        //Parameters and variables shadow units
+        assert_eq!(output("f(2)", known_literals), "4");
+        assert_eq!(output("f(3 s)", known_literals), "9 s^2");
+        assert_eq!(output("m = 2", known_literals), "2");
+        assert_eq!(output("3 m", known_literals), "6");
+    }
+}
@@ -1,127 +1,607 @@
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+use crate::error::{Error, Span};
+use crate::number::Number;
+use crate::symbolic::Symbolic;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "Stored", try_from = "Stored")]
 pub struct Rational {
-    p: f64, //numerator
-    q: f64, //denominator
+    p: BigInt, //numerator
+    q: BigInt, //denominator
+    //Set once the value went through a float approximation
+    inexact: bool,
 }
 
-fn gcd(x: f64, y: f64) -> f64 {
-    let mut x = x.abs();
-    let mut y = y.abs();
-    while y > 0.0 {
-        let rem = x % y;
-        x = y;
-        y = rem;
+// How a `Rational` is written, with the digits as decimal strings so other
+// programs don't need to know how num-bigint encodes its numbers.
+#[derive(Serialize, Deserialize)]
+struct Stored {
+    numerator: String,
+    denominator: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    inexact: bool,
+}
+
+impl From<Rational> for Stored {
+    fn from(x: Rational) -> Self {
+        Stored {
+            numerator: x.p.to_string(),
+            denominator: x.q.to_string(),
+            inexact: x.inexact,
+        }
     }
-    x
 }
 
-fn lcm(x: f64, y: f64) -> f64 {
-    x * y / gcd(x, y)
+impl TryFrom<Stored> for Rational {
+    type Error = String;
+
+    fn try_from(x: Stored) -> Result<Self, String> {
+        let parse = |s: &str| {
+            s.parse::<BigInt>()
+                .map_err(|_| format!("invalid integer {s:?}"))
+        };
+        let (p, q) = (parse(&x.numerator)?, parse(&x.denominator)?);
+        //Only approximations overflow to infinity or NaN
+        if q.is_zero() && !x.inexact {
+            return Err(String::from("zero denominator"));
+        }
+        Ok(Rational::new(p, q).inexact_if(x.inexact))
+    }
+}
+
+/// How `Rational::to_fixed` rounds the digits it drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Ties go to the even neighbour, `0.125` is `0.12`.
+    #[default]
+    HalfEven,
+    /// Ties go away from zero, `0.125` is `0.13`.
+    HalfUp,
+    TowardZero,
+    Floor,
+    Ceil,
+}
+
+impl Rounding {
+    pub const ALL: [Rounding; 5] = [
+        Rounding::HalfEven,
+        Rounding::HalfUp,
+        Rounding::TowardZero,
+        Rounding::Floor,
+        Rounding::Ceil,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Rounding::ALL.into_iter().find(|x| x.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rounding::HalfEven => "half-even",
+            Rounding::HalfUp => "half-up",
+            Rounding::TowardZero => "toward-zero",
+            Rounding::Floor => "floor",
+            Rounding::Ceil => "ceil",
+        }
+    }
+}
+
+// Exact powers bigger than this many bits are approximated instead.
+pub(crate) const MAX_EXACT_BITS: u64 = 1 << 20;
+
+// Digits `Rational::to_repeating` shows before giving up on finding the period.
+const MAX_REPEATING_DIGITS: usize = 256;
+
+/// Most digits the decimal formats show, more would take too long to work
+/// out.
+pub const MAX_PLACES: u32 = 1000;
+
+// Largest exponent a number literal like `1e5` may have.
+const MAX_EXPONENT: u64 = 100_000;
+
+// Largest denominator tried when looking for an exact logarithm.
+pub(crate) const MAX_LOG_DENOM: i64 = 16;
+
+fn gcd(x: &BigInt, y: &BigInt) -> BigInt {
+    x.gcd(y)
+}
+
+fn lcm(x: &BigInt, y: &BigInt) -> BigInt {
+    x.lcm(y)
 }
 
 impl Rational {
-    pub fn new(a: f64, b: f64) -> Self {
-        Self { p: a, q: b }
+    pub fn new(a: impl Into<BigInt>, b: impl Into<BigInt>) -> Self {
+        Self {
+            p: a.into(),
+            q: b.into(),
+            inexact: false,
+        }
+    }
+
+    /// A float result, marked as inexact.
+    pub fn approx(x: f64) -> Self {
+        Rational::from(x).inexact_if(true)
+    }
+
+    /// Marks the value as approximated when `inexact` is set.
+    pub fn inexact_if(mut self, inexact: bool) -> Self {
+        self.inexact |= inexact;
+        self
+    }
+
+    /// False if any step producing this value had to be approximated.
+    pub fn is_exact(&self) -> bool {
+        !self.inexact
     }
 
     pub fn zero() -> Self {
-        Self { p: 0.0, q: 1.0 }
+        Self::new(0, 1)
     }
 
     pub fn one() -> Self {
-        Self { p: 1.0, q: 1.0 }
+        Self::new(1, 1)
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.p
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.q
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.p.is_zero() && !self.q.is_zero()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !self.q.is_zero() && (&self.p % &self.q).is_zero()
     }
 
     pub fn reduce(&self) -> Self {
-        let mut p = self.p;
-        let mut q = self.q;
-        if q.is_sign_negative() {
-            q *= -1.0;
-            p *= -1.0;
+        let mut p = self.p.clone();
+        let mut q = self.q.clone();
+        if q.is_negative() {
+            q = -q;
+            p = -p;
+        }
+        let g = gcd(&p, &q);
+        if !g.is_zero() {
+            p /= &g;
+            q /= &g;
+        }
+        Self {
+            p,
+            q,
+            inexact: self.inexact,
         }
-        let g = gcd(p, q);
-        p /= g;
-        q /= g;
-        Self { p, q }
     }
 
     //Common denominator
     pub fn com_den(&mut self, mut other: Rational) -> Rational {
-        let l = lcm(self.q, other.q);
-        self.p *= l / self.q;
-        self.q = l;
-        other.p *= l / other.q;
+        let l = lcm(&self.q, &other.q);
+        if l.is_zero() {
+            return other;
+        }
+        self.p *= &l / &self.q;
+        self.q = l.clone();
+        other.p *= &l / &other.q;
         other.q = l;
         other
     }
 
     pub fn to_float(&self) -> f64 {
-        self.p / self.q
+        if self.q.is_zero() {
+            return self.p.to_f64().unwrap_or(0.0) / 0.0;
+        }
+        if let (Some(p), Some(q)) = (self.p.to_f64(), self.q.to_f64()) {
+            if p.is_finite() && q.is_finite() {
+                return p / q;
+            }
+        }
+        // Either side is too big for an f64, scale the quotient so it keeps
+        // 64 significant bits and shift the exponent back afterwards.
+        let shift = self.p.bits() as i64 - self.q.bits() as i64 - 64;
+        let quot = if shift > 0 {
+            &self.p / (&self.q << shift as usize)
+        } else {
+            (&self.p << (-shift) as usize) / &self.q
+        };
+        quot.to_f64().unwrap_or(0.0) * 2f64.powf(shift as f64)
     }
 
     pub fn abs(&self) -> Self {
         Self {
             p: self.p.abs(),
             q: self.q.abs(),
+            inexact: self.inexact,
         }
         .reduce()
     }
 
+    pub fn signum(&self) -> Self {
+        let x = self.reduce();
+        Rational::from(x.p.signum()).inexact_if(self.inexact)
+    }
+
+    pub fn floor(&self) -> Self {
+        let x = self.reduce();
+        Rational::from(x.p.div_floor(&x.q)).inexact_if(self.inexact)
+    }
+
+    pub fn ceil(&self) -> Self {
+        let x = self.reduce();
+        Rational::from(x.p.div_ceil(&x.q)).inexact_if(self.inexact)
+    }
+
+    pub fn trunc(&self) -> Self {
+        let x = self.reduce();
+        Rational::from(x.p / x.q).inexact_if(self.inexact)
+    }
+
+    //Halves are rounded away from zero
+    pub fn round(&self) -> Self {
+        let half = Rational::new(1, 2);
+        if self < &Rational::zero() {
+            -(-self.clone() + half).floor()
+        } else {
+            (self.clone() + half).floor()
+        }
+    }
+
+    /// The integer `mode` rounds the value to.
+    pub fn round_with(&self, mode: Rounding) -> BigInt {
+        let x = self.reduce();
+        match mode {
+            Rounding::HalfEven => x.round_half_even(),
+            Rounding::HalfUp => x.round().p,
+            Rounding::TowardZero => x.p / x.q,
+            Rounding::Floor => x.p.div_floor(&x.q),
+            Rounding::Ceil => x.p.div_ceil(&x.q),
+        }
+    }
+
+    /// Errors when more than `MAX_PLACES` digits are asked for.
+    pub fn check_places(places: u32) -> Result<(), Error> {
+        if places > MAX_PLACES {
+            return Err(Error::domain(
+                format!("at most {MAX_PLACES} digits can be shown"),
+                Span::default(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Decimal form with exactly `places` digits after the point, rounded
+    /// with `mode`: `1/3` is `0.33`.
+    pub fn to_fixed(&self, places: u32, mode: Rounding) -> Result<String, Error> {
+        Rational::check_places(places)?;
+        if self.q.is_zero() {
+            return Ok(String::from("undefined"));
+        }
+        let scale = BigInt::from(10).pow(places);
+        let n = (self.clone() * Rational::from(scale)).round_with(mode);
+        let digits = n.abs().to_string();
+        let places = places as usize;
+        let digits = format!("{digits:0>width$}", width = places + 1);
+        let (int, frac) = digits.split_at(digits.len() - places);
+        let sign = if n.is_negative() { "-" } else { "" };
+        Ok(if frac.is_empty() {
+            format!("{sign}{int}")
+        } else {
+            format!("{sign}{int}.{frac}")
+        })
+    }
+
     pub fn sqrt(&self) -> Self {
-        Self {
-            p: self.p.sqrt(),
-            q: self.q.sqrt(),
+        self.root(2)
+    }
+
+    /// Exact `n`th root, `None` when the result isn't rational.
+    pub fn nth_root(&self, n: u32) -> Option<Self> {
+        let x = self.reduce();
+        if n == 0 || x.q.is_zero() || (x.p.is_negative() && n.is_even()) {
+            return None;
         }
-        .reduce()
+        let root = |x: &BigInt| {
+            let r = x.nth_root(n);
+            (pow_big(r.clone(), n.into()) == *x).then_some(r)
+        };
+        Some(Rational::new(root(&x.p)?, root(&x.q)?).inexact_if(x.inexact))
+    }
+
+    pub fn root(&self, n: u32) -> Self {
+        self.nth_root(n)
+            .unwrap_or_else(|| self.pow(Rational::new(1, n)))
     }
 
     pub fn powf(&self, x: f64) -> Self {
-        Self {
-            p: self.p.powf(x),
-            q: self.q.powf(x),
+        Rational::approx(self.to_float().powf(x))
+    }
+
+    /// `self^n` by repeated squaring, negative exponents take the reciprocal.
+    pub fn pow_int(&self, n: &BigInt) -> Self {
+        let x = self.reduce();
+        if n.is_negative() {
+            return (Rational::one() / x).pow_int(&-n);
+        }
+        if x.q.is_one() && x.p.magnitude() <= &One::one() {
+            //0, 1 and -1 stay small whatever the exponent
+            return match (n.is_zero(), x.p.is_negative() && n.is_even()) {
+                (true, _) | (_, true) => Rational::one().inexact_if(x.inexact),
+                _ => x,
+            };
+        }
+        let bits = x.p.bits().max(x.q.bits());
+        match n.to_u64() {
+            Some(e) if bits.saturating_mul(e) <= MAX_EXACT_BITS => Self {
+                p: pow_big(x.p, e),
+                q: pow_big(x.q, e),
+                inexact: x.inexact,
+            },
+            _ => x.powf(n.to_f64().unwrap_or(f64::INFINITY)),
         }
-        .reduce()
     }
 
+    /// Exact whenever the result is rational, `8^(1/3)` is exactly 2.
     pub fn pow(&self, x: Rational) -> Self {
-        let cp = self.powf(x.to_float());
-        Self { p: cp.p, q: cp.q }.reduce()
+        let x = x.reduce();
+        if x.is_integer() {
+            return self.pow_int(&x.p).inexact_if(x.inexact);
+        }
+        // a^(p/q) = (a^(1/q))^p
+        if let Some(root) = x.q.to_u32().and_then(|q| self.nth_root(q)) {
+            return root.pow_int(&x.p).inexact_if(x.inexact);
+        }
+        if self.p.is_negative() != self.q.is_negative() && x.q.is_odd() {
+            let y = (-self.clone()).pow(x.clone());
+            return if x.p.is_odd() { -y } else { y };
+        }
+        self.powf(x.to_float()).inexact_if(true)
     }
 
+    /// Logarithm of `self` in base `x`, exact when it is a small fraction.
     pub fn log(&self, x: Rational) -> Self {
-        let x = x.to_float();
-        Self {
-            p: self.p.log(x) - self.q.log(x),
-            q: 1.0,
+        let f = self.to_float().log(x.to_float());
+        let inexact = self.inexact || x.inexact;
+        if f.is_finite() {
+            // base^(p/q) == self  <=>  base^p == self^q
+            for q in 1..=MAX_LOG_DENOM {
+                let p = (f * q as f64).round();
+                let (a, b) = (x.pow_int(&(p as i64).into()), self.pow_int(&q.into()));
+                if a.is_exact() && b.is_exact() && (a - b).is_zero() {
+                    return Rational::new(p as i64, q).reduce().inexact_if(inexact);
+                }
+            }
         }
-        .reduce()
+        Rational::approx(f)
     }
-}
 
-impl std::fmt::Debug for Rational {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // f.write_fmt(format_args!("{}", self.to_float()))
-        let (p, q) = self.reduce().into();
-        if q == 0.0 {
-            return f.write_str("undefined");
+    /// Decimal form rounded to `digits` significant digits, using an
+    /// exponent for very large or small values: `1.5e-9`.
+    pub fn to_significant(&self, digits: u32) -> String {
+        let x = self.reduce();
+        if x.q.is_zero() {
+            return String::from("undefined");
         }
-        if q == 1.0 {
-            f.write_fmt(format_args!("{}", p))
+        if x.p.is_zero() {
+            return String::from("0");
+        }
+        let sign = if x.p.is_negative() { "-" } else { "" };
+        let digits = digits.max(1) as i64;
+        let (s, k) = x.abs().digits(digits);
+        let out = if (-7..21).contains(&k) {
+            if k >= digits - 1 {
+                format!("{s}{}", "0".repeat((k - digits + 1) as usize))
+            } else if k >= 0 {
+                format!("{}.{}", &s[..=k as usize], &s[k as usize + 1..])
+            } else {
+                format!("0.{}{s}", "0".repeat((-k - 1) as usize))
+            }
         } else {
-            f.write_fmt(format_args!("{}/{}", p, q))
+            let (head, tail) = s.split_at(1);
+            let tail = tail.trim_end_matches('0');
+            let mantissa = if tail.is_empty() {
+                head.to_owned()
+            } else {
+                format!("{head}.{tail}")
+            };
+            return format!("{sign}{mantissa}e{k}");
+        };
+        let out = if out.contains('.') {
+            out.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            &out
+        };
+        format!("{sign}{out}")
+    }
+
+    // The first `digits` significant digits of a positive value, rounded
+    // half to even, and `k` such that 10^k <= x < 10^(k+1) after rounding.
+    fn digits(&self, digits: i64) -> (String, i64) {
+        let ten = Rational::from(10);
+        let mut k =
+            ((self.p.bits() as f64 - self.q.bits() as f64) * std::f64::consts::LOG10_2) as i64;
+        while ten.pow_int(&k.into()) > *self {
+            k -= 1;
+        }
+        while ten.pow_int(&(k + 1).into()) <= *self {
+            k += 1;
+        }
+        let scaled = self.clone() / ten.pow_int(&(k - digits + 1).into());
+        let mut n = scaled.round_half_even();
+        if n.to_string().len() as i64 > digits {
+            n /= 10;
+            k += 1;
+        }
+        (n.to_string(), k)
+    }
+
+    /// `digits` significant digits with an exponent: `1.5e3`. With
+    /// `engineering` the exponent is a multiple of three: `15e3`.
+    pub fn to_scientific(&self, digits: u32, engineering: bool) -> String {
+        let x = self.reduce();
+        if x.q.is_zero() {
+            return String::from("undefined");
+        }
+        if x.p.is_zero() {
+            return String::from("0");
+        }
+        let sign = if x.p.is_negative() { "-" } else { "" };
+        let (mut s, k) = x.abs().digits(digits.max(1) as i64);
+        let e = if engineering {
+            Integer::div_floor(&k, &3) * 3
+        } else {
+            k
+        };
+        let int = (k - e + 1) as usize;
+        if s.len() < int {
+            s.push_str(&"0".repeat(int - s.len()));
+        }
+        let (head, tail) = s.split_at(int);
+        let tail = tail.trim_end_matches('0');
+        if tail.is_empty() {
+            format!("{sign}{head}e{e}")
+        } else {
+            format!("{sign}{head}.{tail}e{e}")
+        }
+    }
+
+    /// Whole part and proper fraction: `3/2` is `1 1/2`.
+    pub fn to_mixed(&self) -> String {
+        let x = self.reduce();
+        if x.q.is_zero() || x.is_integer() || x.p.abs() < x.q {
+            return x.to_string();
+        }
+        let sign = if x.p.is_negative() { "-" } else { "" };
+        let (int, rest) = x.p.abs().div_rem(&x.q);
+        format!("{sign}{int} {rest}/{}", x.q)
+    }
+
+    /// Decimal form with the repeating part in parentheses: `1/6` is
+    /// `0.1(6)`. Periods too long to show end in `...` instead.
+    pub fn to_repeating(&self) -> String {
+        let x = self.reduce();
+        if x.q.is_zero() {
+            return String::from("undefined");
+        }
+        let sign = if x.p.is_negative() { "-" } else { "" };
+        let (int, mut rem) = x.p.abs().div_rem(&x.q);
+        if rem.is_zero() {
+            return format!("{sign}{int}");
+        }
+        let mut digits = String::new();
+        //Where each remainder was first seen, the digits repeat from there
+        let mut seen: HashMap<BigInt, usize> = HashMap::new();
+        while !rem.is_zero() {
+            if let Some(start) = seen.get(&rem) {
+                let (head, period) = digits.split_at(*start);
+                return format!("{sign}{int}.{head}({period})");
+            }
+            if digits.len() >= MAX_REPEATING_DIGITS {
+                return format!("{sign}{int}.{digits}...");
+            }
+            seen.insert(rem.clone(), digits.len());
+            let (d, r) = (rem * 10u32).div_rem(&x.q);
+            digits.push_str(&d.to_string());
+            rem = r;
+        }
+        format!("{sign}{int}.{digits}")
+    }
+
+    /// Integers in base 16, 8 or 2 with their prefix: `0x1f`. `None` for
+    /// fractions.
+    pub fn to_radix(&self, radix: u32) -> Option<String> {
+        let x = self.reduce();
+        if !x.is_integer() {
+            return None;
+        }
+        let prefix = match radix {
+            16 => "0x",
+            8 => "0o",
+            2 => "0b",
+            _ => "",
+        };
+        let sign = if x.p.is_negative() { "-" } else { "" };
+        Some(format!("{sign}{prefix}{}", x.p.abs().to_str_radix(radix)))
+    }
+
+    // Nearest integer, ties go to the even one.
+    fn round_half_even(&self) -> BigInt {
+        let x = self.reduce();
+        let (q, r) = x.p.div_mod_floor(&x.q);
+        let twice = &r * 2;
+        if twice > x.q || (twice == x.q && q.is_odd()) {
+            q + 1
+        } else {
+            q
         }
     }
 }
+
+fn pow_big(mut base: BigInt, mut e: u64) -> BigInt {
+    let mut acc = BigInt::one();
+    while e > 0 {
+        if e & 1 == 1 {
+            acc *= &base;
+        }
+        e >>= 1;
+        if e > 0 {
+            base = &base * &base;
+        }
+    }
+    acc
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        let (a, b) = (self.reduce(), other.reduce());
+        if a.q.is_zero() || b.q.is_zero() {
+            return None;
+        }
+        Some((&a.p * &b.q).cmp(&(&b.p * &a.q)))
+    }
+}
+
+// Compares the values like `partial_cmp`, `2/4 == 1/2`. Whether they are
+// exact doesn't matter.
+impl PartialEq for Rational {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.reduce(), other.reduce());
+        a.p == b.p && a.q == b.q
+    }
+}
+
+impl Eq for Rational {}
+
+impl std::fmt::Debug for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+// `{:.2}` prints fixed decimals rounded half to even.
 impl std::fmt::Display for Rational {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // f.write_fmt(format_args!("{}", self.to_float()))
+        if let Some(places) = f.precision() {
+            let places = u32::try_from(places).unwrap_or(u32::MAX).min(MAX_PLACES);
+            let out = self
+                .to_fixed(places, Rounding::HalfEven)
+                .map_err(|_| std::fmt::Error)?;
+            return f.write_str(&out);
+        }
         let (p, q) = self.reduce().into();
-        if q == 0.0 {
+        if q.is_zero() {
             return f.write_str("undefined");
         }
-        if q == 1.0 {
+        if q.is_one() {
             f.write_fmt(format_args!("{}", p))
         } else {
             f.write_fmt(format_args!("{}/{}", p, q))
@@ -130,20 +610,91 @@ impl std::fmt::Display for Rational {
 }
 
 impl From<f64> for Rational {
-    fn from(mut p: f64) -> Rational {
+    fn from(p: f64) -> Rational {
+        if !p.is_finite() {
+            let p = if p.is_nan() { 0 } else { p.signum() as i32 };
+            return Rational::new(p, 0);
+        }
+        // `Display` for f64 never uses an exponent, so the shortest decimal
+        // representation can be read back digit by digit without loss.
         let sp = p.to_string();
-        let sp = sp.split(".").collect::<Vec<_>>();
-        let mut q = 1.0;
-        if sp.len() == 2 {
-            if let Some(last) = sp.last() {
-                let dec_places = last.len() as i32;
-                let exponent = f64::powi(10.0, dec_places);
-                p *= exponent;
-                q *= exponent;
+        let (int, frac) = sp.split_once('.').unwrap_or((&sp, ""));
+        let digits = format!("{int}{frac}");
+        let p = digits.parse::<BigInt>().unwrap_or_default();
+        let q = num_traits::pow(BigInt::from(10), frac.len());
+
+        Rational::new(p, q).reduce()
+    }
+}
+
+// Whether every `_` in `digits` sits between two digits, `1_000`.
+fn separators_ok(digits: &str, radix: u32) -> bool {
+    let chars: Vec<char> = digits.chars().collect();
+    chars.iter().enumerate().all(|(i, c)| {
+        *c != '_'
+            || i > 0
+                && chars[i - 1].is_digit(radix)
+                && chars.get(i + 1).is_some_and(|x| x.is_digit(radix))
+    })
+}
+
+/// Reads number literals exactly: `0x1F`, `0o17`, `0b1010`, `1_000`,
+/// `0.1`, `6.02E23` and `1e-5`.
+impl std::str::FromStr for Rational {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || Error::Tokenize {
+            lexeme: s.to_owned(),
+            span: Span::default(),
+        };
+        let lower = s.to_lowercase();
+        let radix = match lower.get(..2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            let digits = &lower[2..];
+            if digits.is_empty() || !separators_ok(digits, radix) {
+                return Err(err());
             }
+            let digits = digits.replace('_', "");
+            return BigInt::parse_bytes(digits.as_bytes(), radix)
+                .map(Rational::from)
+                .ok_or_else(err);
         }
-
-        Rational { p, q }.reduce()
+        let (mantissa, exp) = match lower.split_once('e') {
+            Some((m, e)) => {
+                let e = e.strip_prefix('+').unwrap_or(e);
+                let digits = e.strip_prefix('-').unwrap_or(e);
+                if digits.is_empty() || !digits.bytes().all(|x| x.is_ascii_digit()) {
+                    return Err(err());
+                }
+                let e = e.parse::<i64>().map_err(|_| err())?;
+                if e.unsigned_abs() > MAX_EXPONENT {
+                    return Err(err());
+                }
+                (m, e)
+            }
+            None => (lower.as_str(), 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let valid = |x: &str| x.chars().all(|c| c.is_ascii_digit() || c == '_');
+        if int.len() + frac.len() == 0
+            || !valid(int)
+            || !valid(frac)
+            || !separators_ok(int, 10)
+            || !separators_ok(frac, 10)
+        {
+            return Err(err());
+        }
+        let frac = frac.replace('_', "");
+        let digits = format!("{}{frac}", int.replace('_', ""));
+        let p = digits.parse::<BigInt>().map_err(|_| err())?;
+        let scale = Rational::from(10).pow_int(&BigInt::from(exp - frac.len() as i64));
+        Ok((Rational::from(p) * scale).reduce())
     }
 }
 
@@ -153,8 +704,20 @@ impl From<f32> for Rational {
     }
 }
 
-impl From<Rational> for (f64, f64) {
-    fn from(r: Rational) -> (f64, f64) {
+impl From<i64> for Rational {
+    fn from(p: i64) -> Rational {
+        Rational::new(p, 1)
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(p: BigInt) -> Rational {
+        Rational::new(p, 1)
+    }
+}
+
+impl From<Rational> for (BigInt, BigInt) {
+    fn from(r: Rational) -> (BigInt, BigInt) {
         (r.p, r.q)
     }
 }
@@ -162,6 +725,7 @@ impl From<Rational> for (f64, f64) {
 impl std::ops::Add<Rational> for Rational {
     type Output = Rational;
     fn add(mut self, other: Rational) -> Rational {
+        self.inexact |= other.inexact;
         let p = self.com_den(other).p;
         self.p += p;
         self.reduce()
@@ -171,6 +735,7 @@ impl std::ops::Add<Rational> for Rational {
 impl std::ops::Sub<Rational> for Rational {
     type Output = Rational;
     fn sub(mut self, other: Rational) -> Rational {
+        self.inexact |= other.inexact;
         let p = self.com_den(other).p;
         self.p -= p;
         self.reduce()
@@ -180,6 +745,7 @@ impl std::ops::Sub<Rational> for Rational {
 impl std::ops::Mul<Rational> for Rational {
     type Output = Rational;
     fn mul(mut self, other: Rational) -> Rational {
+        self.inexact |= other.inexact;
         self.p *= other.p;
         self.q *= other.q;
         self.reduce()
@@ -189,6 +755,7 @@ impl std::ops::Mul<Rational> for Rational {
 impl std::ops::Div<Rational> for Rational {
     type Output = Rational;
     fn div(mut self, other: Rational) -> Rational {
+        self.inexact |= other.inexact;
         self.p *= other.q;
         self.q *= other.p;
         self.reduce()
@@ -207,105 +774,408 @@ impl std::ops::Neg for Rational {
 impl std::ops::Rem for Rational {
     type Output = Rational;
 
-    fn rem(self, other: Self) -> Self::Output {
-        Self {
-            p: self.to_float() % other.to_float(),
-            q: 1.0,
+    // Truncated remainder, the sign follows the dividend like `f64::rem`.
+    fn rem(mut self, other: Self) -> Self::Output {
+        self.inexact |= other.inexact;
+        let other = self.com_den(other);
+        if other.p.is_zero() {
+            return Rational::new(0, 0);
         }
-        .reduce()
+        self.p %= other.p;
+        self.reduce()
+    }
+}
+
+impl Number for Rational {
+    type Context = ();
+
+    fn from_rational(x: &Rational, _: ()) -> Self {
+        x.clone()
+    }
+
+    fn from_float(x: f64, _: ()) -> Self {
+        Rational::approx(x)
+    }
+
+    fn context(&self) {}
+
+    fn to_rational(&self) -> Option<Rational> {
+        (!self.q.is_zero()).then(|| self.clone())
+    }
+
+    fn to_float(&self) -> f64 {
+        Rational::to_float(self)
+    }
+
+    fn to_symbolic(&self) -> Symbolic {
+        self.clone().into()
+    }
+
+    fn is_exact(&self) -> bool {
+        Rational::is_exact(self)
+    }
+
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        if *self < Rational::zero() && x.reduce().q.is_even() {
+            return Err(Error::domain(
+                "even root of a negative number",
+                Span::default(),
+            ));
+        }
+        Ok(Rational::pow(self, x.clone()))
+    }
+
+    fn root(&self, n: u32) -> Result<Self, Error> {
+        if *self < Rational::zero() && n.is_even() {
+            return Err(Error::domain(
+                "even root of a negative number",
+                Span::default(),
+            ));
+        }
+        Ok(Rational::root(self, n))
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        Rational::log(self, x.clone())
+    }
+
+    fn abs(&self) -> Self {
+        Rational::abs(self)
+    }
+
+    fn signum(&self) -> Self {
+        Rational::signum(self)
     }
 }
 
 mod test {
     #[cfg(test)]
-    use super::Rational;
+    use super::{Error, Rational, Rounding};
+    #[cfg(test)]
+    use num_bigint::BigInt;
 
     #[test]
     fn reduce() {
-        assert_eq!(Rational::new(10.0, -2.0).reduce(), Rational::new(-5.0, 1.0));
-        assert_eq!(Rational::new(-2.0, -10.0).reduce(), Rational::new(1.0, 5.0));
-        assert_eq!(Rational::new(3.0, 2.0).reduce(), Rational::new(3.0, 2.0));
+        let reduced = |p: i128, q: i128| {
+            let (p, q): (BigInt, BigInt) = Rational::new(p, q).reduce().into();
+            format!("{p}/{q}")
+        };
+        assert_eq!(reduced(10, -2), "-5/1");
+        assert_eq!(reduced(-2, -10), "1/5");
+        assert_eq!(reduced(3, 2), "3/2");
+        assert_eq!(
+            reduced(12341241241, 123123312213132312132213),
+            "12341241241/123123312213132312132213"
+        );
+        // Past 2^53 every step has to stay exact to find the common factor.
         assert_eq!(
-            Rational::new(12341241241.0, 123123312213132312132213.0).reduce(),
-            Rational::new(12341241241.0, 123123312213132312132213.0)
+            reduced(
+                123123312213132312132213i128 * 7,
+                123123312213132312132213i128 * 11
+            ),
+            "7/11"
         );
     }
 
+    #[test]
+    fn eq() {
+        // Equal values are equal however they are written, like `partial_cmp`.
+        assert_eq!(Rational::new(2, 4), Rational::new(1, 2));
+        assert_eq!(Rational::new(-3, -6), Rational::new(1, 2));
+        assert_eq!(
+            Rational::new(2, 4).partial_cmp(&Rational::new(1, 2)),
+            Some(std::cmp::Ordering::Equal)
+        );
+        assert_ne!(Rational::new(1, 2), Rational::new(1, 3));
+        assert_ne!(Rational::new(1, 2), Rational::new(-1, 2));
+        assert_eq!(Rational::new(2, 4).inexact_if(true), Rational::new(1, 2));
+        assert_eq!(Rational::new(2, 0), Rational::new(1, 0));
+        assert_ne!(Rational::new(1, 0), Rational::new(-1, 0));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let big = Rational::new(9007199254740993i64, 1);
+        assert_eq!(
+            (big.clone() + Rational::one()).to_string(),
+            "9007199254740994"
+        );
+        assert_eq!(
+            (big.clone() * big.clone()).to_string(),
+            "81129638414606699710187514626049"
+        );
+        assert_eq!((big.clone() - big).to_string(), "0");
+        assert_eq!(
+            (Rational::new(1, 3) + Rational::new(1, 6)).to_string(),
+            "1/2"
+        );
+        assert_eq!(
+            (Rational::new(1, 3) / Rational::new(-2, 3)).to_string(),
+            "-1/2"
+        );
+        assert_eq!(
+            (Rational::new(7, 2) % Rational::new(3, 2)).to_string(),
+            "1/2"
+        );
+        assert_eq!(
+            (Rational::new(-7, 2) % Rational::new(3, 2)).to_string(),
+            "-1/2"
+        );
+        assert_eq!(Rational::new(1, 0).to_string(), "undefined");
+    }
+
+    #[test]
+    fn rounding() {
+        let x = Rational::new(-7, 2);
+        assert_eq!(x.floor().to_string(), "-4");
+        assert_eq!(x.ceil().to_string(), "-3");
+        assert_eq!(x.trunc().to_string(), "-3");
+        assert_eq!(x.round().to_string(), "-4");
+        assert_eq!(x.signum().to_string(), "-1");
+        assert_eq!(Rational::new(7, 3).round().to_string(), "2");
+        assert_eq!(Rational::new(5, 2).round().to_string(), "3");
+        assert_eq!(Rational::zero().signum().to_string(), "0");
+    }
+
+    #[test]
+    fn fixed() {
+        let r = |p: i64, q: i64| Rational::new(p, q);
+        assert_eq!(r(1, 3).to_fixed(2, Rounding::HalfEven).unwrap(), "0.33");
+        assert_eq!(r(2, 3).to_fixed(2, Rounding::TowardZero).unwrap(), "0.66");
+        assert_eq!(r(1, 8).to_fixed(2, Rounding::HalfEven).unwrap(), "0.12");
+        assert_eq!(r(1, 8).to_fixed(2, Rounding::HalfUp).unwrap(), "0.13");
+        assert_eq!(r(-1, 8).to_fixed(2, Rounding::HalfUp).unwrap(), "-0.13");
+        assert_eq!(r(-1, 8).to_fixed(2, Rounding::Floor).unwrap(), "-0.13");
+        assert_eq!(r(-1, 8).to_fixed(2, Rounding::Ceil).unwrap(), "-0.12");
+        assert_eq!(r(-1, 1000).to_fixed(2, Rounding::Ceil).unwrap(), "0.00");
+        assert_eq!(r(1999, 2).to_fixed(0, Rounding::HalfEven).unwrap(), "1000");
+        assert_eq!(r(5, 1).to_fixed(3, Rounding::Floor).unwrap(), "5.000");
+        assert_eq!(format!("{:.3}", r(2, 3)), "0.667");
+        assert_eq!(
+            r(1, 3).to_fixed(1000, Rounding::HalfEven).unwrap().len(),
+            1002
+        );
+        assert_eq!(
+            r(1, 3).to_fixed(1001, Rounding::HalfEven),
+            Err(Error::domain(
+                "at most 1000 digits can be shown",
+                Default::default()
+            ))
+        );
+        assert_eq!(Rounding::from_name("half-up"), Some(Rounding::HalfUp));
+    }
+
+    #[test]
+    fn formats() {
+        let r = |p: i64, q: i64| Rational::new(p, q);
+        assert_eq!(r(3, 2).to_mixed(), "1 1/2");
+        assert_eq!(r(-7, 3).to_mixed(), "-2 1/3");
+        assert_eq!(r(1, 3).to_mixed(), "1/3");
+        assert_eq!(r(4, 2).to_mixed(), "2");
+        assert_eq!(r(1, 3).to_repeating(), "0.(3)");
+        assert_eq!(r(1, 6).to_repeating(), "0.1(6)");
+        assert_eq!(r(-22, 7).to_repeating(), "-3.(142857)");
+        assert_eq!(r(1, 8).to_repeating(), "0.125");
+        assert!(r(1, 9973).to_repeating().ends_with("..."));
+        assert_eq!(r(1500, 1).to_scientific(10, false), "1.5e3");
+        assert_eq!(r(-1, 800).to_scientific(3, false), "-1.25e-3");
+        assert_eq!(r(12345, 1).to_scientific(3, true), "12.3e3");
+        assert_eq!(r(12345, 1).to_scientific(1, true), "10e3");
+        assert_eq!(r(1, 800).to_scientific(3, true), "1.25e-3");
+        assert_eq!(r(1, 8000).to_scientific(3, true), "125e-6");
+        assert_eq!(r(255, 1).to_radix(16), Some(String::from("0xff")));
+        assert_eq!(r(-5, 1).to_radix(2), Some(String::from("-0b101")));
+        assert_eq!(r(8, 1).to_radix(8), Some(String::from("0o10")));
+        assert_eq!(r(1, 2).to_radix(16), None);
+    }
+
+    #[test]
+    fn powers() {
+        let r = |p: i64, q: i64| Rational::new(p, q);
+        assert_eq!(r(2, 3).pow(r(10, 1)).to_string(), "1024/59049");
+        assert_eq!(r(2, 1).pow(r(-3, 1)).to_string(), "1/8");
+        assert_eq!(r(8, 1).pow(r(1, 3)).to_string(), "2");
+        assert_eq!(r(4, 9).pow(r(-3, 2)).to_string(), "27/8");
+        assert_eq!(r(-8, 27).pow(r(2, 3)).to_string(), "4/9");
+        assert_eq!(r(16, 1).sqrt().to_string(), "4");
+        assert!(r(8, 1).pow(r(1, 3)).is_exact());
+        assert!(r(0, 1).pow(r(0, 1)).is_exact());
+        // 2^200 doesn't fit any float mantissa
+        let big = r(2, 1).pow_int(&200.into());
+        assert_eq!(big.root(100).to_string(), "4");
+        assert_eq!(
+            big.to_string(),
+            "1606938044258990275541962092341162602522202993782792835301376"
+        );
+
+        assert_eq!(r(2, 1).nth_root(2), None);
+        assert!(!r(2, 1).sqrt().is_exact());
+        assert!(!(r(2, 1).sqrt() * r(0, 1) + r(1, 1)).is_exact());
+        assert_eq!(r(-27, 1).root(3).to_string(), "-3");
+    }
+
+    #[test]
+    fn logarithms() {
+        let r = |p: i64, q: i64| Rational::new(p, q);
+        assert_eq!(r(8, 1).log(r(2, 1)).to_string(), "3");
+        assert_eq!(r(1, 8).log(r(2, 1)).to_string(), "-3");
+        assert_eq!(r(4, 1).log(r(8, 1)).to_string(), "2/3");
+        assert_eq!(r(9, 4).log(r(2, 3)).to_string(), "-2");
+        assert!(r(8, 1).log(r(2, 1)).is_exact());
+        assert!(!r(3, 1).log(r(2, 1)).is_exact());
+    }
+
+    #[test]
+    fn significant() {
+        let r = |p: i64, q: i64| Rational::new(p, q);
+        assert_eq!(r(1, 3).to_significant(5), "0.33333");
+        assert_eq!(r(2, 3).to_significant(3), "0.667");
+        assert_eq!(r(-1234567, 1).to_significant(3), "-1230000");
+        assert_eq!(r(5, 2).to_significant(1), "2");
+        assert_eq!(r(999, 1).to_significant(2), "1000");
+        assert_eq!(r(3, 2).to_significant(10), "1.5");
+        assert_eq!(r(1, 100000000).to_significant(3), "1e-8");
+        assert_eq!(
+            Rational::from(num_traits::pow(num_bigint::BigInt::from(7), 40)).to_significant(4),
+            "6.367e33"
+        );
+    }
+
+    #[test]
+    fn com_den() {
+        let mut x = Rational::new(1, 6);
+        let y = x.com_den(Rational::new(3, 4));
+        assert_eq!(x.numer().to_string(), "2");
+        assert_eq!(x.denom().to_string(), "12");
+        assert_eq!(y.numer().to_string(), "9");
+        assert_eq!(y.denom().to_string(), "12");
+    }
+
     #[test]
     fn to_float() {
-        assert_eq!(Rational::new(10.0, -2.0).to_float(), -5.0);
-        assert_eq!(Rational::new(3.0, 2.0).to_float(), 3.0 / 2.0);
+        assert_eq!(Rational::new(10, -2).to_float(), -5.0);
+        assert_eq!(Rational::new(3, 2).to_float(), 3.0 / 2.0);
+        let huge = num_traits::pow(num_bigint::BigInt::from(10), 400);
+        assert_eq!(Rational::new(huge.clone() * 3, huge * 2).to_float(), 1.5);
+    }
+
+    #[test]
+    fn from_str() {
+        let r = |x: &str| x.parse::<Rational>().map(|x| x.to_string());
+        assert_eq!(r("0x1F"), Ok(String::from("31")));
+        assert_eq!(r("0o17"), Ok(String::from("15")));
+        assert_eq!(r("0b1010"), Ok(String::from("10")));
+        assert_eq!(r("0.1"), Ok(String::from("1/10")));
+        assert_eq!(r("1e-5"), Ok(String::from("1/100000")));
+        assert_eq!(r("6.02E23"), Ok(String::from("602000000000000000000000")));
+        assert_eq!(r("1_000_000"), Ok(String::from("1000000")));
+        assert_eq!(r(".5"), Ok(String::from("1/2")));
+        assert_eq!(r("2.5e+1"), Ok(String::from("25")));
+        assert_eq!(r("0b1_01"), Ok(String::from("5")));
+        for x in [
+            "", ".", "1__0", "_1", "1_", "0x", "0b12", "1e", "1e5.5", "1.2.3", "inf", "1e999999",
+        ] {
+            assert!(r(x).is_err(), "{x}");
+        }
     }
 
     #[test]
     fn from_float() {
-        assert_eq!(Rational::from(3.4), Rational::new(17.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 1.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 2.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 3.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 4.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 6.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 7.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 8.0).reduce());
-        assert_eq!(Rational::from(0.0), Rational::new(0.0, 9.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(1.0, 1.0).reduce());
-        assert_eq!(Rational::from(0.5), Rational::new(1.0, 2.0).reduce());
+        assert_eq!(Rational::from(3.4), Rational::new(17, 5).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 1).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 2).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 3).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 4).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 5).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 6).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 7).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 8).reduce());
+        assert_eq!(Rational::from(0.0), Rational::new(0, 9).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(1, 1).reduce());
+        assert_eq!(Rational::from(0.5), Rational::new(1, 2).reduce());
         assert_eq!(
             Rational::from(0.3333333333333333),
-            Rational::new(3333333333333333.0, 10000000000000000.0).reduce()
+            Rational::new(3333333333333333i64, 10000000000000000i64).reduce()
+        );
+        assert_eq!(Rational::from(0.25), Rational::new(1, 4).reduce());
+        assert_eq!(Rational::from(0.2), Rational::new(1, 5).reduce());
+        assert_eq!(Rational::from(0.125), Rational::new(1, 8).reduce());
+        assert_eq!(Rational::from(2.0), Rational::new(2, 1).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(2, 2).reduce());
+        assert_eq!(Rational::from(0.5), Rational::new(2, 4).reduce());
+        assert_eq!(Rational::from(0.4), Rational::new(2, 5).reduce());
+        assert_eq!(Rational::from(0.25), Rational::new(2, 8).reduce());
+        assert_eq!(Rational::from(3.0), Rational::new(3, 1).reduce());
+        assert_eq!(Rational::from(1.5), Rational::new(3, 2).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(3, 3).reduce());
+        assert_eq!(Rational::from(0.75), Rational::new(3, 4).reduce());
+        assert_eq!(Rational::from(0.6), Rational::new(3, 5).reduce());
+        assert_eq!(Rational::from(0.5), Rational::new(3, 6).reduce());
+        assert_eq!(Rational::from(0.375), Rational::new(3, 8).reduce());
+        assert_eq!(Rational::from(4.0), Rational::new(4, 1).reduce());
+        assert_eq!(Rational::from(2.0), Rational::new(4, 2).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(4, 4).reduce());
+        assert_eq!(Rational::from(0.8), Rational::new(4, 5).reduce());
+        assert_eq!(Rational::from(0.5), Rational::new(4, 8).reduce());
+        assert_eq!(Rational::from(5.0), Rational::new(5, 1).reduce());
+        assert_eq!(Rational::from(2.5), Rational::new(5, 2).reduce());
+        assert_eq!(Rational::from(1.25), Rational::new(5, 4).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(5, 5).reduce());
+        assert_eq!(Rational::from(0.625), Rational::new(5, 8).reduce());
+        assert_eq!(Rational::from(6.0), Rational::new(6, 1).reduce());
+        assert_eq!(Rational::from(3.0), Rational::new(6, 2).reduce());
+        assert_eq!(Rational::from(2.0), Rational::new(6, 3).reduce());
+        assert_eq!(Rational::from(1.5), Rational::new(6, 4).reduce());
+        assert_eq!(Rational::from(1.2), Rational::new(6, 5).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(6, 6).reduce());
+        assert_eq!(Rational::from(0.75), Rational::new(6, 8).reduce());
+        assert_eq!(Rational::from(7.0), Rational::new(7, 1).reduce());
+        assert_eq!(Rational::from(3.5), Rational::new(7, 2).reduce());
+        assert_eq!(Rational::from(1.75), Rational::new(7, 4).reduce());
+        assert_eq!(Rational::from(1.4), Rational::new(7, 5).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(7, 7).reduce());
+        assert_eq!(Rational::from(0.875), Rational::new(7, 8).reduce());
+        assert_eq!(Rational::from(8.0), Rational::new(8, 1).reduce());
+        assert_eq!(Rational::from(4.0), Rational::new(8, 2).reduce());
+        assert_eq!(Rational::from(2.0), Rational::new(8, 4).reduce());
+        assert_eq!(Rational::from(1.6), Rational::new(8, 5).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(8, 8).reduce());
+        assert_eq!(Rational::from(9.0), Rational::new(9, 1).reduce());
+        assert_eq!(Rational::from(4.5), Rational::new(9, 2).reduce());
+        assert_eq!(Rational::from(3.0), Rational::new(9, 3).reduce());
+        assert_eq!(Rational::from(2.25), Rational::new(9, 4).reduce());
+        assert_eq!(Rational::from(1.8), Rational::new(9, 5).reduce());
+        assert_eq!(Rational::from(1.5), Rational::new(9, 6).reduce());
+        assert_eq!(Rational::from(1.125), Rational::new(9, 8).reduce());
+        assert_eq!(Rational::from(1.0), Rational::new(9, 9).reduce());
+        // assert_eq!(Rational::new(3, 2).to_float(), 3.0 / 2.0);
+    }
+    #[test]
+    fn serde() {
+        let json = |x: &Rational| serde_json::to_string(x).unwrap();
+        let load = |s: &str| serde_json::from_str::<Rational>(s).map_err(|e| e.to_string());
+        let big = Rational::new(
+            "123456789012345678901234567890"
+                .parse::<num_bigint::BigInt>()
+                .unwrap(),
+            -7,
+        );
+        assert_eq!(
+            json(&big),
+            r#"{"numerator":"123456789012345678901234567890","denominator":"-7"}"#
         );
-        assert_eq!(Rational::from(0.25), Rational::new(1.0, 4.0).reduce());
-        assert_eq!(Rational::from(0.2), Rational::new(1.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.125), Rational::new(1.0, 8.0).reduce());
-        assert_eq!(Rational::from(2.0), Rational::new(2.0, 1.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(2.0, 2.0).reduce());
-        assert_eq!(Rational::from(0.5), Rational::new(2.0, 4.0).reduce());
-        assert_eq!(Rational::from(0.4), Rational::new(2.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.25), Rational::new(2.0, 8.0).reduce());
-        assert_eq!(Rational::from(3.0), Rational::new(3.0, 1.0).reduce());
-        assert_eq!(Rational::from(1.5), Rational::new(3.0, 2.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(3.0, 3.0).reduce());
-        assert_eq!(Rational::from(0.75), Rational::new(3.0, 4.0).reduce());
-        assert_eq!(Rational::from(0.6), Rational::new(3.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.5), Rational::new(3.0, 6.0).reduce());
-        assert_eq!(Rational::from(0.375), Rational::new(3.0, 8.0).reduce());
-        assert_eq!(Rational::from(4.0), Rational::new(4.0, 1.0).reduce());
-        assert_eq!(Rational::from(2.0), Rational::new(4.0, 2.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(4.0, 4.0).reduce());
-        assert_eq!(Rational::from(0.8), Rational::new(4.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.5), Rational::new(4.0, 8.0).reduce());
-        assert_eq!(Rational::from(5.0), Rational::new(5.0, 1.0).reduce());
-        assert_eq!(Rational::from(2.5), Rational::new(5.0, 2.0).reduce());
-        assert_eq!(Rational::from(1.25), Rational::new(5.0, 4.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(5.0, 5.0).reduce());
-        assert_eq!(Rational::from(0.625), Rational::new(5.0, 8.0).reduce());
-        assert_eq!(Rational::from(6.0), Rational::new(6.0, 1.0).reduce());
-        assert_eq!(Rational::from(3.0), Rational::new(6.0, 2.0).reduce());
-        assert_eq!(Rational::from(2.0), Rational::new(6.0, 3.0).reduce());
-        assert_eq!(Rational::from(1.5), Rational::new(6.0, 4.0).reduce());
-        assert_eq!(Rational::from(1.2), Rational::new(6.0, 5.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(6.0, 6.0).reduce());
-        assert_eq!(Rational::from(0.75), Rational::new(6.0, 8.0).reduce());
-        assert_eq!(Rational::from(7.0), Rational::new(7.0, 1.0).reduce());
-        assert_eq!(Rational::from(3.5), Rational::new(7.0, 2.0).reduce());
-        assert_eq!(Rational::from(1.75), Rational::new(7.0, 4.0).reduce());
-        assert_eq!(Rational::from(1.4), Rational::new(7.0, 5.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(7.0, 7.0).reduce());
-        assert_eq!(Rational::from(0.875), Rational::new(7.0, 8.0).reduce());
-        assert_eq!(Rational::from(8.0), Rational::new(8.0, 1.0).reduce());
-        assert_eq!(Rational::from(4.0), Rational::new(8.0, 2.0).reduce());
-        assert_eq!(Rational::from(2.0), Rational::new(8.0, 4.0).reduce());
-        assert_eq!(Rational::from(1.6), Rational::new(8.0, 5.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(8.0, 8.0).reduce());
-        assert_eq!(Rational::from(9.0), Rational::new(9.0, 1.0).reduce());
-        assert_eq!(Rational::from(4.5), Rational::new(9.0, 2.0).reduce());
-        assert_eq!(Rational::from(3.0), Rational::new(9.0, 3.0).reduce());
-        assert_eq!(Rational::from(2.25), Rational::new(9.0, 4.0).reduce());
-        assert_eq!(Rational::from(1.8), Rational::new(9.0, 5.0).reduce());
-        assert_eq!(Rational::from(1.5), Rational::new(9.0, 6.0).reduce());
-        assert_eq!(Rational::from(1.125), Rational::new(9.0, 8.0).reduce());
-        assert_eq!(Rational::from(1.0), Rational::new(9.0, 9.0).reduce());
-        // assert_eq!(Rational::new(3.0, 2.0).to_float(), 3.0 / 2.0);
+        assert_eq!(load(&json(&big)), Ok(big));
+        let approx = Rational::approx(0.5);
+        assert!(!load(&json(&approx)).unwrap().is_exact());
+        for x in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let x = Rational::approx(x);
+            let loaded = load(&json(&x)).unwrap();
+            assert_eq!(loaded.to_float().to_bits(), x.to_float().to_bits());
+            assert!(!loaded.is_exact());
+        }
+        assert!(load(r#"{"numerator":"1","denominator":"0"}"#)
+            .unwrap_err()
+            .contains("zero denominator"));
+        assert!(load(r#"{"numerator":"1.5","denominator":"1"}"#).is_err());
     }
 }
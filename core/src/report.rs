@@ -0,0 +1,189 @@
+use serde::Serialize;
+
+use crate::error::{Error, Span};
+use crate::format::Format;
+use crate::integer::WordSize;
+use crate::number::Number;
+use crate::parser::{KnownLiterals, Statement};
+use crate::quantity::Value;
+use crate::tokenizer::tokenize;
+
+/// One evaluated line for other programs to read, see `to_json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub input: String,
+    /// The parsed line, `None` when it didn't parse.
+    pub ast: Option<Statement>,
+    /// The variable assigned or the function defined by the line.
+    pub assigned: Option<String>,
+    /// `None` for definitions and errors.
+    pub result: Option<ReportValue>,
+    pub error: Option<ReportError>,
+}
+
+/// A result in the unit it is shown in.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportValue {
+    /// As the calculator shows it, `sqrt(2)/2`.
+    pub text: String,
+    pub exact: bool,
+    /// In decimal, only set for exact rational values.
+    pub numerator: Option<String>,
+    pub denominator: Option<String>,
+    /// The real part, `NaN` is written as `null`.
+    pub float: f64,
+    /// `None` for real values.
+    pub imaginary: Option<f64>,
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportError {
+    /// See `Error::kind`.
+    pub kind: &'static str,
+    pub message: String,
+    /// Bytes of the input the error points at.
+    pub span: Span,
+}
+
+impl ReportValue {
+    /// `x` with `text` in `format`, approximations are never shown as
+    /// fractions.
+    pub fn new(x: &Value, format: Format, word: Option<WordSize>) -> Result<Self, Error> {
+        let format = match format {
+            Format::Exact | Format::Mixed | Format::Repeating if !x.is_exact() => Format::Float,
+            format => format,
+        };
+        //Negative words are shown in radix formats like a CPU register holds them
+        let unsigned = word.and_then(|x| WordSize::new(x.bits(), false));
+        let text = match (format, unsigned) {
+            (Format::Radix(_), Some(bits)) => bits.wrap(x.clone()).to_format_string(format)?,
+            _ => x.to_format_string(format)?,
+        };
+        let (value, unit) = x.in_unit();
+        let exact = value
+            .to_rational()
+            .filter(|_| x.is_exact())
+            .map(|x| x.reduce());
+        Ok(Self {
+            text,
+            exact: x.is_exact(),
+            numerator: exact.as_ref().map(|x| x.numer().to_string()),
+            denominator: exact.as_ref().map(|x| x.denom().to_string()),
+            float: value.re().to_float(),
+            imaginary: (!value.is_real()).then(|| value.im().to_float()),
+            unit,
+        })
+    }
+}
+
+impl From<&Error> for ReportError {
+    fn from(e: &Error) -> Self {
+        Self {
+            kind: e.kind(),
+            message: e.to_string(),
+            span: e.span(),
+        }
+    }
+}
+
+impl Report {
+    /// Runs `input` like `Parse::parse_with` and records every step. Flags
+    /// aren't read, `format` and `word` are what they would set.
+    pub fn new(
+        input: &str,
+        known_literals: &mut KnownLiterals,
+        format: Format,
+        word: Option<WordSize>,
+    ) -> Self {
+        let mut report = Report {
+            input: input.to_owned(),
+            ast: None,
+            assigned: None,
+            result: None,
+            error: None,
+        };
+        if let Err(e) = report.run(known_literals, format, word) {
+            report.error = Some(ReportError::from(&e));
+        }
+        report
+    }
+
+    fn run(
+        &mut self,
+        known_literals: &mut KnownLiterals,
+        format: Format,
+        word: Option<WordSize>,
+    ) -> Result<(), Error> {
+        let statement = Statement::new(tokenize(&self.input), known_literals)?;
+        self.ast = Some(statement.clone());
+        let name = statement.name().map(str::to_owned);
+        let out = statement.run::<Value>(known_literals, (), word)?;
+        //Only names that were really assigned, a failed line changes nothing
+        self.assigned = name;
+        if let Some(x) = out {
+            self.result = Some(ReportValue::new(&x, format, word)?);
+        }
+        Ok(())
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// One line of JSON, `{"input": "1/2", "result": {"numerator": "1", ...}}`.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Report;
+    #[cfg(test)]
+    use crate::{format::Format, parser::KnownLiterals};
+    #[cfg(test)]
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_report() {
+        let mut known_literals = KnownLiterals::new();
+        let mut report = |s: &str| {
+            let report = Report::new(s, &mut known_literals, Format::Exact, None);
+            serde_json::from_str::<Value>(&report.to_json().unwrap()).unwrap()
+        };
+        let x = report("x = -3/6");
+        assert_eq!(x["assigned"], "x");
+        assert_eq!(x["result"]["text"], "-1/2");
+        assert_eq!(x["result"]["numerator"], "-1");
+        assert_eq!(x["result"]["denominator"], "2");
+        assert_eq!(x["result"]["float"], -0.5);
+        assert_eq!(x["error"], Value::Null);
+        assert_eq!(
+            x["ast"]["Assignment"][1]["op"]["Div"][1]["op"],
+            json!({"Number": {"numerator": "6", "denominator": "1"}})
+        );
+
+        let x = report("sqrt(2) + 2i");
+        assert_eq!(x["result"]["exact"], true);
+        assert_eq!(x["result"]["numerator"], Value::Null);
+        assert_eq!(x["result"]["imaginary"], 2.0);
+
+        let x = report("5 km");
+        assert_eq!(x["result"]["numerator"], "5000");
+        assert_eq!(x["result"]["unit"], "m");
+
+        let x = report("f(y) = y x");
+        assert_eq!(x["assigned"], "f");
+        assert_eq!(x["result"], Value::Null);
+
+        let x = report("y = 1/0");
+        assert_eq!(x["assigned"], Value::Null);
+        assert_eq!(
+            x["error"],
+            json!({"kind": "division_by_zero", "message": "Division by zero", "span": {"start": 4, "end": 7}})
+        );
+        assert_eq!(report("2 +")["error"]["kind"], "unexpected_end");
+        assert_eq!(report("2 +")["ast"], Value::Null);
+    }
+}
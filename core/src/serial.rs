@@ -0,0 +1,261 @@
+use std::fmt::Display;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Span};
+use crate::op::{Node, Op};
+use crate::parser::Statement;
+use crate::tokenizer::{SpannedToken, Token};
+use crate::unit::Unit;
+
+/// Version of the encoding, raised whenever `Op`, `Token` or the values in
+/// them change shape so old data is rejected instead of misread.
+pub const VERSION: u32 = 1;
+
+// What is written, the data with the version it was written in.
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    data: T,
+}
+
+// Only the version, so a newer encoding is reported as such instead of as
+// whatever part of it doesn't parse.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// Why saved data couldn't be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    Binary(String),
+    /// Written by another version of the encoding.
+    Version(u32),
+    /// Decoded, but not something the parser could have produced.
+    Invalid(Error),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Json(e) => write!(f, "invalid JSON: {e}"),
+            LoadError::Binary(e) => write!(f, "invalid binary data: {e}"),
+            LoadError::Version(v) => {
+                write!(f, "written with encoding version {v}, expected {VERSION}")
+            }
+            LoadError::Invalid(e) => write!(f, "invalid data: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Checks for what the types of loaded data can't rule out, like a builtin
+/// with the wrong number of arguments, which the parser would reject.
+pub trait Validate {
+    fn validate(&self) -> Result<(), Error>;
+}
+
+fn check_span(span: Span) -> Result<(), Error> {
+    if span.start > span.end {
+        return Err(Error::domain(
+            format!("span {}..{} ends before it starts", span.start, span.end),
+            Span::default(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_unit(unit: &Unit, span: Span) -> Result<(), Error> {
+    if unit.factor.is_zero() {
+        return Err(Error::domain(format!("unit {unit} has no size"), span));
+    }
+    Ok(())
+}
+
+fn check_op(op: &Op, span: Span) -> Result<(), Error> {
+    match op {
+        Op::Builtin(f, args) => f.check_arity(args.len(), span)?,
+        Op::Unit(unit) => check_unit(unit, span)?,
+        _ => {}
+    }
+    op.operands().into_iter().try_for_each(Node::validate)
+}
+
+impl Validate for Op {
+    fn validate(&self) -> Result<(), Error> {
+        check_op(self, Span::default())
+    }
+}
+
+impl Validate for Node {
+    fn validate(&self) -> Result<(), Error> {
+        check_span(self.span)?;
+        check_op(&self.op, self.span)
+    }
+}
+
+impl Validate for Statement {
+    fn validate(&self) -> Result<(), Error> {
+        match self {
+            Statement::Expression(x) | Statement::Assignment(_, x) => x.validate(),
+            Statement::Definition(_, function) => function.body.validate(),
+        }
+    }
+}
+
+impl Validate for SpannedToken {
+    fn validate(&self) -> Result<(), Error> {
+        check_span(self.span)?;
+        match &self.token {
+            Token::Unit(unit) => check_unit(unit, self.span),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self) -> Result<(), Error> {
+        self.iter().try_for_each(T::validate)
+    }
+}
+
+/// Parsed expressions, tokens or statements as JSON, read back with
+/// `from_json`.
+pub fn to_json<T: Serialize>(data: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&Envelope {
+        version: VERSION,
+        data,
+    })
+}
+
+pub fn from_json<T: DeserializeOwned + Validate>(s: &str) -> Result<T, LoadError> {
+    let header: Header = serde_json::from_str(s).map_err(LoadError::Json)?;
+    if header.version != VERSION {
+        return Err(LoadError::Version(header.version));
+    }
+    let envelope: Envelope<T> = serde_json::from_str(s).map_err(LoadError::Json)?;
+    envelope.data.validate().map_err(LoadError::Invalid)?;
+    Ok(envelope.data)
+}
+
+/// Like `to_json`, but in the smaller binary CBOR format. Read back
+/// with `from_bytes`.
+pub fn to_bytes<T: Serialize>(data: &T) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+    let mut out = Vec::new();
+    let envelope = Envelope {
+        version: VERSION,
+        data,
+    };
+    ciborium::into_writer(&envelope, &mut out)?;
+    Ok(out)
+}
+
+pub fn from_bytes<T: DeserializeOwned + Validate>(bytes: &[u8]) -> Result<T, LoadError> {
+    let binary = |e: ciborium::de::Error<std::io::Error>| LoadError::Binary(e.to_string());
+    let header: Header = ciborium::from_reader(bytes).map_err(binary)?;
+    if header.version != VERSION {
+        return Err(LoadError::Version(header.version));
+    }
+    let envelope: Envelope<T> = ciborium::from_reader(bytes).map_err(binary)?;
+    envelope.data.validate().map_err(LoadError::Invalid)?;
+    Ok(envelope.data)
+}
+
+mod test {
+    #[cfg(test)]
+    use super::{from_bytes, from_json, to_bytes, to_json, LoadError};
+    #[cfg(test)]
+    use crate::{
+        builtin::Builtin,
+        error::{Error, Span},
+        op::{Node, Op},
+        parser::{KnownLiterals, Statement},
+        quantity::Value,
+        tokenizer::{tokenize, SpannedToken},
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let known_literals = KnownLiterals::new();
+        for s in [
+            "-2^3! + 5 km in m",
+            "f(x, y) = if(x < y, nCr(x, 2), 1/3)",
+            "x = ~0xff",
+        ] {
+            let tokens = tokenize(s);
+            assert_eq!(
+                from_json::<Vec<SpannedToken>>(&to_json(&tokens).unwrap()).unwrap(),
+                tokens
+            );
+            assert_eq!(
+                from_bytes::<Vec<SpannedToken>>(&to_bytes(&tokens).unwrap()).unwrap(),
+                tokens
+            );
+
+            let statement = Statement::new(tokens, &known_literals).unwrap();
+            assert_eq!(
+                from_json::<Statement>(&to_json(&statement).unwrap()).unwrap(),
+                statement
+            );
+            let bytes = to_bytes(&statement).unwrap();
+            assert!(bytes.len() < to_json(&statement).unwrap().len());
+            assert_eq!(from_bytes::<Statement>(&bytes).unwrap(), statement);
+        }
+        //Loaded trees evaluate like the ones they were saved from
+        let node = match Statement::new(tokenize("sqrt(8) / 2"), &known_literals).unwrap() {
+            Statement::Expression(node) => node,
+            x => panic!("{x:?}"),
+        };
+        let loaded: Node = from_bytes(&to_bytes(&node).unwrap()).unwrap();
+        let value: Value = loaded.apply(&known_literals).unwrap();
+        assert_eq!(value.to_string(), "sqrt(2)");
+        assert_eq!(loaded.span, node.span);
+    }
+
+    #[test]
+    fn test_errors() {
+        let json = r#"{"version": 99, "data": {"Literal": "x"}}"#;
+        assert!(matches!(
+            from_json::<Node>(json),
+            Err(LoadError::Version(99))
+        ));
+        assert!(matches!(
+            from_json::<Node>(r#"{"version": 1, "data": 5}"#),
+            Err(LoadError::Json(_))
+        ));
+        assert!(matches!(
+            from_bytes::<Node>(&[0xff, 0x00]),
+            Err(LoadError::Binary(_))
+        ));
+        //Well formed, but not what the parser produces
+        let json = r#"{"version": 1, "data": {"op": {"Builtin": ["If", []]}, "span": {"start": 0, "end": 4}}}"#;
+        assert!(matches!(
+            from_json::<Node>(json),
+            Err(LoadError::Invalid(Error::ArityMismatch { found: 0, .. }))
+        ));
+        let node = Node::new(Op::Builtin(Builtin::Sin, Vec::new()), Span::new(0, 5));
+        assert!(matches!(
+            from_bytes::<Node>(&to_bytes(&node).unwrap()),
+            Err(LoadError::Invalid(Error::ArityMismatch { .. }))
+        ));
+        let json = r#"{"version": 1, "data": {"op": {"Number": {"numerator": "1", "denominator": "0"}}, "span": {"start": 0, "end": 1}}}"#;
+        assert!(from_json::<Node>(json)
+            .unwrap_err()
+            .to_string()
+            .contains("zero denominator"));
+        let json =
+            r#"{"version": 1, "data": {"op": {"Literal": "x"}, "span": {"start": 2, "end": 1}}}"#;
+        assert!(matches!(
+            from_json::<Node>(json),
+            Err(LoadError::Invalid(Error::Domain { .. }))
+        ));
+        assert_eq!(
+            LoadError::Version(99).to_string(),
+            "written with encoding version 99, expected 1"
+        );
+    }
+}
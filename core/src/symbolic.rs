@@ -0,0 +1,889 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Span};
+use crate::number::Number;
+use crate::rational::{Rational, MAX_LOG_DENOM};
+
+// Primes up to this are split off when a radicand is factored.
+const TRIAL_DIVISION_LIMIT: u32 = 10_000;
+
+// Sums with more terms than this are approximated.
+const MAX_TERMS: usize = 64;
+
+// Largest integer power a sum is expanded to, `(1 + sqrt(2))^n`.
+const MAX_SUM_POWER: i64 = 32;
+
+// Largest root degree checked when pulling perfect powers out of a radical.
+const MAX_PERFECT_POWER: u32 = 64;
+
+/// Irrational factor of a term.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Atom {
+    /// An integer > 1 raised to a power strictly between 0 and 1.
+    Int(BigInt),
+    Pi,
+    E,
+}
+
+impl Atom {
+    fn to_float(&self) -> f64 {
+        match self {
+            Atom::Int(n) => Rational::from(n.clone()).to_float(),
+            Atom::Pi => std::f64::consts::PI,
+            Atom::E => std::f64::consts::E,
+        }
+    }
+}
+
+// coef * atom_0^exp_0 * atom_1^exp_1 * ...
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Term {
+    coef: Rational,
+    factors: Vec<(Atom, Rational)>,
+}
+
+/// An exact value like `2*sqrt(2)`, `pi/3` or `1 + e^2`.
+///
+/// Stored as a canonical sum of terms, so equal values compare equal.
+/// Anything that can't be kept exact (`sin(1)`, `1/(1 + sqrt(2))`)
+/// collapses into a single inexact `Rational`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Symbolic {
+    terms: Vec<Term>,
+}
+
+fn factor(n: &BigInt) -> Vec<(BigInt, u32)> {
+    let mut n = n.clone();
+    let mut ret = Vec::new();
+    let mut d = 2u32;
+    while d <= TRIAL_DIVISION_LIMIT && BigInt::from(d) * d <= n {
+        let mut k = 0;
+        while (&n % d).is_zero() {
+            n /= d;
+            k += 1;
+        }
+        if k > 0 {
+            ret.push((BigInt::from(d), k));
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+    if n > BigInt::one() {
+        ret.push((n, 1));
+    }
+    ret
+}
+
+// `m` and `k` with `n == m^k`, for some `k > 1` dividing `d`.
+fn perfect_power(n: &BigInt, d: &BigInt) -> Option<(BigInt, u32)> {
+    let d = d.to_u32()?;
+    (2..=d.min(MAX_PERFECT_POWER))
+        .filter(|k| d % k == 0)
+        .find_map(|k| {
+            let m = n.nth_root(k);
+            (num_traits::pow(m.clone(), k as usize) == *n).then_some((m, k))
+        })
+}
+
+fn fmt_pow(name: &str, e: &Rational) -> String {
+    if *e == Rational::one() {
+        name.to_owned()
+    } else if e.is_integer() {
+        format!("{name}^{e}")
+    } else {
+        format!("{name}^({e})")
+    }
+}
+
+impl Term {
+    fn new(coef: Rational) -> Self {
+        Self {
+            coef,
+            factors: Vec::new(),
+        }
+    }
+
+    fn to_float(&self) -> f64 {
+        self.factors
+            .iter()
+            .fold(self.coef.to_float(), |acc, (a, e)| {
+                acc * a.to_float().powf(e.to_float())
+            })
+    }
+
+    fn cmp_factors(&self, other: &Term) -> Ordering {
+        for ((a, x), (b, y)) in self.factors.iter().zip(&other.factors) {
+            let ord = a.cmp(b).then(x.partial_cmp(y).unwrap_or(Ordering::Equal));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        self.factors.len().cmp(&other.factors.len())
+    }
+
+    // Merges repeated atoms, keeps integer bases coprime and moves whole
+    // powers of them into the coefficient.
+    fn normalized(mut coef: Rational, mut factors: Vec<(Atom, Rational)>) -> Term {
+        loop {
+            factors.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut merged: Vec<(Atom, Rational)> = Vec::with_capacity(factors.len());
+            for (atom, e) in factors {
+                match merged.last_mut() {
+                    Some((last, sum)) if *last == atom => *sum = sum.clone() + e,
+                    _ => merged.push((atom, e)),
+                }
+            }
+            factors = merged;
+
+            let ints: Vec<(usize, &BigInt)> = factors
+                .iter()
+                .enumerate()
+                .filter_map(|(i, x)| match &x.0 {
+                    Atom::Int(n) => Some((i, n)),
+                    _ => None,
+                })
+                .collect();
+            let shared = ints.iter().enumerate().find_map(|(k, (i, a))| {
+                ints[k + 1..].iter().find_map(|(j, b)| {
+                    let g = a.gcd(b);
+                    (!g.is_one()).then_some((*i, *j, g))
+                })
+            });
+            //a^x * b^y = g^(x+y) * (a/g)^x * (b/g)^y
+            if let Some((i, j, g)) = shared {
+                let (b, y) = factors.remove(j);
+                let (a, x) = factors.remove(i);
+                let (Atom::Int(a), Atom::Int(b)) = (a, b) else {
+                    unreachable!()
+                };
+                factors.push((Atom::Int(&a / &g), x.clone()));
+                factors.push((Atom::Int(&b / &g), y.clone()));
+                factors.push((Atom::Int(g), x + y));
+                continue;
+            }
+
+            let mut changed = false;
+            let mut next = Vec::with_capacity(factors.len());
+            for (atom, e) in factors {
+                if e.is_zero() {
+                    continue;
+                }
+                let Atom::Int(n) = atom else {
+                    next.push((atom, e));
+                    continue;
+                };
+                if n.is_one() {
+                    continue;
+                }
+                if let Some((m, k)) = perfect_power(&n, e.denom()) {
+                    next.push((Atom::Int(m), e * Rational::from(k as i64)));
+                    changed = true;
+                    continue;
+                }
+                let whole = e.floor();
+                if !whole.is_zero() {
+                    coef = coef * Rational::from(n.clone()).pow_int(whole.numer());
+                }
+                let e = e - whole;
+                if !e.is_zero() {
+                    next.push((Atom::Int(n), e));
+                }
+            }
+            factors = next;
+            if !changed {
+                break;
+            }
+        }
+        Term { coef, factors }
+    }
+
+    fn mul(&self, other: &Term) -> Term {
+        Term::normalized(
+            self.coef.clone() * other.coef.clone(),
+            self.factors.iter().chain(&other.factors).cloned().collect(),
+        )
+    }
+
+    // Odd roots of a negative coefficient keep the sign, even ones are left
+    // for the caller to reject.
+    fn pow(&self, r: &Rational) -> Term {
+        let mut factors: Vec<(Atom, Rational)> = self
+            .factors
+            .iter()
+            .map(|(a, e)| (a.clone(), e.clone() * r.clone()))
+            .collect();
+        let negative = self.coef < Rational::zero();
+        let mut coef = if r.is_integer() {
+            self.coef.abs().pow_int(r.numer())
+        } else {
+            let (p, q) = self.coef.abs().into();
+            for (n, k) in factor(&p) {
+                factors.push((Atom::Int(n), Rational::from(k as i64) * r.clone()));
+            }
+            for (n, k) in factor(&q) {
+                factors.push((Atom::Int(n), -(Rational::from(k as i64) * r.clone())));
+            }
+            Rational::one()
+        };
+        if negative && r.numer().is_odd() {
+            coef = -coef;
+        }
+        Term::normalized(coef, factors)
+    }
+
+    // Absolute value of the term, the sign is written by the caller.
+    fn fmt_abs(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (p, q) = self.coef.abs().into();
+        if q.is_zero() {
+            return f.write_str("undefined");
+        }
+        let mut num: Vec<String> = Vec::new();
+        let mut den: Vec<String> = Vec::new();
+        if !q.is_one() {
+            den.push(q.to_string());
+        }
+        //Radicals with the same degree are written under one root
+        let mut roots: Vec<(BigInt, BigInt)> = Vec::new();
+        for (atom, e) in &self.factors {
+            let name = match atom {
+                Atom::Int(n) => {
+                    let (k, d) = (e.numer().to_usize().unwrap_or(1), e.denom().clone());
+                    let n = num_traits::pow(n.clone(), k);
+                    match roots.iter_mut().find(|x| x.0 == d) {
+                        Some((_, r)) => *r *= n,
+                        None => roots.push((d, n)),
+                    }
+                    continue;
+                }
+                Atom::Pi => "pi",
+                Atom::E => "e",
+            };
+            if *e < Rational::zero() {
+                den.push(fmt_pow(name, &-e.clone()));
+            } else {
+                num.push(fmt_pow(name, e));
+            }
+        }
+        roots.sort();
+        for (d, r) in roots {
+            if d == BigInt::from(2) {
+                num.push(format!("sqrt({r})"));
+            } else {
+                num.push(format!("root({d}, {r})"));
+            }
+        }
+        if !p.is_one() || num.is_empty() {
+            num.insert(0, p.to_string());
+        }
+        f.write_str(&num.join("*"))?;
+        match den.len() {
+            0 => Ok(()),
+            1 => write!(f, "/{}", den[0]),
+            _ => write!(f, "/({})", den.join("*")),
+        }
+    }
+}
+
+impl Symbolic {
+    pub fn zero() -> Self {
+        Self { terms: Vec::new() }
+    }
+
+    pub fn one() -> Self {
+        Rational::one().into()
+    }
+
+    pub fn pi() -> Self {
+        Self::atom(Atom::Pi)
+    }
+
+    pub fn e() -> Self {
+        Self::atom(Atom::E)
+    }
+
+    fn atom(atom: Atom) -> Self {
+        Self {
+            terms: vec![Term {
+                coef: Rational::one(),
+                factors: vec![(atom, Rational::one())],
+            }],
+        }
+    }
+
+    /// A float result, marked as inexact.
+    pub fn approx(x: f64) -> Self {
+        Self {
+            terms: vec![Term::new(Rational::approx(x))],
+        }
+    }
+
+    fn from_terms(mut terms: Vec<Term>) -> Self {
+        if terms.iter().any(|x| !x.coef.is_exact()) {
+            return Self::approx(terms.iter().map(Term::to_float).fold(0.0, |a, b| a + b));
+        }
+        terms.sort_by(|a, b| a.cmp_factors(b));
+        let mut ret: Vec<Term> = Vec::with_capacity(terms.len());
+        for t in terms {
+            match ret.last_mut() {
+                Some(last) if last.factors == t.factors => {
+                    last.coef = last.coef.clone() + t.coef;
+                }
+                _ => ret.push(t),
+            }
+        }
+        ret.retain(|x| !x.coef.is_zero());
+        if ret.len() > MAX_TERMS {
+            return Self::approx(ret.iter().map(Term::to_float).fold(0.0, |a, b| a + b));
+        }
+        Self { terms: ret }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.iter().all(|x| x.coef.is_zero())
+    }
+
+    /// False once any step producing this value had to be approximated.
+    pub fn is_exact(&self) -> bool {
+        self.terms.iter().all(|x| x.coef.is_exact())
+    }
+
+    /// The value as a fraction, `None` if it is irrational.
+    pub fn to_rational(&self) -> Option<Rational> {
+        match self.terms.as_slice() {
+            [] => Some(Rational::zero()),
+            [x] if x.factors.is_empty() => Some(x.coef.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn to_float(&self) -> f64 {
+        self.terms
+            .iter()
+            .map(Term::to_float)
+            .fold(0.0, |a, b| a + b)
+    }
+
+    /// `k` such that the value is `k*pi`.
+    pub fn pi_multiple(&self) -> Option<Rational> {
+        match self.terms.as_slice() {
+            [] => Some(Rational::zero()),
+            [x] if x.factors == [(Atom::Pi, Rational::one())] => Some(x.coef.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn pow(&self, x: &Symbolic) -> Symbolic {
+        let approx = || Self::approx(self.to_float().powf(x.to_float()));
+        let Some(r) = x.to_rational().filter(|_| self.is_exact() && x.is_exact()) else {
+            return approx();
+        };
+        match self.terms.as_slice() {
+            [] => Rational::zero().pow(r).into(),
+            [t] if t.coef < Rational::zero() && r.denom().is_even() => Self::approx(f64::NAN),
+            [t] => Self::from_terms(vec![t.pow(&r)]),
+            _ if r.is_integer() && r > Rational::zero() && r <= MAX_SUM_POWER.into() => {
+                //Repeated squaring
+                let mut n = r.numer().clone();
+                let (mut base, mut acc) = (self.clone(), Symbolic::one());
+                while !n.is_zero() {
+                    if n.is_odd() {
+                        acc = acc * base.clone();
+                    }
+                    n >>= 1;
+                    if !n.is_zero() {
+                        base = base.clone() * base;
+                    }
+                }
+                acc
+            }
+            _ => approx(),
+        }
+    }
+
+    pub fn sqrt(&self) -> Symbolic {
+        self.pow(&Rational::new(1, 2).into())
+    }
+
+    /// Logarithm of `self` in base `x`.
+    pub fn log(&self, x: &Symbolic) -> Symbolic {
+        if let (Some(a), Some(b)) = (self.to_rational(), x.to_rational()) {
+            return a.log(b).into();
+        }
+        if *self == Symbolic::one() && x.is_exact() {
+            return Symbolic::zero();
+        }
+        let f = self.to_float().ln() / x.to_float().ln();
+        //log_b(b^k) = k for a small fraction k, `ln(e^2)` or `log 2 sqrt(2)`
+        if self.is_exact() && x.is_exact() && self.terms.len() == 1 && x.terms.len() == 1 {
+            for q in 1..=MAX_LOG_DENOM {
+                let k = Rational::new((f * q as f64).round() as i64, q).reduce();
+                if x.pow(&k.clone().into()) == *self {
+                    return k.into();
+                }
+            }
+        }
+        Self::approx(f)
+    }
+
+    pub fn abs(&self) -> Symbolic {
+        if *self < Symbolic::zero() {
+            -self.clone()
+        } else {
+            self.clone()
+        }
+    }
+
+    pub fn signum(&self) -> Symbolic {
+        match self.to_rational() {
+            Some(x) => x.signum().into(),
+            None => Rational::from(self.to_float().signum()).into(),
+        }
+    }
+
+    pub fn floor(&self) -> Symbolic {
+        self.integer_part(Rational::floor, f64::floor)
+    }
+
+    pub fn ceil(&self) -> Symbolic {
+        self.integer_part(Rational::ceil, f64::ceil)
+    }
+
+    pub fn round(&self) -> Symbolic {
+        self.integer_part(Rational::round, f64::round)
+    }
+
+    pub fn trunc(&self) -> Symbolic {
+        self.integer_part(Rational::trunc, f64::trunc)
+    }
+
+    fn integer_part(&self, exact: fn(&Rational) -> Rational, float: fn(f64) -> f64) -> Symbolic {
+        if let Some(x) = self.to_rational() {
+            return exact(&x).into();
+        }
+        // An irrational value is never a whole or half number, so the float
+        // can only land on the wrong side when it is very close to one.
+        let x = self.to_float();
+        let out = Rational::from(float(x));
+        if self.is_exact() && (2.0 * x - (2.0 * x).round()).abs() > 1e-9 * x.abs().max(1.0) {
+            out.into()
+        } else {
+            Self::approx(out.to_float())
+        }
+    }
+}
+
+impl Symbolic {
+    /// The value in another number type, irrational factors are computed
+    /// there from `pi`, `e` and powers of integers.
+    pub fn eval<N: Number>(&self, ctx: N::Context) -> N {
+        let mut out = N::zero(ctx);
+        for t in &self.terms {
+            let mut term = N::from_rational(&t.coef, ctx);
+            for (atom, e) in &t.factors {
+                let base = match atom {
+                    Atom::Int(n) => N::from_rational(&n.clone().into(), ctx),
+                    Atom::Pi => N::pi(ctx),
+                    Atom::E => N::e(ctx),
+                };
+                let y = base
+                    .pow(&N::from_rational(e, ctx))
+                    .unwrap_or_else(|_| N::from_float(f64::NAN, ctx));
+                term = term * y;
+            }
+            out = out + term;
+        }
+        out
+    }
+}
+
+impl Number for Symbolic {
+    type Context = ();
+
+    fn from_rational(x: &Rational, _: ()) -> Self {
+        x.clone().into()
+    }
+
+    fn from_float(x: f64, _: ()) -> Self {
+        Symbolic::approx(x)
+    }
+
+    fn from_symbolic(x: &Symbolic, _: ()) -> Self {
+        x.clone()
+    }
+
+    fn context(&self) {}
+
+    fn to_rational(&self) -> Option<Rational> {
+        Symbolic::to_rational(self)
+    }
+
+    fn to_float(&self) -> f64 {
+        Symbolic::to_float(self)
+    }
+
+    fn to_symbolic(&self) -> Symbolic {
+        self.clone()
+    }
+
+    fn is_exact(&self) -> bool {
+        Symbolic::is_exact(self)
+    }
+
+    fn is_zero(&self) -> bool {
+        Symbolic::is_zero(self)
+    }
+
+    fn pi(_: ()) -> Self {
+        Symbolic::pi()
+    }
+
+    fn e(_: ()) -> Self {
+        Symbolic::e()
+    }
+
+    fn pow(&self, x: &Self) -> Result<Self, Error> {
+        if *self < Symbolic::zero() {
+            match x.to_rational() {
+                Some(y) if !y.denom().is_even() => {}
+                Some(_) => return Err(domain("even root of a negative number")),
+                None => return Err(domain("irrational power of a negative number")),
+            }
+        }
+        Ok(Symbolic::pow(self, x))
+    }
+
+    fn log(&self, x: &Self) -> Self {
+        Symbolic::log(self, x)
+    }
+
+    fn floor(&self) -> Self {
+        Symbolic::floor(self)
+    }
+
+    fn ceil(&self) -> Self {
+        Symbolic::ceil(self)
+    }
+
+    fn round(&self) -> Self {
+        Symbolic::round(self)
+    }
+
+    fn trunc(&self) -> Self {
+        Symbolic::trunc(self)
+    }
+
+    fn abs(&self) -> Self {
+        Symbolic::abs(self)
+    }
+
+    fn signum(&self) -> Self {
+        Symbolic::signum(self)
+    }
+
+    fn sin(&self) -> Self {
+        sin_pi(self).unwrap_or_else(|| self.float_op(f64::sin))
+    }
+
+    fn cos(&self) -> Self {
+        cos_pi(self).unwrap_or_else(|| self.float_op(f64::cos))
+    }
+
+    fn tan(&self) -> Self {
+        match (sin_pi(self), cos_pi(self)) {
+            (Some(sin), Some(cos)) if !cos.is_zero() => sin / cos,
+            _ => self.float_op(f64::tan),
+        }
+    }
+
+    fn asin(&self) -> Self {
+        asin(self).unwrap_or_else(|| self.float_op(f64::asin))
+    }
+
+    //acos(x) = pi/2 - asin(x)
+    fn acos(&self) -> Self {
+        asin(self)
+            .map(|y| Symbolic::pi() / 2.into() - y)
+            .unwrap_or_else(|| self.float_op(f64::acos))
+    }
+
+    fn atan(&self) -> Self {
+        atan(self).unwrap_or_else(|| self.float_op(f64::atan))
+    }
+
+    fn atan2(&self, x: &Self) -> Self {
+        let (y, zero) = (self, Symbolic::zero());
+        let exact = match x.partial_cmp(&zero) {
+            _ if x.is_zero() => Some(Symbolic::pi() / 2.into() * y.signum()),
+            Some(Ordering::Greater) => atan(&(y.clone() / x.clone())),
+            _ => atan(&(y.clone() / x.clone())).map(|a| {
+                // Shift into the left half plane, y == 0 gives pi
+                let turn = if *y < zero {
+                    -Symbolic::pi()
+                } else {
+                    Symbolic::pi()
+                };
+                a + turn
+            }),
+        };
+        exact.unwrap_or_else(|| Symbolic::approx(y.to_float().atan2(x.to_float())))
+    }
+
+    fn exp(&self) -> Self {
+        Symbolic::e().pow(self)
+    }
+
+    fn ln(&self) -> Self {
+        Symbolic::log(self, &Symbolic::e())
+    }
+}
+
+fn domain(message: &str) -> Error {
+    Error::domain(message, Span::default())
+}
+
+// sin(k*pi) for the k in [0, 1/2] where it has a short exact form.
+fn sin_table() -> [(Rational, Symbolic); 5] {
+    let half = Symbolic::from(Rational::new(1, 2));
+    [
+        (Rational::zero(), Symbolic::zero()),
+        (Rational::new(1, 6), half.clone()),
+        (Rational::new(1, 4), Symbolic::from(2).sqrt() * half.clone()),
+        (Rational::new(1, 3), Symbolic::from(3).sqrt() * half),
+        (Rational::new(1, 2), Symbolic::one()),
+    ]
+}
+
+fn sin_pi(x: &Symbolic) -> Option<Symbolic> {
+    let k = x.pi_multiple()?;
+    let (one, two) = (Rational::one(), Rational::new(2, 1));
+    //Fold into [0, 1/2] using the period and the symmetries of sin
+    let mut k = k.clone() - (k / two.clone()).floor() * two;
+    let negative = k >= one;
+    if negative {
+        k = k - one.clone();
+    }
+    if k > Rational::new(1, 2) {
+        k = one - k;
+    }
+    let (_, y) = sin_table().into_iter().find(|x| x.0 == k)?;
+    Some(if negative { -y } else { y })
+}
+
+fn cos_pi(x: &Symbolic) -> Option<Symbolic> {
+    sin_pi(&(x.clone() + Symbolic::pi() / 2.into()))
+}
+
+fn asin(x: &Symbolic) -> Option<Symbolic> {
+    let negative = *x < Symbolic::zero();
+    let y = x.abs();
+    let (k, _) = sin_table().into_iter().find(|x| x.1 == y)?;
+    let out = Symbolic::pi() * k.into();
+    Some(if negative { -out } else { out })
+}
+
+fn atan(x: &Symbolic) -> Option<Symbolic> {
+    let negative = *x < Symbolic::zero();
+    let y = x.abs();
+    let three = Symbolic::from(3).sqrt();
+    let table = [
+        (Rational::zero(), Symbolic::zero()),
+        (Rational::new(1, 6), three.clone() / 3.into()),
+        (Rational::new(1, 4), Symbolic::one()),
+        (Rational::new(1, 3), three),
+    ];
+    let (k, _) = table.into_iter().find(|x| x.1 == y)?;
+    let out = Symbolic::pi() * k.into();
+    Some(if negative { -out } else { out })
+}
+
+impl From<Rational> for Symbolic {
+    fn from(x: Rational) -> Self {
+        Self::from_terms(vec![Term::new(x.reduce())])
+    }
+}
+
+impl From<f64> for Symbolic {
+    fn from(x: f64) -> Self {
+        Rational::from(x).into()
+    }
+}
+
+impl From<i64> for Symbolic {
+    fn from(x: i64) -> Self {
+        Rational::from(x).into()
+    }
+}
+
+impl PartialOrd for Symbolic {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if let (Some(a), Some(b)) = (self.to_rational(), other.to_rational()) {
+            return a.partial_cmp(&b);
+        }
+        if self == other {
+            return Some(Ordering::Equal);
+        }
+        self.to_float().partial_cmp(&other.to_float())
+    }
+}
+
+impl std::fmt::Debug for Symbolic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Display for Symbolic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.terms.is_empty() {
+            return f.write_str("0");
+        }
+        for (i, t) in self.terms.iter().enumerate() {
+            let negative = t.coef < Rational::zero();
+            f.write_str(match (i, negative) {
+                (0, false) => "",
+                (0, true) => "-",
+                (_, false) => " + ",
+                (_, true) => " - ",
+            })?;
+            t.fmt_abs(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::ops::Add for Symbolic {
+    type Output = Symbolic;
+    fn add(mut self, other: Symbolic) -> Symbolic {
+        self.terms.extend(other.terms);
+        Self::from_terms(self.terms)
+    }
+}
+
+impl std::ops::Sub for Symbolic {
+    type Output = Symbolic;
+    fn sub(self, other: Symbolic) -> Symbolic {
+        self + -other
+    }
+}
+
+impl std::ops::Mul for Symbolic {
+    type Output = Symbolic;
+    fn mul(self, other: Symbolic) -> Symbolic {
+        if self.terms.len() * other.terms.len() > MAX_TERMS * MAX_TERMS {
+            return Self::approx(self.to_float() * other.to_float());
+        }
+        let mut terms = Vec::with_capacity(self.terms.len() * other.terms.len());
+        for x in &self.terms {
+            for y in &other.terms {
+                terms.push(x.mul(y));
+            }
+        }
+        Self::from_terms(terms)
+    }
+}
+
+impl std::ops::Div for Symbolic {
+    type Output = Symbolic;
+    fn div(self, other: Symbolic) -> Symbolic {
+        match other.terms.as_slice() {
+            [x] => self * Self::from_terms(vec![x.pow(&Rational::new(-1, 1))]),
+            //Sums in the denominator aren't rationalized
+            _ => Self::approx(self.to_float() / other.to_float()),
+        }
+    }
+}
+
+impl std::ops::Neg for Symbolic {
+    type Output = Symbolic;
+    fn neg(mut self) -> Symbolic {
+        for t in self.terms.iter_mut() {
+            t.coef = -t.coef.clone();
+        }
+        self
+    }
+}
+
+impl std::ops::Rem for Symbolic {
+    type Output = Symbolic;
+    fn rem(self, other: Symbolic) -> Symbolic {
+        match (self.to_rational(), other.to_rational()) {
+            (Some(x), Some(y)) => (x % y).into(),
+            _ => Self::approx(self.to_float() % other.to_float()),
+        }
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::Symbolic;
+    #[cfg(test)]
+    use crate::rational::Rational;
+
+    #[cfg(test)]
+    fn int(x: i64) -> Symbolic {
+        Symbolic::from(x)
+    }
+
+    #[cfg(test)]
+    fn frac(p: i64, q: i64) -> Symbolic {
+        Symbolic::from(Rational::new(p, q))
+    }
+
+    #[test]
+    fn test_surds() {
+        assert_eq!(int(8).sqrt().to_string(), "2*sqrt(2)");
+        assert_eq!(int(2).sqrt().pow(&int(2)), int(2));
+        assert_eq!(int(2).sqrt() * int(6).sqrt(), int(2) * int(3).sqrt());
+        assert_eq!(int(2).sqrt() * int(3).sqrt(), int(6).sqrt());
+        assert_eq!(frac(1, 2).sqrt().to_string(), "sqrt(2)/2");
+        assert_eq!(int(16).pow(&frac(1, 4)), int(2));
+        assert_eq!(int(-54).pow(&frac(1, 3)).to_string(), "-3*root(3, 2)");
+        assert_eq!(int(4).pow(&frac(1, 3)).to_string(), "root(3, 4)");
+        assert_eq!((int(1) / int(3).sqrt()).to_string(), "sqrt(3)/3");
+        assert!(int(8).sqrt().is_exact());
+    }
+
+    #[test]
+    fn test_sums() {
+        let x = int(1) + int(2).sqrt();
+        assert_eq!(x.to_string(), "1 + sqrt(2)");
+        assert_eq!((x.clone() * x.clone()).to_string(), "3 + 2*sqrt(2)");
+        assert_eq!(x.pow(&int(2)), x.clone() * x.clone());
+        assert_eq!(x.clone() - int(2).sqrt(), int(1));
+        assert_eq!(
+            (int(2).sqrt() - int(3).sqrt()).to_string(),
+            "sqrt(2) - sqrt(3)"
+        );
+        assert!(!(int(1) / x).is_exact());
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!((Symbolic::pi() / int(3)).to_string(), "pi/3");
+        assert_eq!(Symbolic::e().pow(&int(2)).to_string(), "e^2");
+        assert_eq!(
+            (int(2) / (Symbolic::pi() * Symbolic::e())).to_string(),
+            "2/(pi*e)"
+        );
+        assert_eq!(Symbolic::e().pow(&int(3)).log(&Symbolic::e()), int(3));
+        assert_eq!((Symbolic::pi() * int(2).sqrt()).to_string(), "pi*sqrt(2)");
+        assert_eq!(
+            (Symbolic::pi() / int(3)).pi_multiple(),
+            Some(Rational::new(1, 3))
+        );
+        assert_eq!(Symbolic::pi().floor(), int(3));
+        assert!((Symbolic::pi().to_float() - std::f64::consts::PI).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_order() {
+        assert!(int(2).sqrt() > frac(7, 5));
+        assert!(-Symbolic::pi() < int(-3));
+        assert_eq!((-int(2).sqrt()).abs(), int(2).sqrt());
+    }
+}
@@ -1,42 +1,59 @@
+use crate::error::Span;
 use crate::op::OpType;
-use std::fmt::{Debug, Write};
+use std::fmt::{Debug, Display, Write};
 
-use crate::rational::Rational;
+use serde::{Deserialize, Serialize};
 
+use crate::format::{Format, DEFAULT_DIGITS};
+use crate::integer::WordSize;
+use crate::rational::{Rational, Rounding, MAX_PLACES};
+use crate::unit::Unit;
+
+/// Flags written before a `#` at the start of a line, `x#255` shows `0xff`.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct TokenizerOptions {
     pub debug: bool,
-    pub as_float: bool,
+    /// Shows how the line was read, `p#2 x` prints `2 * x` before the result.
+    pub echo: bool,
+    /// Output format the flags pick, `None` keeps the session's one.
+    ///
+    /// Digits alone are decimal places, rounded half to even unless `u`
+    /// (half up), `z` (toward zero), `_` (floor) or `^` (ceil) is given.
+    /// The letters `q` (fraction), `f` (float), `m` (mixed), `s`
+    /// (scientific), `e` (engineering), `g` (significant digits), `r`
+    /// (repeating), `x`, `o` and `b` (hex, octal, binary) pick the other
+    /// formats, digits after `s`, `e` and `g` are significant digits.
+    pub format: Option<Format>,
+    /// Word size integers wrap around to, `i16` signed and `w16` unsigned.
+    pub word: Option<WordSize>,
 }
 
-// #[derive(Debug, PartialEq, Clone)]
-// pub enum Unit {
-//     Kilometer,
-//     Meter,
-//     Milimeter,
-// }
-
 #[derive(Debug, PartialEq)]
 pub enum TokenType {
     Number,
     Op,
-    // Unit,
+    Unit,
+    Convert,
     OpenP,
     CloseP,
     Literal,
     Eq,
+    Comma,
     Invalid,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Token {
     Number(Rational),
     Op(OpType),
-    // Unit(Unit),
+    Unit(Box<Unit>),
+    /// `in` or `to`, converts the value on the left to the unit on the right.
+    Convert,
     OpenP,
     CloseP,
     Literal(String),
     Eq,
+    Comma,
     Invalid,
 }
 
@@ -45,11 +62,13 @@ impl Token {
         match self {
             Token::Number(_) => TokenType::Number,
             Token::Op(_) => TokenType::Op,
-            // Token::Unit(_) => TokenType::Unit,
+            Token::Unit(_) => TokenType::Unit,
+            Token::Convert => TokenType::Convert,
             Token::OpenP => TokenType::OpenP,
             Token::CloseP => TokenType::CloseP,
             Token::Literal(_) => TokenType::Literal,
             Token::Eq => TokenType::Eq,
+            Token::Comma => TokenType::Comma,
             Token::Invalid => TokenType::Invalid,
         }
     }
@@ -65,6 +84,41 @@ impl Token {
     }
 }
 
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(x) => write!(f, "{x}"),
+            Token::Op(x) => write!(f, "{x}"),
+            Token::Unit(x) => write!(f, "{x}"),
+            Token::Convert => write!(f, "in"),
+            Token::OpenP => write!(f, "("),
+            Token::CloseP => write!(f, ")"),
+            Token::Literal(x) => write!(f, "{x}"),
+            Token::Eq => write!(f, "="),
+            Token::Comma => write!(f, ","),
+            Token::Invalid => write!(f, "<invalid>"),
+        }
+    }
+}
+
+/// A token together with where it came from in the input.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+    pub lexeme: String,
+}
+
+impl SpannedToken {
+    pub fn new(token: Token, span: Span, lexeme: impl Into<String>) -> Self {
+        Self {
+            token,
+            span,
+            lexeme: lexeme.into(),
+        }
+    }
+}
+
 pub trait DbgDisplay {
     fn dbg(&self) -> Result<String, std::fmt::Error>;
 }
@@ -75,96 +129,185 @@ impl DbgDisplay for Vec<Token> {
         for i in 0..self.len() {
             write!(ret, "{:?}\r", self[i])?;
             if i < self.len() - 1 {
-                write!(ret, "\n")?;
+                writeln!(ret)?;
             }
         }
         Ok(ret)
     }
 }
 
-impl DbgDisplay for Vec<(String, Token)> {
+impl DbgDisplay for Vec<SpannedToken> {
     fn dbg(&self) -> Result<String, std::fmt::Error> {
         let mut ret = String::new();
         for i in 0..self.len() {
-            write!(ret, "{} {:?}\r", self[i].0, self[i].1)?;
+            let SpannedToken {
+                token,
+                span,
+                lexeme,
+            } = &self[i];
+            write!(ret, "{}..{} {lexeme} {token:?}\r", span.start, span.end)?;
             if i < self.len() - 1 {
-                write!(ret, "\n")?;
+                writeln!(ret)?;
             }
         }
         Ok(ret)
     }
 }
 
-fn split<'a>(mut s: &'a str) -> Vec<&'a str> {
-    let pats = [' ', '*', '/', '+', '-', '^', '(', ')', '%', '='];
+// Whether `x` is the start of a number with an exponent whose sign follows,
+// the `1e` of `1e-5`.
+fn is_exponent_start(x: &str) -> bool {
+    let Some(mantissa) = x.strip_suffix(['e', 'E']) else {
+        return false;
+    };
+    mantissa.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && mantissa
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == '_')
+}
+
+fn split(s: &str) -> Vec<(Span, &str)> {
+    let pats = [
+        ' ', '*', '/', '+', '-', '^', '(', ')', '%', '=', ',', '&', '|', '~', '<', '>', '!',
+    ];
     let mut ret = Vec::new();
-    // println!("Splitting: {s:?}");
-    loop {
-        let mut idxs = Vec::new();
-        for i in pats {
-            // println!("Looking for: {i:?} in {s:?}");
-            if let Some(x) = s.find(i) {
-                idxs.push(x);
-            }
+    let mut start = 0;
+    for (x, c) in s.char_indices() {
+        if !pats.contains(&c) {
+            continue;
         }
-        // println!("Found: {idxs:?}");
-        if idxs.is_empty() {
-            // println!("Left: {s:?}");
-            if !s.is_empty() {
-                ret.push(s);
-            }
-            break;
-        } else {
-            idxs.sort();
-            let x = idxs.first().unwrap().to_owned();
-            // println!("Found: {x:?} {ln:?}");
-            let to_push = &s[..x];
-            if !to_push.is_empty() {
-                ret.push(to_push);
-                // println!("{to_push:?} {s:?}");
-            }
-            let delim = &s[x..x + 1];
-            // println!("delim: {delim:?}");
-            if delim != " " {
-                ret.push(delim);
-            }
-            s = &s[x + 1..];
+        //The sign of an exponent doesn't end the number
+        if (c == '-' || c == '+')
+            && is_exponent_start(&s[start..x])
+            && s[x + 1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            continue;
         }
+        if start < x {
+            ret.push((Span::new(start, x), &s[start..x]));
+        }
+        if c != ' ' {
+            ret.push((Span::new(x, x + 1), &s[x..x + 1]));
+        }
+        start = x + 1;
+    }
+    if start < s.len() {
+        ret.push((Span::new(start, s.len()), &s[start..]));
     }
     ret
 }
 
 pub fn pre_tokenize(s: &str) -> (&str, TokenizerOptions) {
     let mut ret = TokenizerOptions::default();
-    if let Some(sep_idx) = s.find('#') {
-        for i in s[..sep_idx].chars().into_iter() {
-            match i {
-                'd' => {
-                    ret.debug = true;
-                }
-                'f' => {
-                    ret.as_float = true;
-                }
-                _ => {}
+    let Some(sep_idx) = s.find('#') else {
+        return (s, ret);
+    };
+    let mut mode = None;
+    let mut digits: Option<u32> = None;
+    let mut rounding = Rounding::default();
+    //Signedness and bits of a word size while its digits are being read
+    let mut word: Option<(bool, u32)> = None;
+    for i in s[..sep_idx].chars() {
+        if let (Some((signed, bits)), Some(digit)) = (word, i.to_digit(10)) {
+            word = Some((signed, bits.saturating_mul(10).saturating_add(digit)));
+            continue;
+        }
+        if let Some((signed, bits)) = word.take() {
+            ret.word = WordSize::new(bits, signed);
+        }
+        match i {
+            'd' => {
+                ret.debug = true;
+            }
+            'p' => ret.echo = true,
+            'i' | 'w' => word = Some((i == 'i', 0)),
+            '0'..='9' => {
+                let digit = i.to_digit(10).unwrap_or_default();
+                digits = Some(
+                    digits
+                        .unwrap_or_default()
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
             }
+            'u' => rounding = Rounding::HalfUp,
+            'z' => rounding = Rounding::TowardZero,
+            '_' => rounding = Rounding::Floor,
+            '^' => rounding = Rounding::Ceil,
+            'q' | 'f' | 'm' | 's' | 'e' | 'g' | 'r' | 'x' | 'o' | 'b' => mode = Some(i),
+            _ => {}
         }
-        (&s[sep_idx + 1..], ret)
-    } else {
-        (s, ret)
     }
+    if let Some((signed, bits)) = word {
+        ret.word = WordSize::new(bits, signed);
+    }
+    //Long runs of digits would take forever to show
+    let digits = digits.map(|x| x.min(MAX_PLACES));
+    let significant = digits.unwrap_or(DEFAULT_DIGITS);
+    ret.format = match (mode, digits) {
+        (Some('q'), _) => Some(Format::Exact),
+        (Some('f'), _) => Some(Format::Float),
+        (Some('m'), _) => Some(Format::Mixed),
+        (Some('s'), _) => Some(Format::Scientific(significant)),
+        (Some('e'), _) => Some(Format::Engineering(significant)),
+        (Some('g'), _) => Some(Format::Significant(significant)),
+        (Some('r'), _) => Some(Format::Repeating),
+        (Some('x'), _) => Some(Format::Radix(16)),
+        (Some('o'), _) => Some(Format::Radix(8)),
+        (Some('b'), _) => Some(Format::Radix(2)),
+        (_, Some(places)) => Some(Format::Fixed(places, rounding)),
+        _ => None,
+    };
+    (&s[sep_idx + 1..], ret)
+}
+
+pub fn dbg_tokenize(s: &str) -> String {
+    tokenize(s)
+        .dbg()
+        .unwrap_or("Failed to tokenize".to_string())
 }
 
-pub fn dbg_tokenize(s: &str) -> Vec<(String, Token)> {
-    split(s)
-        .iter()
-        .map(|x| (x.to_string(), _tokenize(x)))
-        .collect()
-    // .dbg()
-    // .unwrap_or("Failed to tokenize".to_string())
+// Operators made of two of the characters `split` splits on.
+const PAIRS: [&str; 8] = ["<<", ">>", "//", "==", "!=", "<=", ">=", "!!"];
+
+pub fn tokenize(s: &str) -> Vec<SpannedToken> {
+    let mut ret = Vec::new();
+    for (span, x) in split(s) {
+        //An imaginary number, `2i` is read as `2 i`
+        if let Some(n) = x
+            .strip_suffix(['i', 'I'])
+            .filter(|n| n.parse::<Rational>().is_ok())
+        {
+            let end = span.start + n.len();
+            ret.push(SpannedToken::new(
+                _tokenize(n),
+                Span::new(span.start, end),
+                n,
+            ));
+            ret.push(SpannedToken::new(
+                Token::Literal(String::from("i")),
+                Span::new(end, span.end),
+                &x[n.len()..],
+            ));
+            continue;
+        }
+        //Operators like `<<` and `<=` are split into single characters
+        if let Some(last) = ret.last_mut().filter(|last| {
+            let pair = format!("{}{x}", last.lexeme);
+            PAIRS.contains(&pair.as_str()) && last.span.end == span.start
+        }) {
+            let lexeme = format!("{}{x}", last.lexeme);
+            *last = SpannedToken::new(_tokenize(&lexeme), last.span.to(span), lexeme);
+            continue;
+        }
+        ret.push(SpannedToken::new(_tokenize(x), span, x));
+    }
+    ret
 }
 
-pub fn tokenize(s: &str) -> Vec<Token> {
-    split(s).into_iter().map(_tokenize).collect()
+fn is_literal(x: &str) -> bool {
+    x.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && x.chars().all(|c| c.is_alphanumeric() || c == '_')
 }
 
 fn _tokenize(x: &str) -> Token {
@@ -176,18 +319,41 @@ fn _tokenize(x: &str) -> Token {
         "+" => Token::Op(OpType::Add),
         "-" => Token::Op(OpType::Sub),
         "^" => Token::Op(OpType::Pow),
-        "sqrt" | "rt" | "root" => Token::Op(OpType::Root),
+        "sqrt" | "rt" => Token::Op(OpType::Root),
         "log" | "lg" => Token::Op(OpType::Log),
         "(" => Token::OpenP,
         ")" => Token::CloseP,
         "%" => Token::Op(OpType::Mod),
+        "&" => Token::Op(OpType::BitAnd),
+        "|" => Token::Op(OpType::BitOr),
+        "xor" => Token::Op(OpType::Xor),
+        "~" => Token::Op(OpType::BitNot),
+        "<<" => Token::Op(OpType::Shl),
+        ">>" => Token::Op(OpType::Shr),
+        "//" => Token::Op(OpType::IntDiv),
+        "gcd" => Token::Op(OpType::Gcd),
+        "lcm" => Token::Op(OpType::Lcm),
+        "==" => Token::Op(OpType::Equal),
+        "!=" => Token::Op(OpType::NotEqual),
+        "<" => Token::Op(OpType::Less),
+        "<=" => Token::Op(OpType::LessEqual),
+        ">" => Token::Op(OpType::Greater),
+        ">=" => Token::Op(OpType::GreaterEqual),
+        "and" => Token::Op(OpType::And),
+        "or" => Token::Op(OpType::Or),
+        "not" => Token::Op(OpType::Not),
+        "!" => Token::Op(OpType::Factorial),
+        "!!" => Token::Op(OpType::DoubleFactorial),
         "=" => Token::Eq,
+        "," => Token::Comma,
 
         y => {
-            if let Ok(o) = y.parse::<f64>() {
-                Token::Number(Rational::from(o))
-            } else {
+            if let Ok(o) = y.parse::<Rational>() {
+                Token::Number(o)
+            } else if is_literal(y) {
                 Token::Literal(y.to_owned())
+            } else {
+                Token::Invalid
             }
         }
     }
@@ -197,6 +363,42 @@ mod test {
     #[cfg(test)]
     use crate::tokenizer::*;
 
+    #[cfg(test)]
+    fn split(s: &str) -> Vec<&str> {
+        super::split(s).into_iter().map(|x| x.1).collect()
+    }
+
+    #[cfg(test)]
+    fn tokenize(s: &str) -> Vec<Token> {
+        super::tokenize(s).into_iter().map(|x| x.token).collect()
+    }
+
+    #[test]
+    fn test_pre_tokenize() {
+        let (line, opts) = pre_tokenize("12u#1/8");
+        assert_eq!(line, "1/8");
+        assert_eq!(opts.format, Some(Format::Fixed(12, Rounding::HalfUp)));
+        assert_eq!(pre_tokenize("s3#1").1.format, Some(Format::Scientific(3)));
+        assert_eq!(pre_tokenize("dx#1").1.format, Some(Format::Radix(16)));
+        assert!(pre_tokenize("d#1").1.debug);
+        assert!(pre_tokenize("pf#1").1.echo);
+        assert_eq!(
+            pre_tokenize("99999999#1").1.format,
+            Some(Format::Fixed(1000, Rounding::HalfEven))
+        );
+        assert_eq!(
+            pre_tokenize("s99999999999#1").1.format,
+            Some(Format::Scientific(1000))
+        );
+        let opts = pre_tokenize("i16x#1").1;
+        assert_eq!(opts.word, WordSize::new(16, true));
+        assert_eq!(opts.format, Some(Format::Radix(16)));
+        assert_eq!(pre_tokenize("w8#1").1.word, WordSize::new(8, false));
+        let (line, opts) = pre_tokenize("1/8");
+        assert_eq!(line, "1/8");
+        assert_eq!(opts.format, None);
+    }
+
     #[test]
     fn test_split() {
         assert_eq!(split("a b"), vec!["a", "b"]);
@@ -209,6 +411,10 @@ mod test {
         );
         assert_eq!(split("log a b"), vec!["log", "a", "b"]);
         assert_eq!(split("sqrt a ^ b"), vec!["sqrt", "a", "^", "b"]);
+        assert_eq!(split("1e-5-2"), vec!["1e-5", "-", "2"]);
+        assert_eq!(split("6.02E+23"), vec!["6.02E+23"]);
+        assert_eq!(split("0x1e-5"), vec!["0x1e", "-", "5"]);
+        assert_eq!(split("e-1"), vec!["e", "-", "1"]);
     }
 
     #[test]
@@ -222,7 +428,7 @@ mod test {
         assert_eq!(_tokenize("log"), Token::Op(OpType::Log));
         assert_eq!(_tokenize("123"), Token::Number(123.0.into()));
         assert_eq!(_tokenize("123.0"), Token::Number(123.0.into()));
-        assert_eq!(_tokenize(".01"), Token::Number(Rational::new(1.0, 100.0)));
+        assert_eq!(_tokenize(".01"), Token::Number(Rational::new(1, 100)));
     }
 
     #[test]
@@ -267,4 +473,105 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_number_literals() {
+        assert_eq!(
+            tokenize("0x1F + 1_000"),
+            vec![
+                Token::Number(31.0.into()),
+                Token::Op(OpType::Add),
+                Token::Number(1000.0.into())
+            ]
+        );
+        assert_eq!(
+            tokenize("1e-5"),
+            vec![Token::Number(Rational::new(1, 100000))]
+        );
+        assert_eq!(tokenize("0.1"), vec![Token::Number(Rational::new(1, 10))]);
+        assert_eq!(tokenize("1__0"), vec![Token::Invalid]);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(
+            tokenize("a<=1 != b<2"),
+            vec![
+                Token::Literal(String::from("a")),
+                Token::Op(OpType::LessEqual),
+                Token::Number(1.0.into()),
+                Token::Op(OpType::NotEqual),
+                Token::Literal(String::from("b")),
+                Token::Op(OpType::Less),
+                Token::Number(2.0.into())
+            ]
+        );
+        assert_eq!(
+            tokenize("x = = 1 and not y"),
+            vec![
+                Token::Literal(String::from("x")),
+                Token::Eq,
+                Token::Eq,
+                Token::Number(1.0.into()),
+                Token::Op(OpType::And),
+                Token::Op(OpType::Not),
+                Token::Literal(String::from("y"))
+            ]
+        );
+        assert_eq!(
+            tokenize("3!!"),
+            vec![
+                Token::Number(3.0.into()),
+                Token::Op(OpType::DoubleFactorial)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        assert_eq!(
+            super::split("12 +  sqrt(3)"),
+            vec![
+                (Span::new(0, 2), "12"),
+                (Span::new(3, 4), "+"),
+                (Span::new(6, 10), "sqrt"),
+                (Span::new(10, 11), "("),
+                (Span::new(11, 12), "3"),
+                (Span::new(12, 13), ")"),
+            ]
+        );
+        assert_eq!(
+            super::tokenize("1-2.5i"),
+            vec![
+                SpannedToken::new(Token::Number(1.0.into()), Span::new(0, 1), "1"),
+                SpannedToken::new(Token::Op(OpType::Sub), Span::new(1, 2), "-"),
+                SpannedToken::new(Token::Number(2.5.into()), Span::new(2, 5), "2.5"),
+                SpannedToken::new(Token::Literal(String::from("i")), Span::new(5, 6), "i"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(_tokenize("abc_1"), Token::Literal(String::from("abc_1")));
+        assert_eq!(_tokenize("$"), Token::Invalid);
+        assert_eq!(_tokenize("2a"), Token::Invalid);
+        assert_eq!(_tokenize("1.2.3"), Token::Invalid);
+    }
+
+    #[test]
+    fn test_dbg_tokenize() {
+        assert_eq!(
+            dbg_tokenize("2 *x"),
+            "0..1 2 Number(2)\r\n2..3 * Op(Mul)\r\n3..4 x Literal(\"x\")\r"
+        );
+        assert_eq!(
+            super::tokenize("Sqrt"),
+            vec![SpannedToken::new(
+                Token::Op(OpType::Root),
+                Span::new(0, 4),
+                "Sqrt"
+            )]
+        );
+    }
 }
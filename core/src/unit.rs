@@ -0,0 +1,266 @@
+use std::fmt::Display;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::rational::Rational;
+
+// Symbols of the SI base units, in the order of `Dimension`.
+const BASE: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+// Derived units a dimension is shown as when it matches exactly.
+const DERIVED: [(&str, [i32; 7]); 7] = [
+    ("N", [1, 1, -2, 0, 0, 0, 0]),
+    ("J", [2, 1, -2, 0, 0, 0, 0]),
+    ("W", [2, 1, -3, 0, 0, 0, 0]),
+    ("Pa", [-1, 1, -2, 0, 0, 0, 0]),
+    ("Hz", [0, 0, -1, 0, 0, 0, 0]),
+    ("C", [0, 0, 1, 1, 0, 0, 0]),
+    ("V", [2, 1, -3, -1, 0, 0, 0]),
+];
+
+const PREFIXES: [(&str, i32); 21] = [
+    ("Y", 24),
+    ("Z", 21),
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("h", 2),
+    ("da", 1),
+    ("d", -1),
+    ("c", -2),
+    ("m", -3),
+    ("u", -6),
+    ("µ", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+    ("a", -18),
+    ("z", -21),
+    ("y", -24),
+];
+
+/// Exponents of the SI base dimensions: length, mass, time, current,
+/// temperature, amount of substance and luminous intensity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Dimension(pub [i32; 7]);
+
+impl Dimension {
+    pub fn is_none(&self) -> bool {
+        self.0 == [0; 7]
+    }
+
+    fn map(&self, f: impl Fn(i32) -> Option<i32>) -> Option<Dimension> {
+        let mut out = [0; 7];
+        for (o, x) in out.iter_mut().zip(self.0) {
+            *o = f(x)?;
+        }
+        Some(Dimension(out))
+    }
+
+    fn zip(&self, other: &Dimension, f: impl Fn(i32, i32) -> i32) -> Dimension {
+        let mut out = self.0;
+        for (o, x) in out.iter_mut().zip(other.0) {
+            *o = f(*o, x);
+        }
+        Dimension(out)
+    }
+
+    pub fn mul(&self, other: &Dimension) -> Dimension {
+        self.zip(other, |a, b| a + b)
+    }
+
+    pub fn div(&self, other: &Dimension) -> Dimension {
+        self.zip(other, |a, b| a - b)
+    }
+
+    /// `None` when some exponent wouldn't be a whole number, `m^(1/2)`.
+    pub fn pow(&self, x: &Rational) -> Option<Dimension> {
+        self.map(|e| {
+            let y = Rational::from(BigInt::from(e)) * x.clone();
+            y.is_integer().then(|| y.numer().to_i32()).flatten()
+        })
+    }
+
+    pub fn root(&self, n: u32) -> Option<Dimension> {
+        let n = n as i32;
+        self.map(|e| (e % n == 0).then_some(e / n))
+    }
+
+    // `kg*m^2`, exponents are shown without their sign.
+    fn fmt_product(factors: &[(&str, i32)]) -> String {
+        factors
+            .iter()
+            .map(|(name, e)| match e.abs() {
+                1 => name.to_string(),
+                e => format!("{name}^{e}"),
+            })
+            .collect::<Vec<_>>()
+            .join("*")
+    }
+}
+
+impl Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_none() {
+            return f.write_str("1");
+        }
+        if let Some((name, _)) = DERIVED.iter().find(|x| x.1 == self.0) {
+            return f.write_str(name);
+        }
+        let factors: Vec<(&str, i32)> = BASE.into_iter().zip(self.0).filter(|x| x.1 != 0).collect();
+        let num: Vec<_> = factors.iter().copied().filter(|x| x.1 > 0).collect();
+        let den: Vec<_> = factors.iter().copied().filter(|x| x.1 < 0).collect();
+        match (num.is_empty(), den.len()) {
+            (_, 0) => f.write_str(&Dimension::fmt_product(&num)),
+            (true, _) => {
+                let s: Vec<String> = den.iter().map(|(n, e)| format!("{n}^{e}")).collect();
+                f.write_str(&s.join("*"))
+            }
+            (false, 1) => write!(
+                f,
+                "{}/{}",
+                Dimension::fmt_product(&num),
+                Dimension::fmt_product(&den)
+            ),
+            (false, _) => write!(
+                f,
+                "{}/({})",
+                Dimension::fmt_product(&num),
+                Dimension::fmt_product(&den)
+            ),
+        }
+    }
+}
+
+/// A unit of measurement: its size in SI base units and its dimension.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unit {
+    pub name: String,
+    pub factor: Rational,
+    pub dim: Dimension,
+}
+
+// Units known by name, the last field tells whether SI prefixes apply.
+fn table() -> Vec<(&'static str, Rational, [i32; 7], bool)> {
+    let r = |p: i64, q: i64| Rational::new(p, q);
+    let pound = r(45359237, 100000000);
+    let mile = r(201168, 125);
+    vec![
+        //SI base units, the gram so that `kg` is a prefixed unit
+        ("m", r(1, 1), [1, 0, 0, 0, 0, 0, 0], true),
+        ("g", r(1, 1000), [0, 1, 0, 0, 0, 0, 0], true),
+        ("s", r(1, 1), [0, 0, 1, 0, 0, 0, 0], true),
+        ("A", r(1, 1), [0, 0, 0, 1, 0, 0, 0], true),
+        ("K", r(1, 1), [0, 0, 0, 0, 1, 0, 0], true),
+        ("mol", r(1, 1), [0, 0, 0, 0, 0, 1, 0], true),
+        ("cd", r(1, 1), [0, 0, 0, 0, 0, 0, 1], true),
+        //Derived
+        ("N", r(1, 1), DERIVED[0].1, true),
+        ("J", r(1, 1), DERIVED[1].1, true),
+        ("W", r(1, 1), DERIVED[2].1, true),
+        ("Pa", r(1, 1), DERIVED[3].1, true),
+        ("Hz", r(1, 1), DERIVED[4].1, true),
+        ("C", r(1, 1), DERIVED[5].1, true),
+        ("V", r(1, 1), DERIVED[6].1, true),
+        ("L", r(1, 1000), [3, 0, 0, 0, 0, 0, 0], true),
+        //Time
+        ("min", r(60, 1), [0, 0, 1, 0, 0, 0, 0], false),
+        ("h", r(3600, 1), [0, 0, 1, 0, 0, 0, 0], false),
+        ("day", r(86400, 1), [0, 0, 1, 0, 0, 0, 0], false),
+        //Imperial
+        ("in", r(127, 5000), [1, 0, 0, 0, 0, 0, 0], false),
+        ("ft", r(381, 1250), [1, 0, 0, 0, 0, 0, 0], false),
+        ("yd", r(1143, 1250), [1, 0, 0, 0, 0, 0, 0], false),
+        ("mi", mile.clone(), [1, 0, 0, 0, 0, 0, 0], false),
+        ("mph", mile / r(3600, 1), [1, 0, -1, 0, 0, 0, 0], false),
+        ("lb", pound.clone(), [0, 1, 0, 0, 0, 0, 0], false),
+        ("oz", pound / r(16, 1), [0, 1, 0, 0, 0, 0, 0], false),
+        (
+            "gal",
+            r(473176473, 125000000000),
+            [3, 0, 0, 0, 0, 0, 0],
+            false,
+        ),
+    ]
+}
+
+impl Unit {
+    /// Looks up a unit symbol like `km`, `Pa` or `ft`, case matters.
+    pub fn parse(name: &str) -> Option<Unit> {
+        let table = table();
+        let unit = |(symbol, factor, dim, _): &(&str, Rational, [i32; 7], bool)| Unit {
+            name: symbol.to_string(),
+            factor: factor.clone(),
+            dim: Dimension(*dim),
+        };
+        //`min` is minutes, not milli-inches
+        if let Some(x) = table.iter().find(|x| x.0 == name) {
+            return Some(unit(x));
+        }
+        for (prefix, e) in PREFIXES {
+            let Some(rest) = name.strip_prefix(prefix) else {
+                continue;
+            };
+            if let Some(x) = table.iter().find(|x| x.0 == rest && x.3) {
+                let ten = Rational::from(BigInt::from(10));
+                let scale = ten.pow_int(&BigInt::from(e));
+                return Some(Unit {
+                    name: name.to_owned(),
+                    factor: x.1.clone() * scale,
+                    dim: Dimension(x.2),
+                });
+            }
+        }
+        None
+    }
+}
+
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+mod test {
+    #[cfg(test)]
+    use super::{Dimension, Unit};
+    #[cfg(test)]
+    use crate::rational::Rational;
+
+    #[test]
+    fn test_parse() {
+        let km = Unit::parse("km").unwrap();
+        assert_eq!(km.factor, Rational::new(1000, 1));
+        assert_eq!(km.dim, Dimension([1, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(Unit::parse("kg").unwrap().factor, Rational::one());
+        assert_eq!(Unit::parse("mm").unwrap().factor, Rational::new(1, 1000));
+        assert_eq!(Unit::parse("hPa").unwrap().factor, Rational::new(100, 1));
+        assert_eq!(Unit::parse("min").unwrap().factor, Rational::new(60, 1));
+        assert_eq!(Unit::parse("ft").unwrap().factor, Rational::new(381, 1250));
+        assert_eq!(Unit::parse("kft"), None);
+        assert_eq!(Unit::parse("x"), None);
+        assert_eq!(Unit::parse("M"), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let d = |x: [i32; 7]| Dimension(x).to_string();
+        assert_eq!(d([1, 0, -1, 0, 0, 0, 0]), "m/s");
+        assert_eq!(d([1, 1, -2, 0, 0, 0, 0]), "N");
+        assert_eq!(d([2, 1, -3, 0, 0, 0, 0]), "W");
+        assert_eq!(d([1, 0, -2, 0, 0, 0, 0]), "m/s^2");
+        assert_eq!(d([3, 0, 0, 0, 0, 0, 0]), "m^3");
+        assert_eq!(d([1, 1, -2, -1, 0, 0, 0]), "m*kg/(s^2*A)");
+        assert_eq!(d([-1, 0, 0, 0, 0, 0, 0]), "m^-1");
+        assert_eq!(
+            Dimension([2, 0, 0, 0, 0, 0, 0]).pow(&Rational::new(1, 2)),
+            Some(Dimension([1, 0, 0, 0, 0, 0, 0]))
+        );
+        assert_eq!(Dimension([1, 0, 0, 0, 0, 0, 0]).root(2), None);
+    }
+}